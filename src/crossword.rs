@@ -1,22 +1,251 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use log::info;
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
 
-#[derive(Serialize, Deserialize, Debug)]
+pub mod cell_value;
+pub mod clue_list;
+pub mod enumeration;
+pub mod grid;
+pub mod ipuz;
+pub mod jpz;
+pub mod pdf;
+pub mod puz;
+pub mod solution;
+pub mod style;
+pub mod svg;
+pub mod text;
+
+pub use cell_value::{CellValue, CellValueError, MAX_CELL_LENGTH};
+pub use clue_list::{ClueList, ClueListError};
+pub use enumeration::{BreakKind, Enumeration, EnumerationError, WordBreak};
+pub use grid::{Entry, Grid};
+pub use ipuz::{Ipuz, IpuzError};
+pub use jpz::{Jpz, JpzError};
+pub use pdf::{PaperSize, PdfOptions, PrintedLetters};
+pub use puz::{Puz, PuzError};
+pub use solution::{CheckResult, Solution, SolutionInput, Target};
+pub use style::{CellStyle, StyledSquare};
+pub use svg::SvgOptions;
+pub use text::TextOptions;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Direction {
     Across,
     Down
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "CrosswordData")]
 pub struct Crossword {
+    #[serde(serialize_with = "serialise_in_clue_order")]
     across: HashMap<String, Clue>,
+    #[serde(serialize_with = "serialise_in_clue_order")]
     down: HashMap<String, Clue>,
+    #[serde(default, skip_serializing_if = "PuzzleMetadata::is_empty")]
+    metadata: PuzzleMetadata,
+    /// Circled, shaded and barred squares, row by row.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    styles: Vec<StyledSquare>,
+    /// Where each square sits in the clues that run through it, so crossing
+    /// clues can be kept in step without searching every clue.
+    #[serde(skip)]
+    index: HashMap<(usize, usize), Vec<CellRef>>,
+}
+
+/// A square's position within one clue.
+#[derive(Debug, Clone)]
+struct CellRef {
+    direction: Direction,
+    clue: String,
+    position: usize,
+}
+
+/// The stored form of a crossword, before the index is built.
+#[derive(Deserialize)]
+struct CrosswordData {
+    across: HashMap<String, Clue>,
+    down: HashMap<String, Clue>,
+    #[serde(default)]
+    metadata: PuzzleMetadata,
+    #[serde(default)]
+    styles: Vec<StyledSquare>,
+}
+
+impl From<CrosswordData> for Crossword {
+    fn from(data: CrosswordData) -> Self {
+        let mut crossword = Self::new(data.across, data.down, data.metadata);
+        crossword.styles = data.styles;
+        crossword
+    }
+}
+
+/// The widest or tallest grid that will be accepted.
+pub const MAX_GRID_SIZE: usize = 50;
+
+/// The most clues a puzzle can have. Every entry is at least two squares
+/// long, so even the largest grid can't hold more.
+pub const MAX_CLUES: usize = MAX_GRID_SIZE * MAX_GRID_SIZE;
+
+/// A crossword with squares beyond the largest allowed grid, so it can't
+/// be laid out. `x` and `y` are the furthest square in each direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridSizeError {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl std::fmt::Display for GridSizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the grid reaches ({}, {}), outside the largest allowed grid of {MAX_GRID_SIZE} by {MAX_GRID_SIZE}", self.x, self.y)
+    }
+}
+
+impl std::error::Error for GridSizeError {}
+
+/// Something wrong with a crossword, pointing at the clue and square
+/// responsible where there is one.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clue: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell: Option<(usize, usize)>,
+    pub reason: String,
+}
+
+impl ValidationError {
+    fn new(clue: Option<&str>, cell: Option<(usize, usize)>, reason: String) -> Self {
+        ValidationError { clue: clue.map(|x| x.to_string()), cell, reason }
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.clue, self.cell) {
+            (Some(clue), Some((x, y))) => write!(f, "{clue} at ({x}, {y}): {}", self.reason),
+            (Some(clue), None) => write!(f, "{clue}: {}", self.reason),
+            (None, Some((x, y))) => write!(f, "({x}, {y}): {}", self.reason),
+            (None, None) => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+/// Writes clues as `1a, 2a, 10a` rather than in whatever order the map holds
+/// them, so saved puzzles and API responses are stable.
+fn serialise_in_clue_order<S>(clues: &HashMap<String, Clue>, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+    let mut ids: Vec<&String> = clues.keys().collect();
+    ids.sort_by_key(|id| clue_order(id));
+    let mut map = serializer.serialize_map(Some(ids.len()))?;
+    for id in ids {
+        map.serialize_entry(id, &clues[id])?;
+    }
+    map.end()
+}
+
+/// Sorts by the number a clue id starts with, then by the rest of the id.
+/// Ids that don't start with a number go last.
+fn clue_order(id: &str) -> (usize, &str) {
+    let digits = id.chars().take_while(|c| c.is_ascii_digit()).count();
+    (id[..digits].parse().unwrap_or(usize::MAX), &id[digits..])
+}
+
+/// Details about where a puzzle came from. Every field is optional, so
+/// crosswords saved before these existed still load.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct PuzzleMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publication: Option<String>,
+    /// An ISO 8601 date, e.g. `2024-03-01`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publication_date: Option<String>,
+    /// From 1 (easiest) to 5.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub copyright: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Where the scan the puzzle was made from can be found, either an
+    /// http(s) URL or a relative path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_image: Option<String>,
+}
+
+const MAX_METADATA_LEN: usize = 200;
+const MAX_NOTES_LEN: usize = 4000;
+
+impl PuzzleMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Checks every field, returning a message for each one that is wrong.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        let short_fields = [
+            ("author", &self.author),
+            ("publication", &self.publication),
+            ("copyright", &self.copyright),
+            ("source_image", &self.source_image),
+        ];
+        for (field, value) in short_fields {
+            if value.as_ref().is_some_and(|x| x.chars().count() > MAX_METADATA_LEN) {
+                errors.push(format!("{field} must be at most {MAX_METADATA_LEN} characters"));
+            }
+        }
+        if self.notes.as_ref().is_some_and(|x| x.chars().count() > MAX_NOTES_LEN) {
+            errors.push(format!("notes must be at most {MAX_NOTES_LEN} characters"));
+        }
+
+        if let Some(date) = &self.publication_date {
+            if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+                errors.push(format!("publication_date {date} is not a date like 2024-03-01"));
+            }
+        }
+
+        if let Some(difficulty) = self.difficulty {
+            if !(1..=5).contains(&difficulty) {
+                errors.push(format!("difficulty must be between 1 and 5, not {difficulty}"));
+            }
+        }
+
+        if let Some(source) = &self.source_image {
+            let is_url = source.starts_with("https://") || source.starts_with("http://");
+            // a ':' before the first '/' is a scheme, such as `javascript:`.
+            let has_scheme = source.split('/').next().is_some_and(|x| x.contains(':'));
+            let is_relative = !has_scheme && !source.starts_with('/') && !source.contains('\\') && !source.split('/').any(|x| x == "..");
+            if !is_url && !is_relative {
+                errors.push("source_image must be an http(s) URL or a relative path".to_string());
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
 }
 
 impl Crossword {
 
+    fn new(across: HashMap<String, Clue>, down: HashMap<String, Clue>, metadata: PuzzleMetadata) -> Self {
+        let mut index: HashMap<(usize, usize), Vec<CellRef>> = HashMap::new();
+        for (direction, clues) in [(Direction::Across, &across), (Direction::Down, &down)] {
+            for (id, clue) in clues {
+                for (position, cell) in clue.cells.iter().enumerate() {
+                    index.entry((cell.x, cell.y))
+                        .or_default()
+                        .push(CellRef { direction, clue: id.clone(), position });
+                }
+            }
+        }
+        Self { across, down, metadata, styles: Vec::new(), index }
+    }
+
     pub fn demo_grid() -> Self {
 
         let clue_1 = Clue::new(8, (0,0), "For all the money that e'er I had", Direction::Across);
@@ -35,59 +264,462 @@ impl Crossword {
             ("2d".to_string(), clue_4)
             ]);
 
-        Self{across, down}
+        Self::new(across, down, PuzzleMetadata::default())
+
+    }
+
+    /// Lays the clues out on a grid just big enough to hold them. Squares
+    /// that aren't part of any clue are blocks. Fails if the grid would be
+    /// bigger than `MAX_GRID_SIZE` either way.
+    pub fn grid(&self) -> Result<Grid, GridSizeError> {
+        let cells = || self.across.values().chain(self.down.values()).flat_map(|clue| clue.cells.iter());
+        let (width, height) = match (cells().map(|cell| cell.x).max(), cells().map(|cell| cell.y).max()) {
+            (Some(x), Some(y)) if x >= MAX_GRID_SIZE || y >= MAX_GRID_SIZE => return Err(GridSizeError { x, y }),
+            (Some(x), Some(y)) => (x + 1, y + 1),
+            _ => (0, 0),
+        };
+
+        let mut grid = Grid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                grid.set_block(x, y, true);
+            }
+        }
+        for cell in cells() {
+            grid.set_block(cell.x, cell.y, false);
+        }
+        // a square shared by two clues may only have been filled in on one of them.
+        for cell in cells().filter(|cell| !cell.c.is_blank()) {
+            grid.set_letter(cell.x, cell.y, cell.c.clone());
+        }
+        for square in &self.styles {
+            grid.set_style(square.x, square.y, square.style);
+        }
+        Ok(grid)
+    }
+
+    /// Makes a crossword with a clue, without a hint, for every entry in the
+    /// grid, keeping the grid's styles.
+    pub fn from_grid(grid: &Grid) -> Self {
+        let mut across = HashMap::new();
+        let mut down = HashMap::new();
+        for entry in grid.entries() {
+            let cells = entry.cells()
+                .map(|(x, y)| Cell { x, y, c: grid.letter(x, y).cloned().unwrap_or_default() })
+                .collect();
+            let clue = Clue { hint: String::new(), cells, enumeration: None, linked: Vec::new() };
+            match entry.direction {
+                Direction::Across => across.insert(entry.id(), clue),
+                Direction::Down => down.insert(entry.id(), clue),
+            };
+        }
+        let mut crossword = Self::new(across, down, PuzzleMetadata::default());
+        crossword.styles = (0..grid.height())
+            .flat_map(|y| (0..grid.width()).map(move |x| (x, y)))
+            .map(|(x, y)| StyledSquare { x, y, style: grid.style(x, y) })
+            .filter(|square| !square.style.is_plain())
+            .collect();
+        crossword
+    }
+
+    /// Rebuilds the clues from the grid with standard numbering. A hint,
+    /// enumeration and links are kept when an existing clue starts on the
+    /// same square in the same direction.
+    pub fn renumbered(&self) -> Result<Self, GridSizeError> {
+        let mut crossword = Self::from_grid(&self.grid()?);
+        crossword.metadata = self.metadata.clone();
+
+        let starts = |crossword: &Self| -> HashMap<(usize, usize, Direction), String> {
+            [(Direction::Across, &crossword.across), (Direction::Down, &crossword.down)]
+                .into_iter()
+                .flat_map(|(direction, clues)| clues.iter().filter_map(move |(id, clue)| {
+                    clue.cells.first().map(|start| ((start.x, start.y, direction), id.clone()))
+                }))
+                .collect()
+        };
+        let old_ids = starts(self);
+        let new_ids: HashMap<String, String> = starts(&crossword).into_iter()
+            .filter_map(|(start, new_id)| old_ids.get(&start).map(|old_id| (old_id.clone(), new_id)))
+            .collect();
+
+        for (direction, clues) in [(Direction::Across, &mut crossword.across), (Direction::Down, &mut crossword.down)] {
+            for clue in clues.values_mut() {
+                let start = &clue.cells[0];
+                let Some(old) = old_ids.get(&(start.x, start.y, direction)).and_then(|id| self.clue(id)) else { continue };
+                clue.hint = old.hint.clone();
+                clue.enumeration = old.enumeration.clone();
+                clue.linked = old.linked.iter().filter_map(|id| new_ids.get(id).cloned()).collect();
+            }
+        }
+        Ok(crossword)
+    }
+
+    /// Checks the clues fit together into a grid: each clue's squares run
+    /// in a straight line in its direction without crossing a bar, crossing
+    /// clues agree on the letters they share, no square holds more than
+    /// `MAX_CELL_LENGTH` letters, the grid isn't too big and every clue has
+    /// a hint, apart from those carrying on a linked clue. Styles must be for
+    /// squares in the grid, and bars must have a square on each side. Linked
+    /// clues must exist and belong to one group, and an enumeration must fill
+    /// its clue's squares. Clues are numbered the usual way, so an id is the
+    /// number on the clue's first square followed by `a` or `d`, and there
+    /// can be at most `MAX_CLUES`. The metadata is checked too.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let clue_count = self.across.len() + self.down.len();
+        if clue_count > MAX_CLUES {
+            return Err(vec![ValidationError::new(None, None, format!("the puzzle has {clue_count} clues but can have at most {MAX_CLUES}"))])
+        }
+
+        let mut errors = Vec::new();
+        let mut letters: HashMap<(usize, usize), (&CellValue, &str)> = HashMap::new();
+
+        let mut styled: HashSet<(usize, usize)> = HashSet::new();
+        let mut bars: HashSet<(usize, usize, Direction)> = HashSet::new();
+        for square in &self.styles {
+            let (x, y) = (square.x, square.y);
+            if !self.index.contains_key(&(x, y)) {
+                errors.push(ValidationError::new(None, Some((x, y)), "the square is styled but isn't in the grid".to_string()));
+                continue;
+            }
+            if !styled.insert((x, y)) {
+                errors.push(ValidationError::new(None, Some((x, y)), "the square is styled more than once".to_string()));
+            }
+            let edges = [
+                (Direction::Across, square.style.bar_right, (x + 1, y), "on the right"),
+                (Direction::Down, square.style.bar_bottom, (x, y + 1), "at the bottom"),
+            ];
+            for (direction, _, next, side) in edges.into_iter().filter(|(_, barred, _, _)| *barred) {
+                match self.index.contains_key(&next) {
+                    true => {
+                        bars.insert((x, y, direction));
+                    },
+                    false => errors.push(ValidationError::new(None, Some((x, y)), format!("the bar {side} has no square on its other side"))),
+                }
+            }
+        }
+
+        let mut linked_from: HashMap<&str, &str> = HashMap::new();
+        for clues in [&self.across, &self.down] {
+            let mut ids: Vec<&String> = clues.keys().collect();
+            ids.sort_by_key(|id| clue_order(id));
+            for id in ids {
+                for linked in &clues[id].linked {
+                    let reason = match self.clue(linked) {
+                        _ if linked == id => "the clue is linked to itself".to_string(),
+                        None => format!("the linked clue {linked} isn't in the puzzle"),
+                        Some(other) if !other.linked.is_empty() => format!("{linked} has linked clues of its own"),
+                        Some(_) => match linked_from.insert(linked, id) {
+                            Some(other) => format!("{linked} is already linked from {other}"),
+                            None => continue,
+                        },
+                    };
+                    errors.push(ValidationError::new(Some(id), None, reason));
+                }
+            }
+        }
+
+        // a grid that's too big is reported square by square below.
+        let numbers: Option<HashMap<(usize, usize, Direction), usize>> = self.grid().ok()
+            .map(|grid| grid.entries().iter().map(|entry| ((entry.x, entry.y, entry.direction), entry.number)).collect());
+
+        for (direction, clues) in [(Direction::Across, &self.across), (Direction::Down, &self.down)] {
+            let (suffix, name) = match direction {
+                Direction::Across => ('a', "across"),
+                Direction::Down => ('d', "down"),
+            };
+            let mut ids: Vec<&String> = clues.keys().collect();
+            ids.sort_by_key(|id| clue_order(id));
+            for id in ids {
+                let clue = &clues[id];
+                let number = id.strip_suffix(suffix).filter(|x| !x.is_empty() && x.chars().all(|c| c.is_ascii_digit()));
+                if number.is_none() {
+                    errors.push(ValidationError::new(Some(id), None, format!("the id should be the clue's number followed by {suffix}")));
+                }
+                if clue.hint.trim().is_empty() && !linked_from.contains_key(id.as_str()) {
+                    errors.push(ValidationError::new(Some(id), None, "the hint is empty".to_string()));
+                }
+                if let Some(enumeration) = &clue.enumeration {
+                    let length = self.answer_length(clue);
+                    if enumeration.length() != length {
+                        errors.push(ValidationError::new(Some(id), None,
+                            format!("the enumeration ({enumeration}) is {} letters but the clue has {length} squares", enumeration.length())));
+                    }
+                }
+                let start = match clue.cells.first() {
+                    Some(start) => start,
+                    None => {
+                        errors.push(ValidationError::new(Some(id), None, "the clue has no squares".to_string()));
+                        continue;
+                    }
+                };
+                if let (Some(number), Some(numbers)) = (number, &numbers) {
+                    let reason = match numbers.get(&(start.x, start.y, direction)) {
+                        None => Some(format!("the clue doesn't start an entry {name} in the grid")),
+                        Some(expected) if expected.to_string() != number => Some(format!("the clue starts on the square numbered {expected}")),
+                        Some(_) => None,
+                    };
+                    if let Some(reason) = reason {
+                        errors.push(ValidationError::new(Some(id), Some((start.x, start.y)), reason));
+                    }
+                }
+
+                for (i, cell) in clue.cells.iter().enumerate() {
+                    if cell.x >= MAX_GRID_SIZE || cell.y >= MAX_GRID_SIZE {
+                        errors.push(ValidationError::new(Some(id), Some((cell.x, cell.y)),
+                            format!("the square is outside the largest allowed grid of {MAX_GRID_SIZE} by {MAX_GRID_SIZE}")));
+                        break;
+                    }
+                    let expected = match direction {
+                        Direction::Across => (start.x + i, start.y),
+                        Direction::Down => (start.x, start.y + i),
+                    };
+                    if (cell.x, cell.y) != expected {
+                        let reason = match direction {
+                            Direction::Across => format!("expected the square at ({}, {}) to continue the clue across", expected.0, expected.1),
+                            Direction::Down => format!("expected the square at ({}, {}) to continue the clue down", expected.0, expected.1),
+                        };
+                        errors.push(ValidationError::new(Some(id), Some((cell.x, cell.y)), reason));
+                        break;
+                    }
+                    if i > 0 && bars.contains(&(clue.cells[i - 1].x, clue.cells[i - 1].y, direction)) {
+                        errors.push(ValidationError::new(Some(id), Some((cell.x, cell.y)), "the clue runs across a bar".to_string()));
+                        break;
+                    }
+
+                    if cell.c.is_blank() {
+                        continue;
+                    }
+                    if cell.c.letter_count() > *MAX_CELL_LENGTH {
+                        errors.push(ValidationError::new(Some(id), Some((cell.x, cell.y)),
+                            CellValueError::TooLong { value: cell.c.to_string(), max: *MAX_CELL_LENGTH }.to_string()));
+                    }
+                    match letters.get(&(cell.x, cell.y)) {
+                        Some((other, other_id)) if **other != cell.c => {
+                            errors.push(ValidationError::new(Some(id), Some((cell.x, cell.y)),
+                                format!("the letter {} doesn't match the {other} from {other_id}", cell.c)));
+                        },
+                        Some(_) => (),
+                        None => {
+                            letters.insert((cell.x, cell.y), (&cell.c, id));
+                        },
+                    }
+                }
+            }
+        }
+
+        if let Err(metadata_errors) = self.metadata.validate() {
+            errors.extend(metadata_errors.into_iter().map(|reason| ValidationError::new(None, None, reason)));
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    pub fn metadata(&self) -> &PuzzleMetadata {
+        &self.metadata
+    }
+
+    pub fn set_metadata(&mut self, metadata: PuzzleMetadata) {
+        self.metadata = metadata;
+    }
+
+    pub fn styles(&self) -> &[StyledSquare] {
+        &self.styles
+    }
+
+    /// Every clue's id (e.g. `3a`) and hint, across clues first.
+    pub fn hints(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.across.iter()
+            .chain(self.down.iter())
+            .map(|(id, clue)| (id.as_str(), clue.hint.as_str()))
+    }
+
+    /// Looks a clue up by its id in either direction.
+    fn clue(&self, id: &str) -> Option<&Clue> {
+        self.across.get(id).or_else(|| self.down.get(id))
+    }
+
+    /// The number of squares a clue's answer fills, including those of the
+    /// clues linked to it.
+    fn answer_length(&self, clue: &Clue) -> usize {
+        clue.cells.len() + clue.linked.iter().filter_map(|id| self.clue(id)).map(|x| x.cells.len()).sum::<usize>()
+    }
+
+    /// The clues in one direction as they're printed: the number, e.g. `5`
+    /// or `5/12` for a linked clue, and the hint followed by the enumeration
+    /// or length. A clue that carries on another is printed as `See 5`
+    /// unless it has a hint of its own.
+    fn printed_clues(&self, direction: Direction) -> Vec<(String, String)> {
+        let number = |id: &str| id.trim_end_matches(|c: char| !c.is_ascii_digit()).to_string();
+        let linked_from: HashMap<&str, &str> = [&self.across, &self.down].into_iter()
+            .flat_map(|clues| clues.iter())
+            .flat_map(|(id, clue)| clue.linked.iter().map(move |linked| (linked.as_str(), id.as_str())))
+            .collect();
+        let clues = match direction {
+            Direction::Across => &self.across,
+            Direction::Down => &self.down,
+        };
+        let mut ids: Vec<&String> = clues.keys().collect();
+        ids.sort_by_key(|id| clue_order(id));
+        ids.into_iter()
+            .map(|id| {
+                let clue = &clues[id];
+                if let Some(head) = linked_from.get(id.as_str()).filter(|_| clue.hint.trim().is_empty()) {
+                    return (number(id), format!("See {}", number(head)))
+                }
+                let mut label = number(id);
+                for linked in &clue.linked {
+                    label.push('/');
+                    label.push_str(&number(linked));
+                    match (direction, self.across.contains_key(linked)) {
+                        (Direction::Across, false) => label.push_str(" down"),
+                        (Direction::Down, true) => label.push_str(" across"),
+                        _ => (),
+                    }
+                }
+                let enumeration = match &clue.enumeration {
+                    Some(enumeration) => enumeration.to_string(),
+                    None => self.answer_length(clue).to_string(),
+                };
+                (label, format!("{} ({enumeration})", clue.hint))
+            })
+            .collect()
+    }
+
+    /// The clues in one direction in the order they're printed, with the
+    /// number each is printed with.
+    fn numbered_clues(&self, direction: Direction) -> Vec<(&str, &Clue)> {
+        let clues = match direction {
+            Direction::Across => &self.across,
+            Direction::Down => &self.down,
+        };
+        let mut ids: Vec<&String> = clues.keys().collect();
+        ids.sort_by_key(|id| clue_order(id));
+        ids.into_iter()
+            .map(|id| (id.trim_end_matches(|c: char| !c.is_ascii_digit()), &clues[id]))
+            .collect()
+    }
+
+    /// The number printed in each square that starts a clue.
+    fn square_numbers(&self) -> HashMap<(usize, usize), &str> {
+        [Direction::Across, Direction::Down].into_iter()
+            .flat_map(|direction| self.numbered_clues(direction))
+            .filter(|(number, _)| !number.is_empty())
+            .filter_map(|(number, clue)| clue.cells.first().map(|cell| ((cell.x, cell.y), number)))
+            .collect()
+    }
+
+    fn cell(&self, cell_ref: &CellRef) -> Option<&Cell> {
+        let clues = match cell_ref.direction {
+            Direction::Across => &self.across,
+            Direction::Down => &self.down,
+        };
+        clues.get(&cell_ref.clue).and_then(|clue| clue.cells.get(cell_ref.position))
+    }
 
+    /// Fills in a square in every clue that runs through it. Returns the
+    /// ids of those clues, across before down.
+    pub fn update_cell(&mut self, incoming_cell: Cell) -> Result<Vec<String>, CellError> {
+        if incoming_cell.c.letter_count() > *MAX_CELL_LENGTH {
+            return Err(CellError::Value(CellValueError::TooLong { value: incoming_cell.c.to_string(), max: *MAX_CELL_LENGTH }))
+        }
+        self.set_cell(incoming_cell).ok_or(CellError::NotInGrid)
     }
 
-    pub fn update_cell(&mut self, incoming_cell: Cell) {
-        self.across.iter_mut().for_each(|(_, clue)| {
-            clue.cells.iter_mut()
-            .find( |cell| cell.x == incoming_cell.x && cell.y == incoming_cell.y)
-            .and_then(|cell | {
-                info!("updating across clue cell");
-                Some(cell.c = incoming_cell.c)
-            });
-        });
-        self.down.iter_mut().for_each(|(_, clue)| {
-            clue.cells.iter_mut()
-            .find( |cell| cell.x == incoming_cell.x && cell.y == incoming_cell.y)
-            .and_then(|cell | {
-                info!("updating down clue cell");
-                Some(cell.c = incoming_cell.c)
-            });
-        });
+    /// Fills in a square however long its value, or returns `None` if the
+    /// square isn't part of the grid.
+    fn set_cell(&mut self, incoming_cell: Cell) -> Option<Vec<String>> {
+        let refs = self.index.get(&(incoming_cell.x, incoming_cell.y))?;
+        for cell_ref in refs {
+            let clues = match cell_ref.direction {
+                Direction::Across => &mut self.across,
+                Direction::Down => &mut self.down,
+            };
+            if let Some(cell) = clues.get_mut(&cell_ref.clue).and_then(|clue| clue.cells.get_mut(cell_ref.position)) {
+                cell.c = incoming_cell.c.clone();
+            }
+        }
+        Some(refs.iter().map(|cell_ref| cell_ref.clue.clone()).collect())
     }
 
 }
 
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Why a square couldn't be filled in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CellError {
+    NotInGrid,
+    Value(CellValueError),
+}
+
+impl std::fmt::Display for CellError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CellError::NotInGrid => write!(f, "the square is not part of the grid"),
+            CellError::Value(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Cell {
     x: usize,
     y: usize,
-    #[serde(deserialize_with = "char_deserialise")]
-    c: char,
+    c: CellValue,
 }
 
-fn char_deserialise<'de, D>(deserializer: D) -> Result<char, D::Error> where D: Deserializer<'de> {
-    let c_str: String = Deserialize::deserialize(deserializer)?;
-    let c = c_str.chars().next().unwrap_or(' ');
-    Ok(c)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "ClueData")]
+pub struct Clue {
+    hint: String,
+    cells: Vec<Cell>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    enumeration: Option<Enumeration>,
+    /// The ids of the entries that carry on this clue's answer, in order,
+    /// e.g. `["12d"]` for a clue printed as `5/12`. Those entries need no
+    /// hint of their own, and the enumeration covers them all.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    linked: Vec<String>,
 }
 
-impl Cell {
-    fn default() -> Self {
-        Self { x: 0, y: 0, c: ' ' }
+/// A clue as it's uploaded or saved. Without an `enumeration`, one on the
+/// end of the hint like `Pet (3)` is split off into it if it fits the clue.
+#[derive(Deserialize)]
+struct ClueData {
+    hint: String,
+    cells: Vec<Cell>,
+    #[serde(default)]
+    enumeration: Option<Enumeration>,
+    #[serde(default)]
+    linked: Vec<String>,
+}
+
+impl From<ClueData> for Clue {
+    fn from(data: ClueData) -> Self {
+        let (hint, enumeration) = match data.enumeration {
+            Some(enumeration) => (data.hint, Some(enumeration)),
+            None => split_fitting_hint(&data.hint, data.cells.len(), !data.linked.is_empty()),
+        };
+        Self { hint, cells: data.cells, enumeration, linked: data.linked }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Clue {
-    hint: String,
-    cells: Vec<Cell>
+/// Splits an enumeration off the end of a hint only if it could be the
+/// answer's: one filling the clue's `squares`, or more for a clue with
+/// linked entries. Otherwise it's part of the hint, like the year in
+/// `Year of Hastings (1066)`.
+fn split_fitting_hint(hint: &str, squares: usize, linked: bool) -> (String, Option<Enumeration>) {
+    match Enumeration::split_hint(hint) {
+        (before, Some(enumeration)) if enumeration.length() == squares || linked && enumeration.length() > squares => (before, Some(enumeration)),
+        _ => (hint.to_string(), None),
+    }
 }
 
 impl Clue {
+    /// A clue of `len` blank squares. An enumeration on the end of the hint
+    /// is split off as it is when clues are uploaded.
     fn new(len:usize, start: (usize,usize), hint: &str, direction: Direction) -> Self {
         let mut cells = vec![Cell::default(); len];
 
@@ -108,7 +740,295 @@ impl Clue {
             }
         }
 
-        Self{ hint: hint.to_string(),  cells }
+        let (hint, enumeration) = split_fitting_hint(hint, len, false);
+        Self{ hint, cells, enumeration, linked: Vec::new() }
 
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub(crate) mod test_fixtures {
+    use super::{Crossword, SolutionInput};
+
+    /// The answers to `Crossword::demo_grid`. With `rebus`, its first square
+    /// holds `FOR` rather than `F`.
+    pub fn demo_answers(rebus: bool) -> SolutionInput {
+        let json = r#"{"clues":{"1a":"FORALLTH","3a":"ISPENTIT","1d":"FANDIFOR","2d":"LIKENESS"}}"#;
+        match rebus {
+            true => serde_json::from_str(&json.replace("FORALLTH", "[FOR]ORALLTH").replace("FANDIFOR", "[FOR]ANDIFOR")).unwrap(),
+            false => serde_json::from_str(json).unwrap(),
+        }
+    }
+
+    /// Checks a puzzle read back from a file has the hints `cw` was written
+    /// with. Files number their clues afresh, so `cw` is renumbered first.
+    pub fn assert_same_hints(cw: &Crossword, back: &Crossword) {
+        let renumbered = cw.renumbered().unwrap();
+        let mut expected: Vec<(&str, &str)> = renumbered.hints().collect();
+        let mut hints: Vec<(&str, &str)> = back.hints().collect();
+        hints.sort();
+        expected.sort();
+        assert_eq!(hints, expected);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Cell, CellError, CellValue, CellValueError, Clue, Crossword, Direction, Enumeration, GridSizeError, PuzzleMetadata, SvgOptions, ValidationError, MAX_CLUES};
+
+    #[test]
+    fn test_update_cell() {
+        let mut cw = Crossword::demo_grid();
+        assert_eq!(cw.update_cell(Cell { x: 0, y: 0, c: 'A'.into() }), Ok(vec!["1a".to_string(), "1d".to_string()]));
+        assert_eq!(cw.update_cell(Cell { x: 2, y: 4, c: 'B'.into() }), Ok(vec!["3a".to_string()]));
+        assert_eq!(cw.update_cell(Cell { x: 2, y: 2, c: 'C'.into() }), Err(CellError::NotInGrid));
+        assert_eq!(cw.update_cell(Cell { x: 99, y: 0, c: 'D'.into() }), Err(CellError::NotInGrid));
+
+        let grid = cw.grid().unwrap();
+        assert_eq!(grid.letter(0, 0), Some(&'A'.into()));
+        assert_eq!(grid.letter(2, 4), Some(&'B'.into()));
+        assert!(cw.across["1a"].cells[0].c == 'A'.into() && cw.down["1d"].cells[0].c == 'A'.into());
+    }
+
+    #[test]
+    fn test_update_cell_with_rebus() {
+        let mut cw = Crossword::demo_grid();
+        let star = CellValue::parse("STAR", 4).unwrap();
+        assert!(cw.update_cell(Cell { x: 4, y: 0, c: star.clone() }).is_ok());
+        assert_eq!(cw.grid().unwrap().letter(4, 0), Some(&star));
+
+        let too_long = CellValue::parse("TOOLONGFORASQUARE", usize::MAX).unwrap();
+        assert!(matches!(cw.update_cell(Cell { x: 4, y: 0, c: too_long }), Err(CellError::Value(CellValueError::TooLong { .. }))));
+        assert_eq!(cw.letter(4, 0), Some(&star));
+    }
+
+    #[test]
+    fn test_cell_from_json() {
+        let cell: Cell = serde_json::from_str(r#"{"x":1,"y":0,"c":"q"}"#).unwrap();
+        assert_eq!(cell.c, 'q'.into());
+        let cell: Cell = serde_json::from_str(r#"{"x":1,"y":0,"c":"Ελ"}"#).unwrap();
+        assert_eq!(cell.c.letter_count(), 2);
+        assert_eq!(serde_json::to_string(&Cell::default()).unwrap(), r#"{"x":0,"y":0,"c":" "}"#);
+        assert!(serde_json::from_str::<Cell>(r#"{"x":1,"y":0,"c":"A B"}"#).is_err());
+    }
+
+    #[test]
+    fn test_update_cell_after_loading() {
+        let json = serde_json::to_string(&Crossword::demo_grid()).unwrap();
+        let mut cw: Crossword = serde_json::from_str(&json).unwrap();
+        assert_eq!(cw.update_cell(Cell { x: 4, y: 4, c: 'E'.into() }), Ok(vec!["3a".to_string(), "2d".to_string()]));
+        assert_eq!(cw.down["2d"].cells[4].c, 'E'.into());
+    }
+
+    #[test]
+    fn test_crossword_without_metadata() {
+        let cw: Crossword = serde_json::from_str(r#"{"across":{},"down":{}}"#).unwrap();
+        assert!(cw.metadata().is_empty());
+        assert_eq!(serde_json::to_string(&cw).unwrap(), r#"{"across":{},"down":{}}"#);
+    }
+
+    #[test]
+    fn test_valid_metadata() {
+        let metadata = PuzzleMetadata {
+            author: Some("Araucaria".to_string()),
+            publication_date: Some("2024-03-01".to_string()),
+            difficulty: Some(5),
+            source_image: Some("scans/guardian.png".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(metadata.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_invalid_metadata() {
+        let metadata = PuzzleMetadata {
+            publication_date: Some("01/03/2024".to_string()),
+            difficulty: Some(6),
+            source_image: Some("../../etc/passwd".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(metadata.validate().map_err(|e| e.len()), Err(3));
+
+        for source in ["javascript:alert(1)", " JavaScript:alert(1)", "data:text/html,hi", "//example.com/a.png", "\\\\example.com\\a.png"] {
+            let metadata = PuzzleMetadata { source_image: Some(source.to_string()), ..Default::default() };
+            assert!(metadata.validate().is_err(), "{source}");
+        }
+        let metadata = PuzzleMetadata { source_image: Some("https://example.com/a.png".to_string()), ..Default::default() };
+        assert_eq!(metadata.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_demo_grid_layout() {
+        let grid = Crossword::demo_grid().grid().unwrap();
+        assert_eq!((grid.width(), grid.height()), (8, 8));
+        assert!(!grid.is_block(0, 0));
+        assert!(grid.is_block(1, 1));
+        assert!(!grid.is_block(4, 4));
+    }
+
+    #[test]
+    fn test_grid_too_big() {
+        let cw: Crossword = serde_json::from_str(r#"{"across":{"1a":{"hint":"Far","cells":[{"x":49,"y":200000000,"c":"A"}]}},"down":{}}"#).unwrap();
+        assert_eq!(cw.grid().unwrap_err(), GridSizeError { x: 49, y: 200000000 });
+        assert!(cw.to_svg(&SvgOptions::default()).is_err());
+        let cw: Crossword = serde_json::from_str(r#"{"across":{"1a":{"hint":"Far","cells":[{"x":18446744073709551615,"y":0,"c":"A"}]}},"down":{}}"#).unwrap();
+        assert!(cw.renumbered().is_err());
+        assert_eq!(Crossword::new(HashMap::new(), HashMap::new(), PuzzleMetadata::default()).grid().map(|x| x.width()), Ok(0));
+    }
+
+    #[test]
+    fn test_renumbered_keeps_hints() {
+        let cw = Crossword::demo_grid().renumbered().unwrap();
+        let hints: Vec<(&str, &str)> = cw.hints().collect();
+        assert!(hints.contains(&("1a", "For all the money that e'er I had")));
+        assert!(hints.contains(&("2d", "I've done to none but me.")));
+        assert!(hints.contains(&("3a", "I spent it in good company")));
+        let grid = cw.grid().unwrap();
+        assert_eq!(grid.entries().iter().filter(|x| x.direction == Direction::Down).count(), 2);
+    }
+
+    #[test]
+    fn test_clues_serialise_in_numeric_order() {
+        let cw: Crossword = serde_json::from_str(r#"{"across":{
+            "10a":{"hint":"c","cells":[]},
+            "2a":{"hint":"b","cells":[]},
+            "1a":{"hint":"a","cells":[]}
+        },"down":{}}"#).unwrap();
+        let json = serde_json::to_string(&cw).unwrap();
+        let positions: Vec<usize> = ["\"1a\"", "\"2a\"", "\"10a\""].iter().map(|x| json.find(x).unwrap()).collect();
+        assert!(positions.windows(2).all(|x| x[0] < x[1]));
+    }
+
+    #[test]
+    fn test_styles() {
+        let mut json = serde_json::to_value(Crossword::demo_grid()).unwrap();
+        json["styles"] = serde_json::json!([{"x": 0, "y": 0, "circled": true}, {"x": 3, "y": 0, "bar_right": true}]);
+        let cw: Crossword = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&cw).unwrap()["styles"], json["styles"]);
+        assert_eq!(cw.validate(), Err(vec![ValidationError { clue: Some("1a".to_string()), cell: Some((4, 0)), reason: "the clue runs across a bar".to_string() }]));
+
+        // renumbering splits 1a at the bar.
+        let renumbered = cw.renumbered().unwrap();
+        let hints: HashMap<&str, &str> = renumbered.hints().collect();
+        assert_eq!(hints.get("1a"), Some(&"For all the money that e'er I had"));
+        assert_eq!(hints.get("2a"), Some(&""));
+        assert_eq!(renumbered.across["1a"].cells.len(), 4);
+        assert_eq!(renumbered.styles(), cw.styles());
+
+        json["styles"] = serde_json::json!([{"x": 2, "y": 2, "shaded": true}, {"x": 7, "y": 0, "bar_right": true}, {"x": 7, "y": 0, "circled": true}]);
+        let cw: Crossword = serde_json::from_value(json).unwrap();
+        let reasons: Vec<String> = cw.validate().unwrap_err().into_iter().map(|e| e.to_string()).collect();
+        assert_eq!(reasons, [
+            "(2, 2): the square is styled but isn't in the grid",
+            "(7, 0): the bar on the right has no square on its other side",
+            "(7, 0): the square is styled more than once",
+        ]);
+    }
+
+    #[test]
+    fn test_enumerations_and_linked_clues() {
+        let mut json = serde_json::to_value(Crossword::demo_grid()).unwrap();
+        json["across"]["1a"]["hint"] = "For all the money (3,3,5)".into();
+        json["across"]["1a"]["linked"] = serde_json::json!(["3a"]);
+        json["across"]["3a"]["hint"] = "".into();
+        json["down"]["2d"]["enumeration"] = "4-4".into();
+        let cw: Crossword = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(cw.across["1a"].hint, "For all the money");
+        let sent = serde_json::to_value(&cw).unwrap();
+        assert_eq!(sent["across"]["1a"]["enumeration"]["breaks"], serde_json::json!([{"after": 3, "kind": "word"}, {"after": 6, "kind": "word"}]));
+        assert_eq!(sent["down"]["2d"]["enumeration"]["text"], "4-4");
+        assert_eq!(cw.validate(), Err(vec![ValidationError { clue: Some("1a".to_string()), cell: None, reason: "the enumeration (3,3,5) is 11 letters but the clue has 16 squares".to_string() }]));
+
+        json["across"]["1a"]["hint"] = "For all the money (8,8)".into();
+        let cw: Crossword = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(cw.validate(), Ok(()));
+        assert_eq!(cw.printed_clues(Direction::Across), [
+            ("1/3".to_string(), "For all the money (8,8)".to_string()),
+            ("3".to_string(), "See 1".to_string()),
+        ]);
+        assert_eq!(cw.printed_clues(Direction::Down)[1], ("2".to_string(), "I've done to none but me. (4-4)".to_string()));
+        let renumbered = cw.renumbered().unwrap();
+        assert_eq!(renumbered.across["1a"].linked, ["3a"]);
+        assert_eq!(renumbered.down["2d"].enumeration.as_ref().map(|x| x.as_str()), Some("4-4"));
+
+        json["across"]["1a"]["linked"] = serde_json::json!(["1a", "9a", "1d"]);
+        json["down"]["1d"]["linked"] = serde_json::json!(["2d"]);
+        json["down"]["2d"]["linked"] = serde_json::json!(["1d"]);
+        let cw: Crossword = serde_json::from_value(json).unwrap();
+        let reasons: Vec<String> = cw.validate().unwrap_err().into_iter().map(|e| e.to_string()).collect();
+        assert_eq!(reasons, [
+            "1a: the clue is linked to itself",
+            "1a: the linked clue 9a isn't in the puzzle",
+            "1a: 1d has linked clues of its own",
+            "1d: 2d has linked clues of its own",
+            "2d: 1d has linked clues of its own",
+            "1a: the enumeration (8,8) is 16 letters but the clue has 24 squares",
+            "3a: the hint is empty",
+            "2d: the enumeration (4-4) is 8 letters but the clue has 16 squares",
+        ]);
+    }
+
+    #[test]
+    fn test_hints_ending_in_numbers() {
+        let mut json = serde_json::to_value(Crossword::demo_grid()).unwrap();
+        json["across"]["1a"]["hint"] = "For all the money (8)".into();
+        json["across"]["3a"]["hint"] = "Novel (Catch-22)".into();
+        json["down"]["1d"]["hint"] = "Year of Hastings (1066)".into();
+        json["down"]["2d"]["hint"] = "Tea for two (2)".into();
+        let cw: Crossword = serde_json::from_value(json).unwrap();
+        for (clue, hint) in [(&cw.across["3a"], "Novel (Catch-22)"), (&cw.down["1d"], "Year of Hastings (1066)"), (&cw.down["2d"], "Tea for two (2)")] {
+            assert_eq!(clue.hint, hint);
+            assert_eq!(clue.enumeration, None);
+        }
+        assert_eq!(cw.across["1a"].hint, "For all the money");
+        assert_eq!(cw.across["1a"].enumeration, Some(Enumeration::single(8)));
+        assert_eq!(cw.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_demo_grid_is_valid() {
+        assert_eq!(Crossword::demo_grid().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let cw: Crossword = serde_json::from_str(r#"{"across":{
+            "1a":{"hint":"a","cells":[{"x":0,"y":0,"c":"A"},{"x":2,"y":0,"c":" "}]},
+            "2a":{"hint":" ","cells":[{"x":0,"y":1,"c":"B"},{"x":1,"y":1,"c":" "}]}
+        },"down":{
+            "1d":{"hint":"b","cells":[{"x":0,"y":0,"c":"C"},{"x":0,"y":1,"c":" "}]},
+            "3d":{"hint":"c","cells":[{"x":60,"y":0,"c":" "}]}
+        }}"#).unwrap();
+        let errors = cw.validate().unwrap_err();
+        assert_eq!(errors.len(), 4);
+        assert_eq!(errors[0], ValidationError { clue: Some("1a".to_string()), cell: Some((2, 0)), reason: "expected the square at (1, 0) to continue the clue across".to_string() });
+        assert_eq!(errors[1].clue.as_deref(), Some("2a"));
+        assert_eq!(errors[2].to_string(), "1d at (0, 0): the letter C doesn't match the A from 1a");
+        assert_eq!(errors[3].cell, Some((60, 0)));
+    }
+
+    #[test]
+    fn test_clue_ids() {
+        let cw: Crossword = serde_json::from_str(r#"{"across":{
+            "1a":{"hint":"Pet","cells":[{"x":0,"y":0,"c":"C"},{"x":1,"y":0,"c":"A"},{"x":2,"y":0,"c":"T"}]},
+            "4a":{"hint":"Digit","cells":[{"x":0,"y":2,"c":"T"},{"x":1,"y":2,"c":"E"},{"x":2,"y":2,"c":"N"}]}
+        },"down":{
+            "1a":{"hint":"Baby bed","cells":[{"x":0,"y":0,"c":"C"},{"x":0,"y":1,"c":"O"},{"x":0,"y":2,"c":"T"}]},
+            "2d":{"hint":"Ten","cells":[{"x":2,"y":0,"c":"T"},{"x":2,"y":1,"c":"E"},{"x":2,"y":2,"c":"N"}]},
+            "3d":{"hint":"Lone","cells":[{"x":1,"y":2,"c":"E"}]}
+        }}"#).unwrap();
+        let errors: Vec<String> = cw.validate().unwrap_err().iter().map(|x| x.to_string()).collect();
+        assert_eq!(errors, [
+            "4a at (0, 2): the clue starts on the square numbered 3",
+            "1a: the id should be the clue's number followed by d",
+            "3d at (1, 2): the clue doesn't start an entry down in the grid",
+        ]);
+
+        let across = (0..=MAX_CLUES).map(|i| (format!("{}a", i + 1), Clue::new(2, (0, 0), "Clue", Direction::Across))).collect();
+        let errors = Crossword::new(across, HashMap::new(), PuzzleMetadata::default()).validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].reason, format!("the puzzle has {} clues but can have at most {MAX_CLUES}", MAX_CLUES + 1));
+    }
+}
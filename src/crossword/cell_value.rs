@@ -0,0 +1,208 @@
+use std::{env, fmt::{self, Display}, str::FromStr};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+const DEFAULT_MAX_CELL_LENGTH: usize = 8;
+
+lazy_static! {
+    /// The most letters a square may be filled in with, from
+    /// `PUZZLE_MAX_CELL_LENGTH`. It only applies to new values, so lowering
+    /// it doesn't stop puzzles saved with longer ones from loading.
+    pub static ref MAX_CELL_LENGTH: usize = env::var("PUZZLE_MAX_CELL_LENGTH")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .filter(|x| *x > 0)
+        .unwrap_or(DEFAULT_MAX_CELL_LENGTH);
+}
+
+/// What's written in a square: nothing, a letter, or several letters for a
+/// rebus square. Letters are counted as grapheme clusters, so an accented
+/// letter made with a combining mark is one letter, and are kept in NFC so
+/// the same letter typed two ways compares equal.
+///
+/// It is sent and saved as a string, with `" "` for an empty square as when
+/// squares held a single character.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct CellValue(String);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CellValueError {
+    Control,
+    Whitespace,
+    TooLong { value: String, max: usize },
+    Unclosed,
+}
+
+impl Display for CellValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellValueError::Control => write!(f, "a square can't hold control characters"),
+            CellValueError::Whitespace => write!(f, "a square can't hold spaces"),
+            CellValueError::TooLong { value, max } => write!(f, "{value} is {} letters but a square holds at most {max}", value.graphemes(true).count()),
+            CellValueError::Unclosed => write!(f, "a [ has no matching ]"),
+        }
+    }
+}
+
+impl std::error::Error for CellValueError {}
+
+/// Shared by every empty square, so one can be lent out without a clue to
+/// borrow it from.
+pub(crate) static BLANK: CellValue = CellValue(String::new());
+
+impl CellValue {
+    /// Reads a value of at most `max_len` letters. Surrounding spaces are
+    /// ignored and a value that is only spaces is an empty square.
+    pub fn parse(s: &str, max_len: usize) -> Result<Self, CellValueError> {
+        let value: String = s.trim().nfc().collect();
+        if value.chars().any(char::is_control) {
+            return Err(CellValueError::Control)
+        }
+        if value.chars().any(char::is_whitespace) {
+            return Err(CellValueError::Whitespace)
+        }
+        if value.graphemes(true).count() > max_len {
+            return Err(CellValueError::TooLong { value, max: max_len })
+        }
+        Ok(Self(value))
+    }
+
+    pub fn is_blank(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The letters, with an empty string for an empty square.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn letter_count(&self) -> usize {
+        self.0.graphemes(true).count()
+    }
+
+    pub fn to_uppercase(&self) -> Self {
+        Self(self.0.to_uppercase())
+    }
+
+    /// Whether two values are the same letters, ignoring case.
+    pub fn same_letters(&self, other: &CellValue) -> bool {
+        self.0.to_uppercase() == other.0.to_uppercase()
+    }
+
+    /// The value as it's written in an answer: a single letter as it is and
+    /// a rebus in brackets, e.g. `[STAR]`.
+    pub fn bracketed(&self) -> String {
+        match self.letter_count() {
+            0 | 1 => self.0.clone(),
+            _ => format!("[{}]", self.0),
+        }
+    }
+}
+
+/// Splits an answer into the values of its squares, a letter to a square
+/// except where letters are grouped in brackets: `[STAR]TLE` fills four.
+pub fn split_squares(answer: &str, max_len: usize) -> Result<Vec<CellValue>, CellValueError> {
+    let mut squares = Vec::new();
+    let mut graphemes = answer.graphemes(true);
+    while let Some(grapheme) = graphemes.next() {
+        let value = match grapheme {
+            "[" => {
+                let mut inside = String::new();
+                loop {
+                    match graphemes.next() {
+                        Some("]") => break,
+                        Some(g) => inside.push_str(g),
+                        None => return Err(CellValueError::Unclosed),
+                    }
+                }
+                inside
+            },
+            g => g.to_string(),
+        };
+        squares.push(CellValue::parse(&value, max_len)?);
+    }
+    Ok(squares)
+}
+
+/// Values are read with no length limit here, which is how saved puzzles
+/// are loaded; the limit is checked where new values come in.
+impl TryFrom<String> for CellValue {
+    type Error = CellValueError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::parse(&s, usize::MAX)
+    }
+}
+
+impl FromStr for CellValue {
+    type Err = CellValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, *MAX_CELL_LENGTH)
+    }
+}
+
+/// Anything that isn't a visible character is an empty square.
+impl From<char> for CellValue {
+    fn from(c: char) -> Self {
+        Self::parse(&c.to_string(), 1).unwrap_or_default()
+    }
+}
+
+impl From<CellValue> for String {
+    fn from(value: CellValue) -> Self {
+        match value.is_blank() {
+            true => " ".to_string(),
+            false => value.0,
+        }
+    }
+}
+
+impl Display for CellValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_squares, CellValue, CellValueError};
+
+    #[test]
+    fn test_cell_value() {
+        assert_eq!(CellValue::parse("STAR", 4).unwrap().letter_count(), 4);
+        assert!(matches!(CellValue::parse("STARS", 4), Err(CellValueError::TooLong { .. })));
+        assert!(CellValue::parse("  ", 1).unwrap().is_blank());
+        assert_eq!(CellValue::parse("A B", 4), Err(CellValueError::Whitespace));
+        assert_eq!(CellValue::parse("A\u{7}", 4), Err(CellValueError::Control));
+
+        // an e with a combining acute accent is one letter, and matches a precomposed é.
+        let combined = CellValue::parse("e\u{301}", 1).unwrap();
+        assert_eq!(combined, CellValue::parse("\u{e9}", 1).unwrap());
+        assert_eq!(CellValue::parse("\u{939}\u{93f}", 1).unwrap().letter_count(), 1);
+        assert!(CellValue::from('a').same_letters(&CellValue::from('A')));
+    }
+
+    #[test]
+    fn test_cell_value_json() {
+        let value: CellValue = serde_json::from_str(r#""A""#).unwrap();
+        assert_eq!(value, CellValue::from('A'));
+        let blank: CellValue = serde_json::from_str(r#"" ""#).unwrap();
+        assert!(blank.is_blank());
+        assert_eq!(serde_json::to_string(&blank).unwrap(), r#"" ""#);
+        assert_eq!(serde_json::to_string(&CellValue::parse("STAR", 4).unwrap()).unwrap(), r#""STAR""#);
+    }
+
+    #[test]
+    fn test_split_squares() {
+        let squares = split_squares("[STAR]tle", 4).unwrap();
+        assert_eq!(squares.iter().map(|x| x.as_str()).collect::<Vec<_>>(), ["STAR", "t", "l", "e"]);
+        assert_eq!(squares[0].bracketed(), "[STAR]");
+        assert_eq!(split_squares("[STAR", 4), Err(CellValueError::Unclosed));
+        assert!(split_squares("[STARS]", 4).is_err());
+    }
+}
@@ -0,0 +1,529 @@
+use std::{collections::{HashMap, HashSet}, fmt::{self, Display}};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{clue_order, CellValue, Crossword, Direction, Enumeration, Grid, GridSizeError, PuzzleMetadata, Solution, MAX_CELL_LENGTH, MAX_GRID_SIZE};
+
+/// A puzzle written by hand as text: optional `Key: value` headers, the grid
+/// with `#` for blocks and letters (or `.` for unknown squares, and brackets
+/// around a rebus like `[STAR]`), then `Across` and `Down` sections of clues
+/// like `1 Clue text (5)`. A linked clue lists the entries it carries on
+/// into, with `a` or `d` for one in the other direction: `1/2d Clue (3,3)`.
+///
+/// ```text
+/// Title: Ring
+///
+/// CAT
+/// O#E
+/// TEN
+///
+/// Across
+/// 1 Pet (3)
+/// 3 Digit (3)
+///
+/// Down
+/// 1 Baby bed (3)
+/// 2 Ten (3)
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ClueList {
+    headers: Vec<(String, String)>,
+    /// The grid's rows, with `None` for blocks and a blank value for open
+    /// squares whose letter isn't given.
+    rows: Vec<Vec<Option<CellValue>>>,
+    grid_line: usize,
+    across: Vec<ListedClue>,
+    down: Vec<ListedClue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ListedClue {
+    line: usize,
+    number: usize,
+    /// The entries the clue carries on into, with a direction where it
+    /// isn't the clue's own.
+    linked: Vec<(usize, Option<Direction>)>,
+    hint: String,
+    enumeration: Option<Enumeration>,
+}
+
+/// Something wrong with a clue list, with the line it was found on. Line 0
+/// means the file as a whole.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClueListError {
+    pub line: usize,
+    pub reason: String,
+}
+
+impl ClueListError {
+    fn new(line: usize, reason: impl Into<String>) -> Self {
+        Self { line, reason: reason.into() }
+    }
+}
+
+impl Display for ClueListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "{}", self.reason),
+            line => write!(f, "line {line}: {}", self.reason),
+        }
+    }
+}
+
+impl std::error::Error for ClueListError {}
+
+const HEADERS: [&str; 7] = ["title", "author", "publication", "date", "difficulty", "copyright", "notes"];
+
+fn heading(line: &str) -> Option<Direction> {
+    match line.trim().trim_end_matches(':').to_ascii_lowercase().as_str() {
+        "across" => Some(Direction::Across),
+        "down" => Some(Direction::Down),
+        _ => None,
+    }
+}
+
+/// Reads a row of the grid. Spaces between squares are ignored.
+fn parse_row(line_number: usize, line: &str) -> Result<Vec<Option<CellValue>>, ClueListError> {
+    let mut row = Vec::new();
+    let mut graphemes = line.graphemes(true).filter(|g| !matches!(*g, " " | "\t"));
+    while let Some(grapheme) = graphemes.next() {
+        let i = row.len() + 1;
+        let square = match grapheme {
+            "#" => None,
+            "." | "_" | "?" | "-" => Some(CellValue::default()),
+            "[" => {
+                let mut inside = String::new();
+                loop {
+                    match graphemes.next() {
+                        Some("]") => break,
+                        Some(g) => inside.push_str(g),
+                        None => return Err(ClueListError::new(line_number, format!("square {i} starts with `[` but has no `]`"))),
+                    }
+                }
+                let value = CellValue::parse(&inside, *MAX_CELL_LENGTH)
+                    .map_err(|e| ClueListError::new(line_number, format!("square {i}: {e}")))?;
+                Some(value.to_uppercase())
+            },
+            g if g.chars().next().is_some_and(char::is_alphabetic) => Some(CellValue::parse(g, 1).unwrap_or_default().to_uppercase()),
+            g => return Err(ClueListError::new(line_number, format!("square {i} is `{g}`, which should be a letter, `#` for a block or `.` for an unknown letter"))),
+        };
+        row.push(square);
+    }
+    Ok(row)
+}
+
+/// Reads `1 Clue text (5)`, `1. Clue text (2,3)`, `1: Clue text` or
+/// `1/4d Clue text (3,3)`.
+fn parse_clue(line_number: usize, line: &str) -> Result<ListedClue, ClueListError> {
+    let line = line.trim();
+    let error = || ClueListError::new(line_number, format!("expected a clue like `1 Clue text (5)`, not `{line}`"));
+    let digits = |s: &str| s.chars().take_while(|c| c.is_ascii_digit()).count();
+    let n = digits(line);
+    let number = line[..n].parse::<usize>().map_err(|_| error())?;
+    let mut rest = &line[n..];
+    let mut linked = Vec::new();
+    while let Some(after) = rest.strip_prefix('/') {
+        let n = digits(after);
+        let linked_number = after[..n].parse::<usize>().map_err(|_| error())?;
+        rest = &after[n..];
+        // `a` or `d` only gives a direction when it isn't the start of a word.
+        let mut chars = rest.chars();
+        let direction = match (chars.next(), chars.next().filter(|c| c.is_alphanumeric())) {
+            (Some('a'), None) => Some(Direction::Across),
+            (Some('d'), None) => Some(Direction::Down),
+            _ => None,
+        };
+        if direction.is_some() {
+            rest = &rest[1..];
+        }
+        linked.push((linked_number, direction));
+    }
+    let rest = rest.trim_start_matches(['.', ':']).trim();
+
+    let (hint, enumeration) = Enumeration::split_hint(rest);
+    if hint.is_empty() || hint.starts_with('(') && Enumeration::parse(&hint).is_ok() {
+        return Err(ClueListError::new(line_number, format!("clue {number} has no text")))
+    }
+    Ok(ListedClue { line: line_number, number, linked, hint, enumeration })
+}
+
+impl ClueList {
+    /// Whether the data looks like a clue list: text with an `Across` or
+    /// `Down` heading that isn't some other format.
+    pub fn is_clue_list(data: &[u8]) -> bool {
+        match std::str::from_utf8(data) {
+            Ok(text) => {
+                let start = text.trim_start_matches('\u{feff}').trim_start();
+                !start.starts_with(['{', '<']) && text.lines().any(|line| heading(line).is_some())
+            },
+            Err(_) => false,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ClueListError> {
+        let mut list = ClueList::default();
+        let mut lines = text.trim_start_matches('\u{feff}').lines().enumerate().map(|(i, line)| (i + 1, line)).peekable();
+
+        // headers, until the first line that isn't one
+        while let Some((number, line)) = lines.peek().copied() {
+            if line.trim().is_empty() {
+                lines.next();
+                continue;
+            }
+            let Some((key, value)) = line.split_once(':') else { break };
+            if heading(line).is_some() {
+                break
+            }
+            let key = key.trim().to_ascii_lowercase();
+            if !HEADERS.contains(&key.as_str()) {
+                return Err(ClueListError::new(number, format!("unknown header `{}`, expected one of {}", key, HEADERS.join(", "))))
+            }
+            list.headers.push((key, value.trim().to_string()));
+            lines.next();
+        }
+
+        // the grid, until a blank line or a heading
+        while let Some((number, line)) = lines.peek().copied() {
+            if line.trim().is_empty() || heading(line).is_some() {
+                break
+            }
+            if list.rows.is_empty() {
+                list.grid_line = number;
+            }
+            let row = parse_row(number, line)?;
+            if let Some(first) = list.rows.first().filter(|first| first.len() != row.len()) {
+                return Err(ClueListError::new(number, format!("the row has {} squares but the first row has {}", row.len(), first.len())))
+            }
+            list.rows.push(row);
+            lines.next();
+        }
+        if list.rows.is_empty() {
+            return Err(ClueListError::new(0, "there's no grid before the clues"))
+        }
+
+        // the clues, under their headings
+        let mut direction = None;
+        for (number, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(heading) = heading(line) {
+                direction = Some(heading);
+                continue;
+            }
+            let clue = parse_clue(number, line)?;
+            match direction {
+                Some(Direction::Across) => list.across.push(clue),
+                Some(Direction::Down) => list.down.push(clue),
+                None => return Err(ClueListError::new(number, "clues should come after an `Across` or `Down` heading")),
+            }
+        }
+        Ok(list)
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.header("title")
+    }
+
+    fn header(&self, key: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k == key).map(|(_, value)| value.as_str()).filter(|x| !x.is_empty())
+    }
+}
+
+impl Display for ClueList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in &self.headers {
+            let mut key = key.clone();
+            key[..1].make_ascii_uppercase();
+            writeln!(f, "{key}: {value}")?;
+        }
+        if !self.headers.is_empty() {
+            writeln!(f)?;
+        }
+        for row in &self.rows {
+            let row: String = row.iter()
+                .map(|square| match square {
+                    None => "#".to_string(),
+                    Some(c) if c.is_blank() => ".".to_string(),
+                    Some(c) => c.bracketed(),
+                })
+                .collect();
+            writeln!(f, "{row}")?;
+        }
+        for (heading, clues) in [("Across", &self.across), ("Down", &self.down)] {
+            writeln!(f, "\n{heading}")?;
+            for clue in clues {
+                let linked: String = clue.linked.iter()
+                    .map(|(number, direction)| match direction {
+                        Some(Direction::Across) => format!("/{number}a"),
+                        Some(Direction::Down) => format!("/{number}d"),
+                        None => format!("/{number}"),
+                    })
+                    .collect();
+                match &clue.enumeration {
+                    Some(enumeration) => writeln!(f, "{}{linked} {} ({enumeration})", clue.number, clue.hint)?,
+                    None => writeln!(f, "{}{linked} {}", clue.number, clue.hint)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Crossword {
+    /// Builds a crossword from a clue list, numbering the grid the standard
+    /// way. If every open square has a letter they're the solution. Every
+    /// entry must have a clue or be linked from one, so the result is a
+    /// valid crossword.
+    pub fn from_clue_list(list: &ClueList) -> Result<(Crossword, Option<Solution>), ClueListError> {
+        let (width, height) = (list.rows[0].len(), list.rows.len());
+        if width > MAX_GRID_SIZE || height > MAX_GRID_SIZE {
+            return Err(ClueListError::new(list.grid_line, format!("the grid is {width}x{height} but can be at most {MAX_GRID_SIZE}x{MAX_GRID_SIZE}")))
+        }
+
+        let mut grid = Grid::new(width, height);
+        let mut letters = Vec::new();
+        let mut unknown = None;
+        for (y, row) in list.rows.iter().enumerate() {
+            for (x, square) in row.iter().enumerate() {
+                match square {
+                    None => grid.set_block(x, y, true),
+                    Some(c) if c.is_blank() => unknown = unknown.or(Some((x, y))),
+                    Some(c) => letters.push(((x, y), c.clone())),
+                }
+            }
+        }
+        let solution: Option<Solution> = match (letters.is_empty(), unknown) {
+            (true, _) => None,
+            (false, None) => Some(letters.into_iter().collect()),
+            (false, Some((x, y))) => return Err(ClueListError::new(list.grid_line + y, format!("square {} has no letter, but others do; use letters everywhere or nowhere", x + 1))),
+        };
+
+        let mut crossword = Crossword::from_grid(&grid);
+        let entries = grid.entries();
+        let name = |direction: Direction| match direction {
+            Direction::Across => "across",
+            Direction::Down => "down",
+        };
+        // the entries with a clue, or linked from one.
+        let mut covered: HashSet<(Direction, usize)> = HashSet::new();
+        for (direction, clues) in [(Direction::Across, &list.across), (Direction::Down, &list.down)] {
+            let mut seen: HashMap<usize, usize> = HashMap::new();
+            for clue in clues {
+                if let Some(line) = seen.insert(clue.number, clue.line) {
+                    return Err(ClueListError::new(clue.line, format!("{} {} is already on line {line}", clue.number, name(direction))))
+                }
+                let find = |number: usize, direction: Direction| entries.iter()
+                    .find(|entry| entry.direction == direction && entry.number == number)
+                    .ok_or_else(|| ClueListError::new(clue.line, format!("there's no {number} {} in the grid", name(direction))));
+                let entry = find(clue.number, direction)?;
+                let linked = clue.linked.iter()
+                    .map(|(number, linked_direction)| find(*number, linked_direction.unwrap_or(direction)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                covered.insert((direction, clue.number));
+                covered.extend(linked.iter().map(|entry| (entry.direction, entry.number)));
+                if let Some(enumeration) = &clue.enumeration {
+                    let length = entry.len + linked.iter().map(|entry| entry.len).sum::<usize>();
+                    if enumeration.length() != length {
+                        let label: String = clue.linked.iter().map(|(number, _)| format!("/{number}")).collect();
+                        return Err(ClueListError::new(clue.line, format!("({enumeration}) is {} letters but {}{label} {} has {length}", enumeration.length(), clue.number, name(direction))))
+                    }
+                }
+                let clues = match direction {
+                    Direction::Across => &mut crossword.across,
+                    Direction::Down => &mut crossword.down,
+                };
+                if let Some(found) = clues.get_mut(&entry.id()) {
+                    found.hint = clue.hint.clone();
+                    found.enumeration = clue.enumeration.clone();
+                    found.linked = linked.iter().map(|entry| entry.id()).collect();
+                }
+            }
+        }
+        if let Some(entry) = entries.iter().find(|entry| !covered.contains(&(entry.direction, entry.number))) {
+            return Err(ClueListError::new(0, format!("{} {} has no clue", entry.number, name(entry.direction))))
+        }
+
+        crossword.set_metadata(PuzzleMetadata {
+            author: list.header("author").map(str::to_string),
+            publication: list.header("publication").map(str::to_string),
+            publication_date: list.header("date").map(str::to_string),
+            difficulty: list.header("difficulty").and_then(|x| x.parse().ok()),
+            copyright: list.header("copyright").map(str::to_string),
+            notes: list.header("notes").map(str::to_string),
+            source_image: None,
+        });
+        Ok((crossword, solution))
+    }
+
+    /// Writes the crossword as a clue list, renumbering it the standard way.
+    /// The grid holds the solution if there is one, or `.` for every open
+    /// square if not.
+    pub fn to_clue_list(&self, title: &str, solution: Option<&Solution>) -> Result<ClueList, GridSizeError> {
+        let crossword = self.renumbered()?;
+        let grid = crossword.grid()?;
+
+        let metadata = self.metadata();
+        let headers = [
+            ("title", Some(title.to_string())),
+            ("author", metadata.author.clone()),
+            ("publication", metadata.publication.clone()),
+            ("date", metadata.publication_date.clone()),
+            ("difficulty", metadata.difficulty.map(|x| x.to_string())),
+            ("copyright", metadata.copyright.clone()),
+            ("notes", metadata.notes.as_ref().map(|x| x.split_whitespace().collect::<Vec<_>>().join(" "))),
+        ];
+        let headers = headers.into_iter()
+            .filter_map(|(key, value)| value.filter(|x| !x.trim().is_empty()).map(|value| (key.to_string(), value)))
+            .collect();
+
+        let rows = (0..grid.height())
+            .map(|y| (0..grid.width())
+                .map(|x| match grid.is_block(x, y) {
+                    true => None,
+                    false => Some(solution.and_then(|solution| solution.letter(x, y)).cloned().unwrap_or_default()),
+                })
+                .collect())
+            .collect();
+
+        // entries carrying on a linked clue are left out unless they have a hint.
+        let members: HashSet<&str> = crossword.across.values()
+            .chain(crossword.down.values())
+            .flat_map(|clue| clue.linked.iter().map(String::as_str))
+            .collect();
+        let number = |id: &str| id.trim_end_matches(|c: char| !c.is_ascii_digit()).parse::<usize>().ok();
+        let listed = |direction: Direction| -> Vec<ListedClue> {
+            let clues = match direction {
+                Direction::Across => &crossword.across,
+                Direction::Down => &crossword.down,
+            };
+            let mut ids: Vec<&String> = clues.keys()
+                .filter(|id| !members.contains(id.as_str()) || !clues[*id].hint.trim().is_empty())
+                .collect();
+            ids.sort_by_key(|id| clue_order(id));
+            ids.into_iter()
+                .filter_map(|id| {
+                    let clue = &clues[id];
+                    let linked = clue.linked.iter()
+                        .filter_map(|linked| {
+                            let linked_direction = match crossword.across.contains_key(linked) {
+                                true => Direction::Across,
+                                false => Direction::Down,
+                            };
+                            Some((number(linked)?, Some(linked_direction).filter(|x| *x != direction)))
+                        })
+                        .collect();
+                    Some(ListedClue {
+                        line: 0,
+                        number: number(id)?,
+                        linked,
+                        hint: clue.hint.split_whitespace().collect::<Vec<_>>().join(" "),
+                        enumeration: Some(clue.enumeration.clone().unwrap_or_else(|| Enumeration::single(crossword.answer_length(clue)))),
+                    })
+                })
+                .collect()
+        };
+
+        Ok(ClueList { headers, rows, grid_line: 0, across: listed(Direction::Across), down: listed(Direction::Down) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::ClueList;
+    use crate::crossword::Crossword;
+    use crate::crossword::test_fixtures::{assert_same_hints, demo_answers};
+
+    const RING: &str = "Title: Ring
+Author: Bob
+
+C A T
+O # E
+T E N
+
+Across
+1 Pet (3)
+3. Digit
+
+Down:
+1 Baby bed (3)
+2 Ten (1-2)
+";
+
+    fn error(text: &str) -> (usize, String) {
+        let e = match ClueList::parse(text) {
+            Ok(list) => Crossword::from_clue_list(&list).unwrap_err(),
+            Err(e) => e,
+        };
+        (e.line, e.reason)
+    }
+
+    #[test]
+    fn test_from_clue_list() {
+        assert!(ClueList::is_clue_list(RING.as_bytes()));
+        let list = ClueList::parse(RING).unwrap();
+        assert_eq!(list.title(), Some("Ring"));
+        let (cw, solution) = Crossword::from_clue_list(&list).unwrap();
+        assert!(cw.validate().is_ok());
+        let hints: HashMap<&str, &str> = cw.hints().collect();
+        assert_eq!(hints, HashMap::from([("1a", "Pet"), ("3a", "Digit"), ("1d", "Baby bed"), ("2d", "Ten")]));
+        assert_eq!(cw.metadata().author.as_deref(), Some("Bob"));
+        assert!(cw.letter(0, 0).is_some_and(|x| x.is_blank()));
+        assert_eq!(solution.unwrap().letter(2, 1), Some(&'E'.into()));
+
+        let blank = RING.replace("C A T\nO # E\nT E N", "...\n.#.\n...");
+        let (_, solution) = Crossword::from_clue_list(&ClueList::parse(&blank).unwrap()).unwrap();
+        assert!(solution.is_none());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(error(&RING.replace("Author", "Editor")).0, 2);
+        assert_eq!(error(&RING.replace("O # E", "O % E")).0, 5);
+        assert_eq!(error(&RING.replace("O # E", "O #")).0, 5);
+        assert_eq!(error(&RING.replace("O # E", "O # .")).0, 5);
+        assert_eq!(error(&RING.replace("O # E", "O # [E")).1, "square 3 starts with `[` but has no `]`");
+        assert_eq!(error(&RING.replace("1 Pet (3)", "Pet (3)")).0, 9);
+        assert_eq!(error(&RING.replace("1 Pet (3)", "1 Pet (4)")).0, 9);
+        assert_eq!(error(&RING.replace("1 Pet (3)", "5 Pet (3)")).0, 9);
+        assert_eq!(error(&RING.replace("3. Digit", "1 Pet")).0, 10);
+        assert_eq!(error(&RING.replace("2 Ten (1-2)", "")), (0, "2 down has no clue".to_string()));
+        assert_eq!(error("Across\n1 Pet").1, "there's no grid before the clues");
+    }
+
+    #[test]
+    fn test_linked_clues() {
+        let linked = RING.replace("1 Pet (3)", "1/2d Pet, ten (3,3)").replace("2 Ten (1-2)\n", "");
+        let (cw, _) = Crossword::from_clue_list(&ClueList::parse(&linked).unwrap()).unwrap();
+        assert!(cw.validate().is_ok());
+        assert_eq!(cw.across["1a"].linked, ["2d"]);
+        assert_eq!(cw.across["1a"].enumeration.as_ref().map(|x| x.length()), Some(6));
+        assert_eq!(cw.down["2d"].hint, "");
+
+        let text = cw.to_clue_list("Ring", None).unwrap().to_string();
+        assert!(text.contains("\nAcross\n1/2d Pet, ten (3,3)\n3 Digit (3)\n\nDown\n1 Baby bed (3)\n"));
+        assert!(!text.contains("\n2 "));
+
+        assert_eq!(error(&linked.replace("(3,3)", "(3,4)")), (9, "(3,4) is 7 letters but 1/2 across has 6".to_string()));
+        assert_eq!(error(&linked.replace("1/2d", "1/4d")), (9, "there's no 4 down in the grid".to_string()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cw = Crossword::demo_grid();
+        let solution = cw.solution(&demo_answers(true)).unwrap();
+
+        let text = cw.to_clue_list("Demo", Some(&solution)).unwrap().to_string();
+        assert!(text.starts_with("Title: Demo\n\n[FOR]ORALLTH\nA###I###\n"));
+        assert!(text.contains("\nAcross\n1 For all the money that e'er I had (8)\n"));
+        let list = ClueList::parse(&text).unwrap();
+        let (back, back_solution) = Crossword::from_clue_list(&list).unwrap();
+        assert_eq!(back_solution, Some(solution));
+        assert_same_hints(&cw, &back);
+
+        let blank = cw.to_clue_list("Demo", None).unwrap().to_string();
+        assert!(blank.contains("\n........\n.###.###\n"));
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+
+use super::MAX_GRID_SIZE;
+
+/// The lengths of the words in an answer, as printed after a clue: `(3,4)`
+/// for two words or `(5-3)` for a hyphenated one. Along with the text it
+/// gives the breaks between squares, so they can be drawn on the grid. For
+/// linked clues it covers the whole group of entries.
+///
+/// It is sent as `{"text": "5-3", "length": 8, "breaks": [{"after": 5, "kind": "hyphen"}]}`,
+/// and can be uploaded as just the text, e.g. `"5-3"`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "EnumerationData")]
+pub struct Enumeration {
+    text: String,
+    length: usize,
+    breaks: Vec<WordBreak>,
+}
+
+/// A break between the square `after` squares in and the one after it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WordBreak {
+    pub after: usize,
+    pub kind: BreakKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BreakKind {
+    /// Between two words, written `,` or a space.
+    Word,
+    Hyphen,
+}
+
+/// Either form an enumeration can be uploaded in. Only the text is read
+/// from the full form; the breaks are always worked out again from it.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EnumerationData {
+    Text(String),
+    Full { text: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumerationError(String);
+
+impl Display for EnumerationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` isn't an enumeration like 3,4 or 5-3", self.0)
+    }
+}
+
+impl std::error::Error for EnumerationError {}
+
+impl Enumeration {
+    /// Reads `3,4`, `5-3`, `2 3` and the like, with or without brackets. No
+    /// word can be longer than the largest grid is wide.
+    pub fn parse(s: &str) -> Result<Self, EnumerationError> {
+        let error = || EnumerationError(s.to_string());
+        let inside = s.trim();
+        let inside = inside.strip_prefix('(').and_then(|x| x.strip_suffix(')')).unwrap_or(inside).trim();
+
+        let mut text = String::new();
+        let mut length: usize = 0;
+        let mut breaks = Vec::new();
+        let mut rest = inside;
+        loop {
+            let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+            let word = rest[..digits].parse::<usize>().ok().filter(|x| (1..=MAX_GRID_SIZE).contains(x)).ok_or_else(error)?;
+            text.push_str(&word.to_string());
+            length = length.checked_add(word).ok_or_else(error)?;
+            rest = &rest[digits..];
+            if rest.is_empty() {
+                break
+            }
+            let separator = rest.chars().take_while(|c| matches!(c, ',' | '-' | ' ')).count();
+            let kind = match &rest[..separator] {
+                "" => return Err(error()),
+                x if x.trim() == "-" => BreakKind::Hyphen,
+                x if x.trim().is_empty() || x.trim() == "," => BreakKind::Word,
+                _ => return Err(error()),
+            };
+            text.push(match kind {
+                BreakKind::Word => ',',
+                BreakKind::Hyphen => '-',
+            });
+            breaks.push(WordBreak { after: length, kind });
+            rest = &rest[separator..];
+        }
+        Ok(Self { text, length, breaks })
+    }
+
+    /// Splits an enumeration in brackets off the end of a hint, so
+    /// `Pet (3)` is `Pet` and `3`. A hint without one is kept whole.
+    pub fn split_hint(hint: &str) -> (String, Option<Self>) {
+        let split = hint.trim_end()
+            .strip_suffix(')')
+            .and_then(|x| x.rsplit_once('('))
+            .and_then(|(before, inside)| Self::parse(inside).ok().map(|enumeration| (before.trim_end(), enumeration)));
+        match split {
+            Some((before, enumeration)) if !before.is_empty() => (before.to_string(), Some(enumeration)),
+            _ => (hint.to_string(), None),
+        }
+    }
+
+    /// One word filling `length` squares.
+    pub fn single(length: usize) -> Self {
+        Self { text: length.to_string(), length, breaks: Vec::new() }
+    }
+
+    /// How the enumeration is written, without brackets, e.g. `3,4`.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The number of squares the answer fills.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn breaks(&self) -> &[WordBreak] {
+        &self.breaks
+    }
+}
+
+impl TryFrom<EnumerationData> for Enumeration {
+    type Error = EnumerationError;
+
+    fn try_from(data: EnumerationData) -> Result<Self, Self::Error> {
+        match data {
+            EnumerationData::Text(text) | EnumerationData::Full { text } => Self::parse(&text),
+        }
+    }
+}
+
+impl Display for Enumeration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BreakKind, Enumeration, WordBreak};
+
+    #[test]
+    fn test_enumeration() {
+        let enumeration = Enumeration::parse("(3, 4)").unwrap();
+        assert_eq!(enumeration.as_str(), "3,4");
+        assert_eq!(enumeration.length(), 7);
+        assert_eq!(enumeration.breaks(), [WordBreak { after: 3, kind: BreakKind::Word }]);
+
+        let enumeration = Enumeration::parse("2,5-3").unwrap();
+        assert_eq!(enumeration.length(), 10);
+        assert_eq!(enumeration.breaks(), [WordBreak { after: 2, kind: BreakKind::Word }, WordBreak { after: 7, kind: BreakKind::Hyphen }]);
+
+        assert_eq!(Enumeration::parse("50").unwrap().length(), 50);
+        for bad in ["", "3,", "three", "3,,4", "3-,4", "0", "(3", "51", "18446744073709551615,1"] {
+            assert!(Enumeration::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_split_hint() {
+        let (hint, enumeration) = Enumeration::split_hint("Mother's ruin (3) ");
+        assert_eq!(hint, "Mother's ruin");
+        assert_eq!(enumeration, Some(Enumeration::single(3)));
+        assert_eq!(Enumeration::split_hint("Pet (dog or cat)"), ("Pet (dog or cat)".to_string(), None));
+        assert_eq!(Enumeration::split_hint("(5)"), ("(5)".to_string(), None));
+    }
+
+    #[test]
+    fn test_enumeration_json() {
+        let enumeration: Enumeration = serde_json::from_str(r#""5-3""#).unwrap();
+        let json = serde_json::to_string(&enumeration).unwrap();
+        assert_eq!(json, r#"{"text":"5-3","length":8,"breaks":[{"after":5,"kind":"hyphen"}]}"#);
+        assert_eq!(serde_json::from_str::<Enumeration>(&json).unwrap(), enumeration);
+        assert!(serde_json::from_str::<Enumeration>(r#""5-""#).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+
+use super::{CellStyle, CellValue, Direction};
+
+/// The shape of a crossword: its size, which squares are blocked out, how
+/// the others are styled and barred, and what has been written in them.
+/// Squares are stored row by row, with `x` counting columns and `y` counting
+/// rows from the top left.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    blocks: Vec<bool>,
+    letters: Vec<CellValue>,
+    styles: Vec<CellStyle>,
+}
+
+/// A run of two or more open squares, which is where a clue's answer goes.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub number: usize,
+    pub direction: Direction,
+    pub x: usize,
+    pub y: usize,
+    pub len: usize,
+}
+
+impl Entry {
+    /// The id clues are stored under, e.g. `3a` or `14d`.
+    pub fn id(&self) -> String {
+        match self.direction {
+            Direction::Across => format!("{}a", self.number),
+            Direction::Down => format!("{}d", self.number),
+        }
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.len).map(|i| match self.direction {
+            Direction::Across => (self.x + i, self.y),
+            Direction::Down => (self.x, self.y + i),
+        })
+    }
+}
+
+impl Grid {
+    /// A grid with every square open and empty.
+    pub fn new(width: usize, height: usize) -> Self {
+        Grid {
+            width,
+            height,
+            blocks: vec![false; width * height],
+            letters: vec![CellValue::default(); width * height],
+            styles: vec![CellStyle::default(); width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        match x < self.width && y < self.height {
+            true => Some(y * self.width + x),
+            false => None,
+        }
+    }
+
+    /// Squares outside the grid count as blocks.
+    pub fn is_block(&self, x: usize, y: usize) -> bool {
+        match self.index(x, y) {
+            Some(i) => self.blocks[i],
+            None => true,
+        }
+    }
+
+    pub fn set_block(&mut self, x: usize, y: usize, block: bool) {
+        if let Some(i) = self.index(x, y) {
+            self.blocks[i] = block;
+            if block {
+                self.letters[i] = CellValue::default();
+                self.styles[i] = CellStyle::default();
+            }
+        }
+    }
+
+    /// What's written in an open square, which may be blank.
+    pub fn letter(&self, x: usize, y: usize) -> Option<&CellValue> {
+        match self.is_block(x, y) {
+            true => None,
+            false => self.index(x, y).map(|i| &self.letters[i]),
+        }
+    }
+
+    pub fn set_letter(&mut self, x: usize, y: usize, letter: CellValue) {
+        if let Some(i) = self.index(x, y).filter(|i| !self.blocks[*i]) {
+            self.letters[i] = letter;
+        }
+    }
+
+    /// How an open square is styled. Blocks and squares outside the grid are plain.
+    pub fn style(&self, x: usize, y: usize) -> CellStyle {
+        match self.is_block(x, y) {
+            true => CellStyle::default(),
+            false => self.index(x, y).map(|i| self.styles[i]).unwrap_or_default(),
+        }
+    }
+
+    pub fn set_style(&mut self, x: usize, y: usize, style: CellStyle) {
+        if let Some(i) = self.index(x, y).filter(|i| !self.blocks[*i]) {
+            self.styles[i] = style;
+        }
+    }
+
+    /// Whether there's a bar after a square going in a direction, i.e. on
+    /// its right edge for across and its bottom edge for down.
+    pub fn bar_after(&self, x: usize, y: usize, direction: Direction) -> bool {
+        let style = self.style(x, y);
+        match direction {
+            Direction::Across => style.bar_right,
+            Direction::Down => style.bar_bottom,
+        }
+    }
+
+    /// Whether an entry can carry on from one square to the next.
+    fn continues(&self, x: usize, y: usize, direction: Direction) -> bool {
+        let next = match direction {
+            Direction::Across => (x + 1, y),
+            Direction::Down => (x, y + 1),
+        };
+        !self.is_block(x, y) && !self.bar_after(x, y, direction) && !self.is_block(next.0, next.1)
+    }
+
+    fn starts(&self, x: usize, y: usize, direction: Direction) -> bool {
+        let continued = match direction {
+            Direction::Across => x > 0 && self.continues(x - 1, y, direction),
+            Direction::Down => y > 0 && self.continues(x, y - 1, direction),
+        };
+        !continued && self.continues(x, y, direction)
+    }
+
+    fn entry_len(&self, x: usize, y: usize, direction: Direction) -> usize {
+        1 + (0..).take_while(|i| match direction {
+            Direction::Across => self.continues(x + i, y, direction),
+            Direction::Down => self.continues(x, y + i, direction),
+        }).count()
+    }
+
+    /// Numbers the grid the usual way: reading row by row, every square that
+    /// starts an across or down entry gets the next number. Entries end at
+    /// blocks and bars. They are returned in number order, with across
+    /// before down.
+    pub fn entries(&self) -> Vec<Entry> {
+        let mut entries = Vec::new();
+        let mut number = 0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let directions: Vec<Direction> = [Direction::Across, Direction::Down].into_iter()
+                    .filter(|direction| self.starts(x, y, *direction))
+                    .collect();
+                if directions.is_empty() {
+                    continue;
+                }
+                number += 1;
+                for direction in directions {
+                    entries.push(Entry { number, direction, x, y, len: self.entry_len(x, y, direction) });
+                }
+            }
+        }
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CellStyle, Direction, Grid};
+
+    fn grid_from_rows(rows: &[&str]) -> Grid {
+        let mut grid = Grid::new(rows[0].len(), rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                grid.set_block(x, y, c == '#');
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn test_numbering() {
+        let grid = grid_from_rows(&[
+            "...#",
+            ".#..",
+            "....",
+        ]);
+        let ids: Vec<String> = grid.entries().iter().map(|x| x.id()).collect();
+        assert_eq!(ids, vec!["1a", "1d", "2d", "3a", "4d", "5a"]);
+    }
+
+    #[test]
+    fn test_entry_lengths_and_cells() {
+        let grid = grid_from_rows(&[
+            "...#",
+            ".#..",
+            "....",
+        ]);
+        let entries = grid.entries();
+        let four_down = entries.iter().find(|x| x.number == 4 && x.direction == Direction::Down).unwrap();
+        assert_eq!(four_down.len, 2);
+        assert_eq!(four_down.cells().collect::<Vec<_>>(), vec![(3, 1), (3, 2)]);
+    }
+
+    #[test]
+    fn test_single_squares_are_not_entries() {
+        let grid = grid_from_rows(&[
+            ".#.",
+            "#.#",
+        ]);
+        assert!(grid.entries().is_empty());
+    }
+
+    #[test]
+    fn test_bars_end_entries() {
+        let mut grid = grid_from_rows(&[
+            "....",
+            "....",
+        ]);
+        grid.set_style(1, 0, CellStyle { bar_right: true, ..CellStyle::default() });
+        grid.set_style(3, 0, CellStyle { bar_bottom: true, circled: true, ..CellStyle::default() });
+        let ids: Vec<String> = grid.entries().iter().map(|x| format!("{}:{}", x.id(), x.len)).collect();
+        assert_eq!(ids, vec!["1a:2", "1d:2", "2d:2", "3a:2", "3d:2", "4a:4"]);
+        assert!(grid.bar_after(3, 0, Direction::Down) && !grid.bar_after(3, 0, Direction::Across));
+    }
+
+    #[test]
+    fn test_blocks_have_no_letters() {
+        let mut grid = Grid::new(2, 1);
+        grid.set_letter(0, 0, 'A'.into());
+        grid.set_block(0, 0, true);
+        grid.set_letter(0, 0, 'B'.into());
+        assert_eq!(grid.letter(0, 0), None);
+        assert!(grid.letter(1, 0).is_some_and(|x| x.is_blank()));
+    }
+}
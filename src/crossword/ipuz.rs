@@ -0,0 +1,626 @@
+use std::{collections::HashMap, fmt::{self, Display}};
+
+use serde_json::{json, Map, Value};
+
+use super::{CellStyle, CellValue, Crossword, Direction, Enumeration, Grid, GridSizeError, PuzzleMetadata, Solution, MAX_CELL_LENGTH, MAX_GRID_SIZE};
+
+const VERSION: &str = "http://ipuz.org/v2";
+const CROSSWORD_KIND: &str = "http://ipuz.org/crossword#1";
+
+/// An ipuz crossword, checked to be one this server can read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ipuz {
+    value: Map<String, Value>,
+}
+
+/// Something in an ipuz file that can't be read, with the path to the
+/// offending value, e.g. `puzzle[2][3]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpuzError {
+    pub path: String,
+    pub reason: String,
+}
+
+impl IpuzError {
+    fn new(path: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self { path: path.into(), reason: reason.into() }
+    }
+}
+
+impl Display for IpuzError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, "{}", self.reason),
+            false => write!(f, "{}: {}", self.path, self.reason),
+        }
+    }
+}
+
+impl std::error::Error for IpuzError {}
+
+/// A square from the `puzzle` grid.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct IpuzCell {
+    block: bool,
+    /// The number printed in the square, if it has one.
+    label: Option<String>,
+    style: CellStyle,
+    /// Bars on the left and top edges, which belong to the squares before.
+    bar_left: bool,
+    bar_top: bool,
+    value: Option<String>,
+}
+
+impl Ipuz {
+    /// Whether the data looks like an ipuz file rather than some other JSON.
+    pub fn is_ipuz(data: &[u8]) -> bool {
+        let start: Vec<u8> = data.iter().take(512).copied().filter(|b| !b.is_ascii_whitespace()).collect();
+        (start.starts_with(b"{") || start.starts_with(b"ipuz(")) && String::from_utf8_lossy(data).contains("ipuz.org")
+    }
+
+    /// Reads an ipuz file, which may be wrapped in `ipuz(...)`.
+    pub fn parse(data: &str) -> Result<Self, IpuzError> {
+        let data = data.trim();
+        let data = data.strip_prefix("ipuz(")
+            .and_then(|x| x.strip_suffix(')'))
+            .unwrap_or(data);
+        let value: Value = serde_json::from_str(data).map_err(|e| IpuzError::new("", format!("the file isn't valid JSON: {e}")))?;
+        let value = match value {
+            Value::Object(value) => value,
+            _ => return Err(IpuzError::new("", "the file should be a JSON object")),
+        };
+
+        match value.get("version").and_then(Value::as_str) {
+            Some(version) if version.starts_with("http://ipuz.org/v") => (),
+            Some(_) => return Err(IpuzError::new("version", "isn't an ipuz version")),
+            None => return Err(IpuzError::new("version", "is missing")),
+        }
+
+        let kinds: Vec<&str> = match value.get("kind").and_then(Value::as_array) {
+            Some(kinds) => kinds.iter().filter_map(Value::as_str).collect(),
+            None => return Err(IpuzError::new("kind", "is missing")),
+        };
+        if kinds.iter().any(|kind| kind.starts_with("http://ipuz.org/crossword/diagramless")) {
+            return Err(IpuzError::new("kind", "diagramless crosswords aren't supported"))
+        }
+        if !kinds.iter().any(|kind| kind.starts_with("http://ipuz.org/crossword")) {
+            return Err(IpuzError::new("kind", format!("only crosswords are supported, not {}", kinds.join(", "))))
+        }
+        Ok(Self { value })
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.str("title").filter(|title| !title.trim().is_empty())
+    }
+
+    pub fn to_json(&self) -> String {
+        Value::Object(self.value.clone()).to_string()
+    }
+
+    fn str(&self, key: &str) -> Option<&str> {
+        self.value.get(key).and_then(Value::as_str)
+    }
+
+    fn dimensions(&self) -> Result<(usize, usize), IpuzError> {
+        let dimension = |key: &str| {
+            let path = format!("dimensions.{key}");
+            match self.value.get("dimensions").and_then(|x| x.get(key)).and_then(Value::as_u64) {
+                Some(0) => Err(IpuzError::new(path, "must be at least 1")),
+                Some(x) if x as usize > MAX_GRID_SIZE => Err(IpuzError::new(path, format!("must be at most {MAX_GRID_SIZE}"))),
+                Some(x) => Ok(x as usize),
+                None => Err(IpuzError::new(path, "is missing")),
+            }
+        };
+        Ok((dimension("width")?, dimension("height")?))
+    }
+
+    /// The rows of a grid such as `puzzle` or `solution`, checked to match the dimensions.
+    fn rows(&self, key: &str, width: usize, height: usize) -> Result<Option<Vec<&Vec<Value>>>, IpuzError> {
+        let rows = match self.value.get(key) {
+            Some(Value::Array(rows)) => rows,
+            Some(Value::Null) | None => return Ok(None),
+            Some(_) => return Err(IpuzError::new(key, "should be a list of rows")),
+        };
+        if rows.len() != height {
+            return Err(IpuzError::new(key, format!("has {} rows but the puzzle is {height} high", rows.len())))
+        }
+        rows.iter().enumerate()
+            .map(|(y, row)| match row.as_array() {
+                Some(row) if row.len() == width => Ok(row),
+                Some(row) => Err(IpuzError::new(format!("{key}[{y}]"), format!("has {} squares but the puzzle is {width} wide", row.len()))),
+                None => Err(IpuzError::new(format!("{key}[{y}]"), "should be a list of squares")),
+            })
+            .collect::<Result<Vec<&Vec<Value>>, IpuzError>>()
+            .map(Some)
+    }
+
+    fn is_block(&self, value: &Value) -> bool {
+        let block = self.value.get("block").cloned().unwrap_or(json!("#"));
+        *value == block
+    }
+
+    fn puzzle_cell(&self, path: &str, value: &Value) -> Result<IpuzCell, IpuzError> {
+        if self.is_block(value) {
+            return Ok(IpuzCell { block: true, ..IpuzCell::default() })
+        }
+        let empty = self.value.get("empty").cloned().unwrap_or(json!(0));
+        match value {
+            Value::Null => Err(IpuzError::new(path, "squares outside the grid aren't supported")),
+            x if *x == empty => Ok(IpuzCell::default()),
+            Value::Number(n) => Ok(IpuzCell { label: Some(n.to_string()), ..IpuzCell::default() }),
+            Value::String(s) => Ok(IpuzCell { label: Some(s.clone()).filter(|s| !s.is_empty()), ..IpuzCell::default() }),
+            Value::Object(cell) => {
+                let mut parsed = match cell.get("cell") {
+                    Some(inner) => self.puzzle_cell(&format!("{path}.cell"), inner)?,
+                    None => IpuzCell::default(),
+                };
+                if let Some(style) = cell.get("style") {
+                    parsed.style.circled = style.get("shapebg").and_then(Value::as_str) == Some("circle");
+                    parsed.style.shaded = style.get("highlight") == Some(&Value::Bool(true)) || style.get("color").is_some();
+                    if let Some(barred) = style.get("barred") {
+                        let sides = barred.as_str()
+                            .filter(|sides| sides.chars().all(|side| "TRBL".contains(side)))
+                            .ok_or_else(|| IpuzError::new(format!("{path}.style.barred"), "should be some of T, R, B and L"))?;
+                        parsed.style.bar_right = sides.contains('R');
+                        parsed.style.bar_bottom = sides.contains('B');
+                        parsed.bar_left = sides.contains('L');
+                        parsed.bar_top = sides.contains('T');
+                    }
+                }
+                parsed.value = cell.get("value").and_then(Value::as_str).map(str::to_string);
+                Ok(parsed)
+            },
+            _ => Err(IpuzError::new(path, "isn't a square")),
+        }
+    }
+
+    /// The answer or saved letters in a square of `solution` or `saved`.
+    fn letter(&self, path: &str, value: &Value) -> Result<Option<CellValue>, IpuzError> {
+        let letter = match value {
+            x if self.is_block(x) => return Ok(None),
+            Value::Null => return Ok(None),
+            Value::String(s) => s,
+            Value::Object(cell) => match cell.get("value") {
+                Some(Value::String(s)) => s,
+                _ => return Ok(None),
+            },
+            _ => return Err(IpuzError::new(path, "should be a letter")),
+        };
+        cell_value(path, letter)
+    }
+}
+
+/// Reads a square's letters, with `None` for an empty square.
+fn cell_value(path: &str, letters: &str) -> Result<Option<CellValue>, IpuzError> {
+    match CellValue::parse(letters, *MAX_CELL_LENGTH) {
+        Ok(value) if value.is_blank() => Ok(None),
+        Ok(value) => Ok(Some(value)),
+        Err(e) => Err(IpuzError::new(path, e.to_string())),
+    }
+}
+
+/// A clue as it's written in an ipuz file.
+struct IpuzClue {
+    number: String,
+    text: String,
+    enumeration: Option<Enumeration>,
+    /// The entries the clue carries on into, by number, with the direction
+    /// where one is given.
+    continued: Vec<(String, Option<Direction>)>,
+}
+
+fn clue_number(value: &Value) -> Option<String> {
+    match value {
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// Reads a clue from either `[1, "text"]` or `{"number": 1, "clue": "text"}`,
+/// along with an object's `enumeration` and `continued` entries. Without an
+/// `enumeration`, one on the end of the text is used.
+fn read_clue(path: &str, clue: &Value) -> Result<IpuzClue, IpuzError> {
+    let (number, text) = match clue {
+        Value::Array(parts) => match parts.as_slice() {
+            [n, Value::String(text)] => clue_number(n)
+                .map(|n| (n, text.clone()))
+                .ok_or_else(|| IpuzError::new(format!("{path}[0]"), "should be the clue's number"))?,
+            _ => return Err(IpuzError::new(path, "should be a number and the clue")),
+        },
+        Value::Object(fields) => {
+            if fields.contains_key("cells") {
+                return Err(IpuzError::new(format!("{path}.cells"), "clues with their own squares aren't supported"))
+            }
+            let n = fields.get("number").and_then(clue_number)
+                .ok_or_else(|| IpuzError::new(format!("{path}.number"), "is missing"))?;
+            let text = fields.get("clue").and_then(Value::as_str)
+                .ok_or_else(|| IpuzError::new(format!("{path}.clue"), "is missing"))?;
+            (n, text.to_string())
+        },
+        Value::String(_) => return Err(IpuzError::new(path, "clues without a number aren't supported")),
+        _ => return Err(IpuzError::new(path, "isn't a clue")),
+    };
+
+    let (text, mut enumeration) = Enumeration::split_hint(&text);
+    let mut continued = Vec::new();
+    if let Value::Object(fields) = clue {
+        match fields.get("enumeration") {
+            None | Some(Value::Null) => (),
+            Some(Value::String(x)) if x.trim().is_empty() => (),
+            Some(value) => {
+                let written = clue_number(value).unwrap_or_default();
+                enumeration = Some(Enumeration::parse(&written).map_err(|e| IpuzError::new(format!("{path}.enumeration"), e.to_string()))?);
+            },
+        }
+        let links = match fields.get("continued") {
+            None | Some(Value::Null) => &[][..],
+            Some(Value::Array(links)) => links.as_slice(),
+            Some(_) => return Err(IpuzError::new(format!("{path}.continued"), "should be a list of clues")),
+        };
+        for (i, link) in links.iter().enumerate() {
+            let link_path = format!("{path}.continued[{i}]");
+            let number = link.get("number").and_then(clue_number)
+                .ok_or_else(|| IpuzError::new(format!("{link_path}.number"), "is missing"))?;
+            let direction = match link.get("direction").and_then(Value::as_str).map(|x| x.split(':').next().unwrap_or_default()) {
+                None => None,
+                Some("Across") => Some(Direction::Across),
+                Some("Down") => Some(Direction::Down),
+                Some(other) => return Err(IpuzError::new(format!("{link_path}.direction"), format!("should be Across or Down, not {other}"))),
+            };
+            continued.push((number, direction));
+        }
+    }
+    Ok(IpuzClue { number, text, enumeration, continued })
+}
+
+/// ipuz dates are written mm/dd/yyyy.
+fn ipuz_date_to_iso(date: &str) -> String {
+    chrono::NaiveDate::parse_from_str(date, "%m/%d/%Y")
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|_| date.to_string())
+}
+
+fn iso_date_to_ipuz(date: &str) -> String {
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|date| date.format("%m/%d/%Y").to_string())
+        .unwrap_or_else(|_| date.to_string())
+}
+
+impl Crossword {
+    /// Builds a crossword from an ipuz file, along with its solution if it
+    /// has one. Clues are matched to the grid by the numbers printed in the
+    /// squares. Circles, shading (`highlight` or a `color`) and bars are
+    /// kept as styles.
+    pub fn from_ipuz(ipuz: &Ipuz) -> Result<(Crossword, Option<Solution>), IpuzError> {
+        let (width, height) = ipuz.dimensions()?;
+        let puzzle = ipuz.rows("puzzle", width, height)?.ok_or_else(|| IpuzError::new("puzzle", "is missing"))?;
+
+        let mut grid = Grid::new(width, height);
+        let mut labels: HashMap<String, (usize, usize)> = HashMap::new();
+        for (y, row) in puzzle.iter().enumerate() {
+            for (x, value) in row.iter().enumerate() {
+                let cell = ipuz.puzzle_cell(&format!("puzzle[{y}][{x}]"), value)?;
+                grid.set_block(x, y, cell.block);
+                grid.set_style(x, y, cell.style);
+                // a bar on the left or top is the square before's right or bottom bar.
+                if cell.bar_left && x > 0 {
+                    grid.set_style(x - 1, y, CellStyle { bar_right: true, ..grid.style(x - 1, y) });
+                }
+                if cell.bar_top && y > 0 {
+                    grid.set_style(x, y - 1, CellStyle { bar_bottom: true, ..grid.style(x, y - 1) });
+                }
+                if let Some(label) = cell.label {
+                    labels.insert(label, (x, y));
+                }
+                if let Some(value) = cell.value {
+                    if let Some(c) = cell_value(&format!("puzzle[{y}][{x}].value"), &value)? {
+                        grid.set_letter(x, y, c);
+                    }
+                }
+            }
+        }
+
+        if let Some(saved) = ipuz.rows("saved", width, height)? {
+            for (y, row) in saved.iter().enumerate() {
+                for (x, value) in row.iter().enumerate() {
+                    if let Some(c) = ipuz.letter(&format!("saved[{y}][{x}]"), value)? {
+                        grid.set_letter(x, y, c);
+                    }
+                }
+            }
+        }
+
+        let mut crossword = Crossword::from_grid(&grid);
+        let entries = grid.entries();
+
+        let clues = match ipuz.value.get("clues") {
+            Some(Value::Object(clues)) => clues.clone(),
+            Some(_) => return Err(IpuzError::new("clues", "should map directions to lists of clues")),
+            None => Map::new(),
+        };
+        for (key, list) in &clues {
+            let direction = match key.split(':').next() {
+                Some("Across") => Direction::Across,
+                Some("Down") => Direction::Down,
+                _ => return Err(IpuzError::new(format!("clues.{key}"), "only Across and Down clues are supported")),
+            };
+            let list = list.as_array().ok_or_else(|| IpuzError::new(format!("clues.{key}"), "should be a list of clues"))?;
+            for (i, clue) in list.iter().enumerate() {
+                let path = format!("clues.{key}[{i}]");
+                let clue = read_clue(&path, clue)?;
+                let find = |number: &str, direction: Direction, path: &str| labels.get(number)
+                    .and_then(|(x, y)| entries.iter().find(|entry| entry.x == *x && entry.y == *y && entry.direction == direction))
+                    .ok_or_else(|| IpuzError::new(path, format!("there's no {} entry numbered {number} in the grid", format!("{direction:?}").to_lowercase())));
+                let entry = find(&clue.number, direction, &path)?;
+                let linked = clue.continued.iter()
+                    .enumerate()
+                    .map(|(j, (number, linked_direction))| find(number, linked_direction.unwrap_or(direction), &format!("{path}.continued[{j}]")).map(|x| x.id()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let clues = match direction {
+                    Direction::Across => &mut crossword.across,
+                    Direction::Down => &mut crossword.down,
+                };
+                if let Some(found) = clues.get_mut(&entry.id()) {
+                    found.hint = clue.text;
+                    found.enumeration = clue.enumeration;
+                    found.linked = linked;
+                }
+            }
+        }
+
+        let string = |key: &str| ipuz.str(key).map(str::to_string).filter(|x| !x.trim().is_empty());
+        crossword.set_metadata(PuzzleMetadata {
+            author: string("author"),
+            publication: string("publisher").or_else(|| string("publication")),
+            publication_date: string("date").map(|date| ipuz_date_to_iso(&date)),
+            difficulty: string("difficulty").and_then(|x| x.trim().parse().ok()).filter(|x| (1..=5).contains(x)),
+            copyright: string("copyright"),
+            notes: string("notes"),
+            source_image: None,
+        });
+
+        let solution = match ipuz.rows("solution", width, height)? {
+            Some(rows) => {
+                let mut letters = Vec::new();
+                for (y, row) in rows.iter().enumerate() {
+                    for (x, value) in row.iter().enumerate() {
+                        if grid.is_block(x, y) {
+                            continue;
+                        }
+                        match ipuz.letter(&format!("solution[{y}][{x}]"), value)? {
+                            Some(c) => letters.push(((x, y), c.to_uppercase())),
+                            None => return Err(IpuzError::new(format!("solution[{y}][{x}]"), "the square has no answer")),
+                        }
+                    }
+                }
+                Some(letters.into_iter().collect())
+            },
+            None => None,
+        };
+
+        Ok((crossword, solution))
+    }
+
+    /// Writes the crossword as ipuz, renumbering it the standard way.
+    pub fn to_ipuz(&self, title: &str, solution: Option<&Solution>) -> Result<Ipuz, GridSizeError> {
+        let crossword = self.renumbered()?;
+        let grid = crossword.grid()?;
+        let (width, height) = (grid.width(), grid.height());
+        let entries = grid.entries();
+        let numbers: HashMap<(usize, usize), usize> = entries.iter().map(|entry| ((entry.x, entry.y), entry.number)).collect();
+
+        let rows = |square: &dyn Fn(usize, usize) -> Value| -> Value {
+            (0..height).map(|y| (0..width).map(|x| square(x, y)).collect::<Vec<Value>>()).collect::<Vec<Vec<Value>>>().into()
+        };
+        let puzzle = rows(&|x, y| {
+            if grid.is_block(x, y) {
+                return json!("#")
+            }
+            let number = json!(numbers.get(&(x, y)).copied().unwrap_or(0));
+            let style = grid.style(x, y);
+            if style.is_plain() {
+                return number
+            }
+            let mut spec = Map::new();
+            if style.circled {
+                spec.insert("shapebg".to_string(), json!("circle"));
+            }
+            if style.shaded {
+                spec.insert("highlight".to_string(), json!(true));
+            }
+            let barred: String = [(style.bar_right, 'R'), (style.bar_bottom, 'B')].into_iter()
+                .filter_map(|(barred, side)| barred.then_some(side))
+                .collect();
+            if !barred.is_empty() {
+                spec.insert("barred".to_string(), json!(barred));
+            }
+            json!({ "cell": number, "style": spec })
+        });
+        let saved = rows(&|x, y| match grid.letter(x, y) {
+            None => json!("#"),
+            Some(c) if c.is_blank() => json!(""),
+            Some(c) => json!(c.as_str()),
+        });
+
+        let clue_list = |direction: Direction| -> Value {
+            let clues = match direction {
+                Direction::Across => &crossword.across,
+                Direction::Down => &crossword.down,
+            };
+            entries.iter()
+                .filter(|entry| entry.direction == direction)
+                .filter_map(|entry| clues.get(&entry.id()).map(|clue| {
+                    let mut written = json!({ "number": entry.number, "clue": clue.hint });
+                    if let Some(enumeration) = &clue.enumeration {
+                        written["enumeration"] = json!(enumeration.as_str());
+                    }
+                    if !clue.linked.is_empty() {
+                        written["continued"] = clue.linked.iter()
+                            .filter_map(|id| {
+                                let linked_direction = match crossword.across.contains_key(id) {
+                                    true => "Across",
+                                    false => "Down",
+                                };
+                                let number = id.trim_end_matches(|c: char| !c.is_ascii_digit()).parse::<usize>().ok()?;
+                                Some(json!({ "direction": linked_direction, "number": number }))
+                            })
+                            .collect::<Vec<Value>>()
+                            .into();
+                    }
+                    written
+                }))
+                .collect::<Vec<Value>>()
+                .into()
+        };
+
+        let mut value = Map::new();
+        value.insert("version".to_string(), json!(VERSION));
+        value.insert("kind".to_string(), json!([CROSSWORD_KIND]));
+        value.insert("dimensions".to_string(), json!({ "width": width, "height": height }));
+        value.insert("title".to_string(), json!(title));
+        value.insert("block".to_string(), json!("#"));
+        value.insert("empty".to_string(), json!(0));
+        value.insert("puzzle".to_string(), puzzle);
+        if (0..height).any(|y| (0..width).any(|x| grid.letter(x, y).is_some_and(|c| !c.is_blank()))) {
+            value.insert("saved".to_string(), saved);
+        }
+        if let Some(solution) = solution {
+            let answers = rows(&|x, y| match grid.is_block(x, y) {
+                true => json!("#"),
+                false => solution.letter(x, y).map_or(Value::Null, |c| json!(c.as_str())),
+            });
+            value.insert("solution".to_string(), answers);
+        }
+        value.insert("clues".to_string(), json!({ "Across": clue_list(Direction::Across), "Down": clue_list(Direction::Down) }));
+
+        let metadata = self.metadata();
+        let fields = [
+            ("author", metadata.author.clone()),
+            ("publisher", metadata.publication.clone()),
+            ("date", metadata.publication_date.as_deref().map(iso_date_to_ipuz)),
+            ("difficulty", metadata.difficulty.map(|x| x.to_string())),
+            ("copyright", metadata.copyright.clone()),
+            ("notes", metadata.notes.clone()),
+        ];
+        for (key, field) in fields {
+            if let Some(field) = field {
+                value.insert(key.to_string(), json!(field));
+            }
+        }
+
+        Ok(Ipuz { value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Ipuz, IpuzError};
+    use crate::crossword::{CellStyle, Crossword, StyledSquare};
+    use crate::crossword::test_fixtures::{assert_same_hints, demo_answers};
+
+    const RING: &str = r##"ipuz({
+        "version": "http://ipuz.org/v2",
+        "kind": ["http://ipuz.org/crossword#1"],
+        "dimensions": {"width": 3, "height": 3},
+        "title": "Ring",
+        "author": "Bob",
+        "date": "03/01/2024",
+        "puzzle": [[{"cell": 1, "style": {"shapebg": "circle"}}, 0, "2"], [0, "#", 0], [3, 0, 0]],
+        "solution": [["C", "A", "T"], ["o", "#", "E"], ["T", "E", "N"]],
+        "saved": [["C", "", ""], ["", "#", ""], ["", "", ""]],
+        "clues": {
+            "Across": [[1, "Pet"], {"number": 3, "clue": "Digit", "enumeration": "3"}],
+            "Down": [[1, "Baby bed"], ["2", "Ten"]]
+        }
+    })"##;
+
+    fn error(json: &str) -> IpuzError {
+        match Ipuz::parse(json) {
+            Ok(ipuz) => Crossword::from_ipuz(&ipuz).unwrap_err(),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn test_from_ipuz() {
+        assert!(Ipuz::is_ipuz(RING.as_bytes()));
+        let ipuz = Ipuz::parse(RING).unwrap();
+        assert_eq!(ipuz.title(), Some("Ring"));
+        let (cw, solution) = Crossword::from_ipuz(&ipuz).unwrap();
+        let hints: HashMap<&str, &str> = cw.hints().collect();
+        assert_eq!(hints, HashMap::from([("1a", "Pet"), ("3a", "Digit"), ("1d", "Baby bed"), ("2d", "Ten")]));
+        assert_eq!(cw.letter(0, 0), Some(&'C'.into()));
+        assert!(cw.grid().unwrap().style(0, 0).circled);
+        assert_eq!(cw.metadata().publication_date.as_deref(), Some("2024-03-01"));
+        let solution = solution.unwrap();
+        assert_eq!(solution.letter(0, 1), Some(&'O'.into()));
+        assert!(solution.fits(&cw).is_ok());
+
+        let rebus = RING.replace(r#"["T", "E", "N"]"#, r#"["T", "E", "Ne\u0301"]"#);
+        let (_, solution) = Crossword::from_ipuz(&Ipuz::parse(&rebus).unwrap()).unwrap();
+        assert_eq!(solution.unwrap().letter(2, 2).map(|x| x.as_str()), Some("N\u{c9}"));
+
+        let barred = RING.replace(r#""2"], [0"#, r#"{"cell": "2", "style": {"barred": "L", "highlight": true}}], [0"#);
+        let (cw, _) = Crossword::from_ipuz(&Ipuz::parse(&barred).unwrap()).unwrap();
+        let grid = cw.grid().unwrap();
+        assert!(grid.style(1, 0).bar_right && grid.style(2, 0).shaded);
+        assert_eq!(cw.across["1a"].cells.len(), 2);
+    }
+
+    #[test]
+    fn test_linked_clues() {
+        let linked = RING.replace(r#"[[1, "Pet"], "#, r#"[{"number": 1, "clue": "Pet, ten", "enumeration": "3,3", "continued": [{"direction": "Down", "number": 2}]}, "#)
+            .replace(r#"["2", "Ten"]"#, r#"["2", "See 1"]"#);
+        let (cw, _) = Crossword::from_ipuz(&Ipuz::parse(&linked).unwrap()).unwrap();
+        assert!(cw.validate().is_ok());
+        assert_eq!(cw.across["1a"].linked, ["2d"]);
+        assert_eq!(cw.across["1a"].enumeration.as_ref().map(|x| x.as_str()), Some("3,3"));
+        assert_eq!(cw.across["3a"].enumeration.as_ref().map(|x| x.as_str()), Some("3"));
+
+        let json = cw.to_ipuz("Ring", None).unwrap().to_json();
+        assert!(json.contains(r#"{"clue":"Pet, ten","continued":[{"direction":"Down","number":2}],"enumeration":"3,3","number":1}"#));
+        let (back, _) = Crossword::from_ipuz(&Ipuz::parse(&json).unwrap()).unwrap();
+        assert_eq!(back.across["1a"].linked, ["2d"]);
+
+        assert_eq!(error(&linked.replace(r#""number": 2}"#, r#""number": 4}"#)).path, "clues.Across[0].continued[0]");
+        assert_eq!(error(&linked.replace(r#""3,3""#, r#""3 letters""#)).path, "clues.Across[0].enumeration");
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert_eq!(error(r#"{"version": "http://ipuz.org/v2", "kind": ["http://ipuz.org/sudoku#1"]}"#).path, "kind");
+        assert_eq!(error("[1]").reason, "the file should be a JSON object");
+
+        let too_long = RING.replace(r#"["T", "E", "N"]"#, r#"["T", "E", "TOOLONGFORASQUARE"]"#);
+        assert_eq!(error(&too_long).path, "solution[2][2]");
+        let omitted = RING.replace(r##"[0, "#", 0]"##, "[0, null, 0]");
+        assert_eq!(error(&omitted).path, "puzzle[1][1]");
+        let diagonal = RING.replace(r#""Down""#, r#""Diagonal""#);
+        assert_eq!(error(&diagonal).path, "clues.Diagonal");
+        let wrong_number = RING.replace(r#"["2", "Ten"]"#, r#"[3, "Ten"]"#);
+        assert_eq!(error(&wrong_number).path, "clues.Down[1]");
+        let short_row = RING.replace(r#"[3, 0, 0]"#, r#"[3, 0]"#);
+        assert_eq!(error(&short_row).path, "puzzle[2]");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut cw = Crossword::demo_grid();
+        let solution = cw.solution(&demo_answers(false)).unwrap();
+        cw.styles = vec![
+            StyledSquare { x: 0, y: 0, style: CellStyle { circled: true, ..CellStyle::default() } },
+            StyledSquare { x: 3, y: 4, style: CellStyle { shaded: true, bar_right: true, ..CellStyle::default() } },
+        ];
+
+        let json = cw.to_ipuz("Demo", Some(&solution)).unwrap().to_json();
+        let ipuz = Ipuz::parse(&json).unwrap();
+        let (back, back_solution) = Crossword::from_ipuz(&ipuz).unwrap();
+        assert_eq!(back_solution, Some(solution));
+        assert_same_hints(&cw, &back);
+        assert_eq!(back.styles(), cw.styles());
+        assert!(!json.contains("saved"));
+    }
+}
@@ -0,0 +1,713 @@
+use std::{collections::{HashMap, HashSet}, fmt::{self, Display}, io::{Cursor, Read}};
+
+use quick_xml::{escape::escape, events::Event, Reader};
+
+use super::{CellStyle, CellValue, Crossword, Direction, Entry, Enumeration, Grid, GridSizeError, PuzzleMetadata, Solution, MAX_CELL_LENGTH, MAX_GRID_SIZE};
+
+const APPLET_NAMESPACE: &str = "http://crossword.info/xml/crossword-compiler-applet";
+const PUZZLE_NAMESPACE: &str = "http://crossword.info/xml/rectangular-puzzle";
+
+/// The most we'll unpack from a compressed file.
+const MAX_UNCOMPRESSED_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The deepest elements can be nested. Puzzles need less than ten levels.
+const MAX_XML_DEPTH: usize = 64;
+
+/// The background given to shaded squares.
+const SHADE_COLOUR: &str = "#DDDDDD";
+
+/// A Crossword Compiler puzzle, which is the `rectangular-puzzle` element
+/// of a `.jpz` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jpz {
+    puzzle: Element,
+}
+
+/// Something in a jpz file that can't be read, with the element it was
+/// found in, e.g. `cell 3,2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JpzError {
+    pub path: String,
+    pub reason: String,
+}
+
+impl JpzError {
+    fn new(path: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self { path: path.into(), reason: reason.into() }
+    }
+}
+
+impl Display for JpzError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, "{}", self.reason),
+            false => write!(f, "{}: {}", self.path, self.reason),
+        }
+    }
+}
+
+impl std::error::Error for JpzError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+/// Just enough of an XML document to walk the puzzle, without namespaces.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn new(name: &str) -> Self {
+        Self { name: name.to_string(), ..Self::default() }
+    }
+
+    fn attribute(mut self, name: &str, value: impl ToString) -> Self {
+        self.attributes.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn child(mut self, child: Element) -> Self {
+        self.children.push(Node::Element(child));
+        self
+    }
+
+    fn text(mut self, text: &str) -> Self {
+        self.children.push(Node::Text(text.to_string()));
+        self
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    fn elements<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter_map(move |node| match node {
+            Node::Element(element) if element.name == name => Some(element),
+            _ => None,
+        })
+    }
+
+    fn element<'a>(&'a self, name: &'a str) -> Option<&'a Element> {
+        self.elements(name).next()
+    }
+
+    /// The first element with this name, searching depth first.
+    fn find(&self, name: &str) -> Option<&Element> {
+        if self.name == name {
+            return Some(self)
+        }
+        self.children.iter().find_map(|node| match node {
+            Node::Element(element) => element.find(name),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// All the text inside the element, including any formatting tags.
+    fn all_text(&self) -> String {
+        self.children.iter()
+            .map(|node| match node {
+                Node::Element(element) => element.all_text(),
+                Node::Text(text) => text.clone(),
+            })
+            .collect()
+    }
+
+    fn write(&self, out: &mut String) {
+        out.push('<');
+        out.push_str(&self.name);
+        for (key, value) in &self.attributes {
+            out.push_str(&format!(" {key}=\"{}\"", escape(value.as_str())));
+        }
+        if self.children.is_empty() {
+            out.push_str("/>");
+            return
+        }
+        out.push('>');
+        for node in &self.children {
+            match node {
+                Node::Element(element) => element.write(out),
+                Node::Text(text) => out.push_str(&escape(text.as_str())),
+            }
+        }
+        out.push_str(&format!("</{}>", self.name));
+    }
+}
+
+fn parse_xml(text: &str) -> Result<Element, JpzError> {
+    let mut reader = Reader::from_str(text);
+    let error = |reader: &Reader<&[u8]>, e: &dyn Display| JpzError::new("", format!("the file isn't valid XML at byte {}: {e}", reader.buffer_position()));
+    let start = |reader: &Reader<&[u8]>, tag: &quick_xml::events::BytesStart| -> Result<Element, JpzError> {
+        let mut element = Element::new(&String::from_utf8_lossy(tag.local_name().as_ref()));
+        for attribute in tag.attributes() {
+            let attribute = attribute.map_err(|e| error(reader, &e))?;
+            let value = attribute.unescape_value().map_err(|e| error(reader, &e))?;
+            element.attributes.push((String::from_utf8_lossy(attribute.key.local_name().as_ref()).to_string(), value.to_string()));
+        }
+        Ok(element)
+    };
+
+    let mut stack: Vec<Element> = Vec::new();
+    loop {
+        let event = reader.read_event().map_err(|e| error(&reader, &e))?;
+        match event {
+            Event::Start(_) if stack.len() >= MAX_XML_DEPTH => {
+                return Err(JpzError::new("", format!("the elements are nested more than {MAX_XML_DEPTH} deep")))
+            },
+            Event::Start(tag) => stack.push(start(&reader, &tag)?),
+            Event::Empty(tag) => {
+                let element = start(&reader, &tag)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(Node::Element(element)),
+                    None => return Ok(element),
+                }
+            },
+            Event::End(_) => {
+                let element = stack.pop().ok_or_else(|| error(&reader, &"unexpected closing tag"))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(Node::Element(element)),
+                    None => return Ok(element),
+                }
+            },
+            Event::Text(text) => {
+                let text = text.unescape().map_err(|e| error(&reader, &e))?;
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(Node::Text(text.to_string()));
+                }
+            },
+            Event::CData(text) => {
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(Node::Text(String::from_utf8_lossy(&text).to_string()));
+                }
+            },
+            Event::Eof => return Err(error(&reader, &"the document ends early")),
+            _ => (),
+        }
+    }
+}
+
+/// The first file in a zip archive.
+fn unzip(data: &[u8]) -> Result<Vec<u8>, JpzError> {
+    let error = |e: &dyn Display| JpzError::new("", format!("the compressed file can't be read: {e}"));
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(|e| error(&e))?;
+    for i in 0..archive.len() {
+        let file = archive.by_index(i).map_err(|e| error(&e))?;
+        if file.is_dir() {
+            continue;
+        }
+        let mut contents = Vec::new();
+        file.take(MAX_UNCOMPRESSED_SIZE + 1).read_to_end(&mut contents).map_err(|e| error(&e))?;
+        if contents.len() as u64 > MAX_UNCOMPRESSED_SIZE {
+            return Err(JpzError::new("", "the compressed file is too big"))
+        }
+        return Ok(contents)
+    }
+    Err(JpzError::new("", "the compressed file is empty"))
+}
+
+/// Reads `3` or a range like `1-5` from a word's `x` or `y`. Both ends
+/// must be between 1 and `size`, the width or height of the grid.
+fn parse_range(path: &str, value: &str, size: usize) -> Result<Vec<usize>, JpzError> {
+    let number = |x: &str| x.trim().parse::<usize>().ok();
+    let range = match value.split_once('-') {
+        Some((start, end)) => number(start).zip(number(end)),
+        None => number(value).map(|x| (x, x)),
+    };
+    // checked before the range is expanded, so a huge one can't be.
+    let in_grid = |x: usize| (1..=size.min(MAX_GRID_SIZE)).contains(&x);
+    match range {
+        Some((start, end)) if !in_grid(start) || !in_grid(end) => Err(JpzError::new(path, format!("{value} is outside the grid"))),
+        Some((start, end)) if start <= end => Ok((start..=end).collect()),
+        Some((start, end)) => Ok((end..=start).rev().collect()),
+        None => Err(JpzError::new(path, format!("{value} isn't a square or a range of squares"))),
+    }
+}
+
+/// The squares of a word, counting from 0. Each part of it runs along one
+/// row or column, and it can't have more squares than the grid.
+fn word_squares(word: &Element, width: usize, height: usize) -> Result<Vec<(usize, usize)>, JpzError> {
+    let path = format!("word {}", word.get("id").unwrap_or("?"));
+    let mut squares = Vec::new();
+    let mut add = |element: &Element| -> Result<(), JpzError> {
+        let xs = parse_range(&path, element.get("x").ok_or_else(|| JpzError::new(&path, "x is missing"))?, width)?;
+        let ys = parse_range(&path, element.get("y").ok_or_else(|| JpzError::new(&path, "y is missing"))?, height)?;
+        if xs.len() > 1 && ys.len() > 1 {
+            return Err(JpzError::new(&path, "its squares aren't in one row or column"))
+        }
+        if squares.len() + xs.len() * ys.len() > width * height {
+            return Err(JpzError::new(&path, "it has more squares than the grid"))
+        }
+        for y in &ys {
+            for x in &xs {
+                squares.push((x - 1, y - 1));
+            }
+        }
+        Ok(())
+    };
+    if word.get("x").is_some() {
+        add(word)?;
+    }
+    for cells in word.elements("cells") {
+        add(cells)?;
+    }
+    Ok(squares)
+}
+
+/// Splits a word's squares into the grid entries they run through, in
+/// order. A linked clue's word is several entries; any other is one.
+fn word_entries<'a>(squares: &[(usize, usize)], entries: &'a [Entry]) -> Option<Vec<&'a Entry>> {
+    let mut found = Vec::new();
+    let mut rest = squares;
+    while !rest.is_empty() {
+        let entry = entries.iter().find(|entry| entry.len <= rest.len() && entry.cells().eq(rest[..entry.len].iter().copied()))?;
+        rest = &rest[entry.len..];
+        found.push(entry);
+    }
+    Some(found).filter(|x| !x.is_empty())
+}
+
+impl Jpz {
+    /// Whether the data looks like a jpz file, compressed or not.
+    pub fn is_jpz(data: &[u8]) -> bool {
+        if data.starts_with(b"PK\x03\x04") {
+            return unzip(data).is_ok_and(|contents| Self::is_jpz(&contents))
+        }
+        let start = String::from_utf8_lossy(&data[..data.len().min(1024)]).to_string();
+        start.trim_start().starts_with('<') && start.contains("rectangular-puzzle")
+    }
+
+    /// Reads a jpz file, unzipping it first if needed.
+    pub fn parse(data: &[u8]) -> Result<Self, JpzError> {
+        let contents = match data.starts_with(b"PK\x03\x04") {
+            true => unzip(data)?,
+            false => data.to_vec(),
+        };
+        let text = String::from_utf8(contents).map_err(|_| JpzError::new("", "the file isn't valid UTF-8"))?;
+        let root = parse_xml(text.trim_start_matches('\u{feff}'))?;
+        let puzzle = root.find("rectangular-puzzle")
+            .ok_or_else(|| JpzError::new("", "there's no rectangular-puzzle in the file"))?;
+        if puzzle.element("crossword").is_none() {
+            let kind = puzzle.children.iter()
+                .filter_map(|node| match node {
+                    Node::Element(element) if element.name != "metadata" && element.name != "instructions" => Some(element.name.as_str()),
+                    _ => None,
+                })
+                .next()
+                .unwrap_or("empty puzzle");
+            return Err(JpzError::new("rectangular-puzzle", format!("only crosswords are supported, not {kind}")))
+        }
+        Ok(Self { puzzle: puzzle.clone() })
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.metadata("title")
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        Element::new("crossword-compiler-applet")
+            .attribute("xmlns", APPLET_NAMESPACE)
+            .child(self.puzzle.clone())
+            .write(&mut out);
+        out
+    }
+
+    fn metadata(&self, name: &str) -> Option<String> {
+        self.puzzle.element("metadata")
+            .and_then(|metadata| metadata.element(name))
+            .map(|element| element.all_text().trim().to_string())
+            .filter(|text| !text.is_empty())
+    }
+
+    fn crossword(&self) -> &Element {
+        self.puzzle.element("crossword").expect("checked when parsed")
+    }
+}
+
+impl Crossword {
+    /// Builds a crossword from a jpz file, along with its solution if it has
+    /// one. Clues are matched to the grid through the squares of their words,
+    /// and a word running through several entries makes a linked clue.
+    /// Circles, background colours and bars are kept as styles.
+    pub fn from_jpz(jpz: &Jpz) -> Result<(Crossword, Option<Solution>), JpzError> {
+        let crossword = jpz.crossword();
+        let grid_element = crossword.element("grid").ok_or_else(|| JpzError::new("crossword", "there's no grid"))?;
+        let dimension = |name: &str| match grid_element.get(name).and_then(|x| x.parse::<usize>().ok()) {
+            Some(0) => Err(JpzError::new("grid", format!("the {name} must be at least 1"))),
+            Some(x) if x > MAX_GRID_SIZE => Err(JpzError::new("grid", format!("the {name} must be at most {MAX_GRID_SIZE}"))),
+            Some(x) => Ok(x),
+            None => Err(JpzError::new("grid", format!("the {name} is missing"))),
+        };
+        let (width, height) = (dimension("width")?, dimension("height")?);
+
+        let mut grid = Grid::new(width, height);
+        let mut seen = vec![false; width * height];
+        let mut answers = Vec::new();
+        let mut missing_answers = false;
+        let mut styles = Vec::new();
+        // bars on the left and top edges belong to the squares before.
+        let mut bars_before = Vec::new();
+        for cell in grid_element.elements("cell") {
+            let position = cell.get("x").zip(cell.get("y"))
+                .and_then(|(x, y)| x.parse::<usize>().ok().zip(y.parse::<usize>().ok()))
+                .filter(|(x, y)| (1..=width).contains(x) && (1..=height).contains(y));
+            let (x, y) = match position {
+                Some((x, y)) => (x - 1, y - 1),
+                None => return Err(JpzError::new("grid", format!("a cell at {},{} is outside the grid", cell.get("x").unwrap_or("?"), cell.get("y").unwrap_or("?")))),
+            };
+            let path = format!("cell {},{}", x + 1, y + 1);
+            seen[y * width + x] = true;
+
+            match cell.get("type") {
+                Some("block") => {
+                    grid.set_block(x, y, true);
+                    continue;
+                },
+                None | Some("letter") => (),
+                Some(other) => return Err(JpzError::new(path, format!("{other} squares aren't supported"))),
+            }
+            let barred = |side: &str| cell.get(&format!("{side}-bar")) == Some("true");
+            styles.push(((x, y), CellStyle {
+                circled: cell.get("background-shape") == Some("circle"),
+                shaded: cell.get("background-color").is_some_and(|colour| !colour.eq_ignore_ascii_case("#ffffff")),
+                bar_right: barred("right"),
+                bar_bottom: barred("bottom"),
+            }));
+            if barred("left") && x > 0 {
+                bars_before.push((x - 1, y, Direction::Across));
+            }
+            if barred("top") && y > 0 {
+                bars_before.push((x, y - 1, Direction::Down));
+            }
+
+            let letters = |name: &str, value: &str| -> Result<Option<CellValue>, JpzError> {
+                match CellValue::parse(value, *MAX_CELL_LENGTH) {
+                    Ok(value) => Ok(Some(value).filter(|x| !x.is_blank())),
+                    Err(e) => Err(JpzError::new(&path, format!("the {name}: {e}"))),
+                }
+            };
+            match cell.get("solution").map(|value| letters("solution", value)).transpose()?.flatten() {
+                Some(c) => answers.push(((x, y), c.to_uppercase())),
+                None => missing_answers = true,
+            }
+            if let Some(c) = cell.get("solve-state").map(|value| letters("solve-state", value)).transpose()?.flatten() {
+                grid.set_letter(x, y, c);
+            }
+        }
+        if let Some(i) = seen.iter().position(|seen| !seen) {
+            return Err(JpzError::new(format!("cell {},{}", i % width + 1, i / width + 1), "the square is missing"))
+        }
+        for ((x, y), style) in styles {
+            grid.set_style(x, y, style);
+        }
+        for (x, y, direction) in bars_before {
+            let style = grid.style(x, y);
+            grid.set_style(x, y, match direction {
+                Direction::Across => CellStyle { bar_right: true, ..style },
+                Direction::Down => CellStyle { bar_bottom: true, ..style },
+            });
+        }
+
+        let mut result = Crossword::from_grid(&grid);
+        let entries = grid.entries();
+
+        let words = crossword.elements("word")
+            .map(|word| Ok((word.get("id").unwrap_or_default().to_string(), word_squares(word, width, height)?)))
+            .collect::<Result<HashMap<String, Vec<(usize, usize)>>, JpzError>>()?;
+
+        for clues in crossword.elements("clues") {
+            for clue in clues.elements("clue") {
+                let word_id = clue.get("word").unwrap_or_default();
+                let path = format!("clue {}", clue.get("number").unwrap_or(word_id));
+                let squares = words.get(word_id)
+                    .ok_or_else(|| JpzError::new(&path, format!("there's no word {word_id}")))?;
+                let word_entries = word_entries(squares, &entries)
+                    .ok_or_else(|| JpzError::new(&path, "its word isn't made of across or down entries in the grid"))?;
+                let (hint, mut enumeration) = Enumeration::split_hint(clue.all_text().trim());
+                if let Some(format) = clue.get("format").filter(|x| !x.trim().is_empty()) {
+                    enumeration = Some(Enumeration::parse(format).map_err(|e| JpzError::new(&path, e.to_string()))?);
+                }
+                let entry = word_entries[0];
+                let clues = match entry.direction {
+                    Direction::Across => &mut result.across,
+                    Direction::Down => &mut result.down,
+                };
+                if let Some(found) = clues.get_mut(&entry.id()) {
+                    found.hint = hint;
+                    found.enumeration = enumeration;
+                    found.linked = word_entries[1..].iter().map(|x| x.id()).collect();
+                }
+            }
+        }
+
+        result.set_metadata(PuzzleMetadata {
+            author: jpz.metadata("creator"),
+            publication: jpz.metadata("publisher"),
+            copyright: jpz.metadata("copyright"),
+            notes: jpz.metadata("description"),
+            ..PuzzleMetadata::default()
+        });
+
+        let solution = match missing_answers || answers.is_empty() {
+            true => None,
+            false => Some(answers.into_iter().collect()),
+        };
+        Ok((result, solution))
+    }
+
+    /// Writes the crossword as jpz, renumbering it the standard way.
+    pub fn to_jpz(&self, title: &str, solution: Option<&Solution>) -> Result<Jpz, GridSizeError> {
+        let crossword = self.renumbered()?;
+        let grid = crossword.grid()?;
+        let entries = grid.entries();
+        let numbers: HashMap<(usize, usize), usize> = entries.iter().map(|entry| ((entry.x, entry.y), entry.number)).collect();
+
+        let mut metadata = Element::new("metadata").child(Element::new("title").text(title));
+        let details = self.metadata();
+        let fields = [
+            ("creator", &details.author),
+            ("publisher", &details.publication),
+            ("copyright", &details.copyright),
+            ("description", &details.notes),
+        ];
+        for (name, field) in fields {
+            if let Some(field) = field {
+                metadata = metadata.child(Element::new(name).text(field));
+            }
+        }
+
+        let mut grid_element = Element::new("grid")
+            .attribute("width", grid.width())
+            .attribute("height", grid.height());
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                let mut cell = Element::new("cell").attribute("x", x + 1).attribute("y", y + 1);
+                if grid.is_block(x, y) {
+                    grid_element = grid_element.child(cell.attribute("type", "block"));
+                    continue;
+                }
+                if let Some(c) = solution.and_then(|solution| solution.letter(x, y)) {
+                    cell = cell.attribute("solution", c.as_str());
+                }
+                if let Some(number) = numbers.get(&(x, y)) {
+                    cell = cell.attribute("number", number);
+                }
+                if let Some(c) = grid.letter(x, y).filter(|c| !c.is_blank()) {
+                    cell = cell.attribute("solve-state", c.as_str());
+                }
+                let style = grid.style(x, y);
+                if style.circled {
+                    cell = cell.attribute("background-shape", "circle");
+                }
+                if style.shaded {
+                    cell = cell.attribute("background-color", SHADE_COLOUR);
+                }
+                if style.bar_right {
+                    cell = cell.attribute("right-bar", "true");
+                }
+                if style.bar_bottom {
+                    cell = cell.attribute("bottom-bar", "true");
+                }
+                grid_element = grid_element.child(cell);
+            }
+        }
+
+        let mut body = Element::new("crossword").child(grid_element);
+        let mut across = Element::new("clues").attribute("ordering", "normal")
+            .child(Element::new("title").child(Element::new("b").text("Across")));
+        let mut down = Element::new("clues").attribute("ordering", "normal")
+            .child(Element::new("title").child(Element::new("b").text("Down")));
+        // entries carrying on a linked clue are part of its word, and only
+        // get a word and clue of their own if they have a hint.
+        let members: HashSet<&str> = crossword.across.values()
+            .chain(crossword.down.values())
+            .flat_map(|clue| clue.linked.iter().map(String::as_str))
+            .collect();
+        let range = |a: usize, b: usize| match a == b {
+            true => format!("{}", a + 1),
+            false => format!("{}-{}", a + 1, b + 1),
+        };
+        let cells = |entry: &Entry, element: Element| {
+            let (first, last) = (entry.cells().next().unwrap_or((entry.x, entry.y)), entry.cells().last().unwrap_or((entry.x, entry.y)));
+            element.attribute("x", range(first.0, last.0)).attribute("y", range(first.1, last.1))
+        };
+        for (i, entry) in entries.iter().enumerate() {
+            let (clues, list) = match entry.direction {
+                Direction::Across => (&crossword.across, &mut across),
+                Direction::Down => (&crossword.down, &mut down),
+            };
+            let id = entry.id();
+            let Some(clue) = clues.get(&id) else { continue };
+            if members.contains(id.as_str()) && clue.hint.trim().is_empty() {
+                continue;
+            }
+            let word = match clue.linked.is_empty() {
+                true => cells(entry, Element::new("word").attribute("id", i + 1)),
+                false => {
+                    let linked = clue.linked.iter().filter_map(|id| entries.iter().find(|entry| entry.id() == *id));
+                    std::iter::once(entry).chain(linked)
+                        .fold(Element::new("word").attribute("id", i + 1), |word, entry| word.child(cells(entry, Element::new("cells"))))
+                },
+            };
+            body = body.child(word);
+            let mut written = Element::new("clue")
+                .attribute("word", i + 1)
+                .attribute("number", entry.number);
+            if let Some(enumeration) = &clue.enumeration {
+                written = written.attribute("format", enumeration.as_str());
+            }
+            list.children.push(Node::Element(written.text(&clue.hint)));
+        }
+
+        let puzzle = Element::new("rectangular-puzzle")
+            .attribute("xmlns", PUZZLE_NAMESPACE)
+            .child(metadata)
+            .child(body.child(across).child(down));
+        Ok(Jpz { puzzle })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io::Write};
+
+    use super::Jpz;
+    use crate::crossword::{CellStyle, Crossword, StyledSquare};
+    use crate::crossword::test_fixtures::{assert_same_hints, demo_answers};
+
+    const RING: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<crossword-compiler-applet xmlns="http://crossword.info/xml/crossword-compiler-applet">
+<rectangular-puzzle xmlns="http://crossword.info/xml/rectangular-puzzle" alphabet="ABCDEFGHIJKLMNOPQRSTUVWXYZ">
+<metadata><title>Ring</title><creator>Bob</creator></metadata>
+<crossword>
+<grid width="3" height="3">
+<grid-look numbering-scheme="normal"/>
+<cell x="1" y="1" solution="C" number="1" solve-state="C"/><cell x="2" y="1" solution="A"/><cell x="3" y="1" solution="T" number="2"/>
+<cell x="1" y="2" solution="O"/><cell x="2" y="2" type="block"/><cell x="3" y="2" solution="E"/>
+<cell x="1" y="3" solution="T" number="3"/><cell x="2" y="3" solution="E"/><cell x="3" y="3" solution="N"/>
+</grid>
+<word id="1" x="1-3" y="1"/>
+<word id="2" x="1-3" y="3"/>
+<word id="3" x="1" y="1-3"/>
+<word id="4"><cells x="3" y="1"/><cells x="3" y="2"/><cells x="3" y="3"/></word>
+<clues ordering="normal"><title><b>Across</b></title>
+<clue word="1" number="1" format="3"><i>Pet</i> &amp; friend</clue>
+<clue word="2" number="3">Digit</clue>
+</clues>
+<clues ordering="normal"><title><b>Down</b></title>
+<clue word="3" number="1">Baby bed</clue>
+<clue word="4" number="2">Ten</clue>
+</clues>
+</crossword>
+</rectangular-puzzle>
+</crossword-compiler-applet>"#;
+
+    fn error(xml: &str) -> String {
+        match Jpz::parse(xml.as_bytes()) {
+            Ok(jpz) => Crossword::from_jpz(&jpz).unwrap_err().path,
+            Err(e) => e.path,
+        }
+    }
+
+    #[test]
+    fn test_from_jpz() {
+        assert!(Jpz::is_jpz(RING.as_bytes()));
+        let jpz = Jpz::parse(RING.as_bytes()).unwrap();
+        assert_eq!(jpz.title().as_deref(), Some("Ring"));
+        let (cw, solution) = Crossword::from_jpz(&jpz).unwrap();
+        let hints: HashMap<&str, &str> = cw.hints().collect();
+        assert_eq!(hints, HashMap::from([("1a", "Pet & friend"), ("3a", "Digit"), ("1d", "Baby bed"), ("2d", "Ten")]));
+        assert_eq!(cw.letter(0, 0), Some(&'C'.into()));
+        assert_eq!(cw.metadata().author.as_deref(), Some("Bob"));
+        assert!(solution.unwrap().fits(&cw).is_ok());
+
+        let styled = RING.replace(r#"<cell x="2" y="1" solution="A"/>"#, r##"<cell x="2" y="1" solution="A" background-shape="circle" background-color="#DDDDDD"/>"##);
+        let (cw, _) = Crossword::from_jpz(&Jpz::parse(styled.as_bytes()).unwrap()).unwrap();
+        let style = cw.grid().unwrap().style(1, 0);
+        assert!(style.circled && style.shaded);
+
+        // a bar splits the top row, so the first word no longer fits the grid.
+        let barred = RING.replace(r#"<cell x="2" y="1" solution="A"/>"#, r#"<cell x="2" y="1" solution="A" left-bar="true"/>"#);
+        assert_eq!(error(&barred), "clue 1");
+    }
+
+    #[test]
+    fn test_linked_clues() {
+        let linked = RING.replace(r#"<word id="1" x="1-3" y="1"/>"#, r#"<word id="1"><cells x="1-3" y="1"/><cells x="3" y="1-3"/></word>"#)
+            .replace(r#"format="3""#, r#"format="3,3""#)
+            .replace("<clue word=\"4\" number=\"2\">Ten</clue>\n", "");
+        let (cw, _) = Crossword::from_jpz(&Jpz::parse(linked.as_bytes()).unwrap()).unwrap();
+        assert!(cw.validate().is_ok());
+        assert_eq!(cw.across["1a"].linked, ["2d"]);
+        assert_eq!(cw.across["1a"].enumeration.as_ref().map(|x| x.length()), Some(6));
+
+        let xml = cw.to_jpz("Ring", None).unwrap().to_xml();
+        assert!(xml.contains(r#"<word id="1"><cells x="1-3" y="1"/><cells x="3" y="1-3"/></word>"#));
+        assert!(xml.contains(r#"<clue word="1" number="1" format="3,3">Pet &amp; friend</clue>"#));
+        let (back, _) = Crossword::from_jpz(&Jpz::parse(xml.as_bytes()).unwrap()).unwrap();
+        assert_eq!(back.across["1a"].linked, ["2d"]);
+
+        assert_eq!(error(&linked.replace(r#"format="3,3""#, r#"format="three""#)), "clue 1");
+    }
+
+    #[test]
+    fn test_compressed() {
+        let mut zipped = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zipped.start_file("ring.xml", zip::write::FileOptions::default()).unwrap();
+        zipped.write_all(RING.as_bytes()).unwrap();
+        let data = zipped.finish().unwrap().into_inner();
+
+        assert!(Jpz::is_jpz(&data));
+        assert_eq!(Jpz::parse(&data).unwrap(), Jpz::parse(RING.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert_eq!(error(&RING.replace(r#"solution="N""#, r#"solution="TOOLONGFORASQUARE""#)), "cell 3,3");
+        assert_eq!(error(&RING.replace(r#"type="block""#, r#"type="void""#)), "cell 2,2");
+        assert_eq!(error(&RING.replace(r#"<word id="2" x="1-3" y="3"/>"#, r#"<word id="2" x="1-2" y="3"/>"#)), "clue 3");
+        assert_eq!(error(&RING.replace("<crossword>", "<acrostic>").replace("</crossword>", "</acrostic>")), "rectangular-puzzle");
+        assert_eq!(error("<rectangular-puzzle><crossword>"), "");
+    }
+
+    #[test]
+    fn test_hostile_files() {
+        let nested = format!("{}{}", "<a>".repeat(200_000), "</a>".repeat(200_000));
+        assert_eq!(Jpz::parse(nested.as_bytes()).unwrap_err().reason, "the elements are nested more than 64 deep");
+
+        for range in [r#"x="1-200000000" y="1""#, r#"x="0-3" y="1""#, r#"x="1-4" y="1""#, r#"x="1" y="99999999999999999999""#] {
+            let xml = RING.replace(r#"x="1-3" y="1""#, range);
+            let e = Crossword::from_jpz(&Jpz::parse(xml.as_bytes()).unwrap()).unwrap_err();
+            assert_eq!(e.path, "word 1", "{range}");
+        }
+
+        let error = |word: &str| {
+            let xml = RING.replace(r#"<word id="1" x="1-3" y="1"/>"#, word);
+            Crossword::from_jpz(&Jpz::parse(xml.as_bytes()).unwrap()).unwrap_err().reason
+        };
+        assert_eq!(error(r#"<word id="1" x="1-3" y="1-3"/>"#), "its squares aren't in one row or column");
+        let long = format!(r#"<word id="1">{}</word>"#, r#"<cells x="1-3" y="1"/>"#.repeat(4));
+        assert_eq!(error(&long), "it has more squares than the grid");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut cw = Crossword::demo_grid();
+        let solution = cw.solution(&demo_answers(true)).unwrap();
+        cw.styles = vec![StyledSquare { x: 0, y: 1, style: CellStyle { circled: true, bar_bottom: true, ..CellStyle::default() } }];
+
+        let xml = cw.to_jpz("Demo <1>", Some(&solution)).unwrap().to_xml();
+        let jpz = Jpz::parse(xml.as_bytes()).unwrap();
+        assert_eq!(jpz.title().as_deref(), Some("Demo <1>"));
+        let (back, back_solution) = Crossword::from_jpz(&jpz).unwrap();
+        assert_eq!(back_solution, Some(solution));
+        assert_eq!(back.styles(), cw.styles());
+        assert!(xml.contains(r#"background-shape="circle" bottom-bar="true""#));
+        assert_same_hints(&cw, &back);
+    }
+}
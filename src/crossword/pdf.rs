@@ -0,0 +1,400 @@
+use std::str::FromStr;
+
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+use super::{Crossword, Direction, GridSizeError, Solution};
+
+/// The paper a puzzle is printed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaperSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl PaperSize {
+    /// The width and height in points.
+    fn size(&self) -> (f32, f32) {
+        match self {
+            PaperSize::A4 => (595.28, 841.89),
+            PaperSize::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+impl FromStr for PaperSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "a4" => Ok(PaperSize::A4),
+            "letter" => Ok(PaperSize::Letter),
+            _ => Err(format!("paper should be a4 or letter, not {s}")),
+        }
+    }
+}
+
+/// What is written in the squares of a printed grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrintedLetters {
+    #[default]
+    None,
+    /// The letters filled in so far.
+    Fills,
+    Solution,
+}
+
+impl FromStr for PrintedLetters {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(PrintedLetters::None),
+            "fills" => Ok(PrintedLetters::Fills),
+            "solution" => Ok(PrintedLetters::Solution),
+            _ => Err(format!("letters should be none, fills or solution, not {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PdfOptions {
+    pub paper: PaperSize,
+    pub letters: PrintedLetters,
+}
+
+const MARGIN: f32 = 40.0;
+const COLUMNS: usize = 3;
+const COLUMN_GAP: f32 = 14.0;
+const CLUE_SIZE: f32 = 9.0;
+const CLUE_LEADING: f32 = 11.0;
+const MAX_CELL_SIZE: f32 = 26.0;
+const SHADE_GRAY: f32 = 0.85;
+
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+
+/// Widths of the printable ASCII characters in Helvetica, in thousandths of
+/// the font size, from the font's metrics.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+fn text_width(text: &str, size: f32) -> f32 {
+    let units: u32 = text.chars()
+        .map(|c| match c {
+            ' '..='~' => HELVETICA_WIDTHS[c as usize - 32] as u32,
+            _ => 556,
+        })
+        .sum();
+    units as f32 * size / 1000.0
+}
+
+/// Encodes text for the standard fonts. Characters they can't show become `?`.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            '\u{2018}' => 0x91,
+            '\u{2019}' => 0x92,
+            '\u{201c}' => 0x93,
+            '\u{201d}' => 0x94,
+            '\u{2013}' => 0x96,
+            '\u{2014}' => 0x97,
+            '\u{2026}' => 0x85,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Splits text into lines that fit in `width` points.
+fn wrap(text: &str, width: f32, size: f32) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = match line.is_empty() {
+            true => word.to_string(),
+            false => format!("{line} {word}"),
+        };
+        if !line.is_empty() && text_width(&candidate, size) > width {
+            lines.push(std::mem::replace(&mut line, word.to_string()));
+        } else {
+            line = candidate;
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn show(content: &mut Content, font: Name, size: f32, x: f32, y: f32, text: &str) {
+    content.begin_text();
+    content.set_font(font, size);
+    content.next_line(x, y);
+    content.show(Str(&win_ansi(text)));
+    content.end_text();
+}
+
+/// Strokes a circle, made of the usual four Bézier curves.
+fn circle(content: &mut Content, cx: f32, cy: f32, r: f32) {
+    let k = r * 0.5523;
+    content.move_to(cx + r, cy);
+    content.cubic_to(cx + r, cy + k, cx + k, cy + r, cx, cy + r);
+    content.cubic_to(cx - k, cy + r, cx - r, cy + k, cx - r, cy);
+    content.cubic_to(cx - r, cy - k, cx - k, cy - r, cx, cy - r);
+    content.cubic_to(cx + k, cy - r, cx + r, cy - k, cx + r, cy);
+    content.close_path();
+    content.stroke();
+}
+
+/// Lays clues out down a page's columns, starting new pages as they fill up.
+struct ClueColumns {
+    pages: Vec<Content>,
+    column_width: f32,
+    top: f32,
+    bottom: f32,
+    page_top: f32,
+    column: usize,
+    y: f32,
+}
+
+impl ClueColumns {
+    fn new(first_page: Content, top: f32, page_top: f32, page_width: f32) -> Self {
+        let column_width = (page_width - 2.0 * MARGIN - (COLUMNS - 1) as f32 * COLUMN_GAP) / COLUMNS as f32;
+        Self { pages: vec![first_page], column_width, top, bottom: MARGIN, page_top, column: 0, y: top }
+    }
+
+    fn left(&self) -> f32 {
+        MARGIN + self.column as f32 * (self.column_width + COLUMN_GAP)
+    }
+
+    /// Moves to where a block of `lines` lines can go.
+    fn make_room(&mut self, lines: usize) {
+        if self.y - lines as f32 * CLUE_LEADING >= self.bottom || self.y == self.top {
+            return
+        }
+        self.column += 1;
+        if self.column == COLUMNS {
+            self.column = 0;
+            self.pages.push(Content::new());
+            self.top = self.page_top;
+        }
+        self.y = self.top;
+    }
+
+    fn heading(&mut self, title: &str) {
+        self.make_room(3);
+        if self.y != self.top {
+            self.y -= CLUE_LEADING / 2.0;
+        }
+        self.y -= CLUE_LEADING;
+        let (x, y) = (self.left(), self.y);
+        show(self.pages.last_mut().expect("there's always a page"), BOLD, CLUE_SIZE + 1.0, x, y, title);
+        self.y -= CLUE_LEADING / 3.0;
+    }
+
+    fn clue(&mut self, number: &str, text: &str) {
+        let indent = text_width("000", CLUE_SIZE);
+        let lines = wrap(text, self.column_width - indent, CLUE_SIZE);
+        self.make_room(lines.len());
+        self.y -= CLUE_LEADING;
+        let left = self.left();
+        let page = self.pages.last_mut().expect("there's always a page");
+        show(page, BOLD, CLUE_SIZE, left, self.y, number);
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                self.y -= CLUE_LEADING;
+            }
+            show(page, REGULAR, CLUE_SIZE, left + indent, self.y, line);
+        }
+    }
+}
+
+impl Crossword {
+    /// Lays the puzzle out for printing: the title, the grid and then the
+    /// numbered clues in columns, running onto more pages if needed. The
+    /// solution is only used when the options ask for it.
+    pub fn to_pdf(&self, title: &str, options: &PdfOptions, solution: Option<&Solution>) -> Result<Vec<u8>, GridSizeError> {
+        let (page_width, page_height) = options.paper.size();
+        let grid = self.grid()?;
+        let numbers = self.square_numbers();
+
+        let mut first = Content::new();
+        let mut y = page_height - MARGIN - 16.0;
+        show(&mut first, BOLD, 16.0, MARGIN, y, title);
+        if let Some(author) = &self.metadata.author {
+            y -= 14.0;
+            show(&mut first, REGULAR, 10.0, MARGIN, y, &format!("by {author}"));
+        }
+        y -= 14.0;
+
+        let width = grid.width().max(1) as f32;
+        let height = grid.height().max(1) as f32;
+        let cell = MAX_CELL_SIZE
+            .min((page_width - 2.0 * MARGIN) / width)
+            .min((page_height - 2.0 * MARGIN) * 0.55 / height);
+        let left = (page_width - cell * width) / 2.0;
+        let top = y;
+
+        let line_width = (cell / 30.0).max(0.5);
+        first.set_line_width(line_width);
+        // bars are drawn after every square so that none is drawn over them.
+        let mut bars = Vec::new();
+        for gy in 0..grid.height() {
+            for gx in 0..grid.width() {
+                let (x, y) = (left + gx as f32 * cell, top - (gy + 1) as f32 * cell);
+                let style = grid.style(gx, gy);
+                first.rect(x, y, cell, cell);
+                match (grid.is_block(gx, gy), style.shaded) {
+                    (true, _) => {
+                        first.set_fill_gray(0.0);
+                        first.fill_nonzero_and_stroke();
+                    },
+                    (false, true) => {
+                        first.set_fill_gray(SHADE_GRAY);
+                        first.fill_nonzero_and_stroke();
+                        first.set_fill_gray(0.0);
+                    },
+                    (false, false) => {
+                        first.stroke();
+                    },
+                }
+                if grid.is_block(gx, gy) {
+                    continue;
+                }
+                if style.circled {
+                    circle(&mut first, x + cell / 2.0, y + cell / 2.0, cell * 0.45);
+                }
+                if style.bar_right {
+                    bars.push((x + cell, y, x + cell, y + cell));
+                }
+                if style.bar_bottom {
+                    bars.push((x, y, x + cell, y));
+                }
+                if let Some(number) = numbers.get(&(gx, gy)) {
+                    show(&mut first, REGULAR, cell * 0.3, x + cell * 0.06, y + cell * 0.7, number);
+                }
+                let letter = match options.letters {
+                    PrintedLetters::None => None,
+                    PrintedLetters::Fills => grid.letter(gx, gy).filter(|c| !c.is_blank()),
+                    PrintedLetters::Solution => solution.and_then(|solution| solution.letter(gx, gy)),
+                };
+                if let Some(c) = letter {
+                    let c = c.to_string();
+                    // a rebus is shrunk to fit across the square.
+                    let size = (cell * 0.6).min(cell * 0.54 / text_width(&c, 1.0));
+                    show(&mut first, REGULAR, size, x + (cell - text_width(&c, size)) / 2.0, y + cell * 0.18, &c);
+                }
+            }
+        }
+
+        if !bars.is_empty() {
+            first.set_line_width(line_width * 4.0);
+            for (x1, y1, x2, y2) in bars {
+                first.move_to(x1, y1);
+                first.line_to(x2, y2);
+            }
+            first.stroke();
+            first.set_line_width(line_width);
+        }
+
+        let mut columns = ClueColumns::new(first, top - height * cell - 10.0, page_height - MARGIN, page_width);
+        for (direction, heading) in [(Direction::Across, "Across"), (Direction::Down, "Down")] {
+            columns.heading(heading);
+            for (number, text) in self.printed_clues(direction) {
+                columns.clue(&number, &text);
+            }
+        }
+
+        let mut pdf = Pdf::new();
+        let catalog = Ref::new(1);
+        let page_tree = Ref::new(2);
+        let regular = Ref::new(3);
+        let bold = Ref::new(4);
+        let info = Ref::new(5);
+        let page_refs: Vec<(Ref, Ref)> = (0..columns.pages.len() as i32)
+            .map(|i| (Ref::new(6 + 2 * i), Ref::new(7 + 2 * i)))
+            .collect();
+
+        pdf.catalog(catalog).pages(page_tree);
+        pdf.pages(page_tree).kids(page_refs.iter().map(|(page, _)| *page)).count(page_refs.len() as i32);
+        pdf.document_info(info).title(TextStr(title));
+        for (font, name) in [(regular, "Helvetica"), (bold, "Helvetica-Bold")] {
+            pdf.type1_font(font).base_font(Name(name.as_bytes())).encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+        for ((page_ref, content_ref), content) in page_refs.iter().zip(columns.pages) {
+            let mut page = pdf.page(*page_ref);
+            page.media_box(Rect::new(0.0, 0.0, page_width, page_height));
+            page.parent(page_tree);
+            page.contents(*content_ref);
+            let mut resources = page.resources();
+            resources.fonts().pair(REGULAR, regular).pair(BOLD, bold);
+            resources.finish();
+            page.finish();
+            pdf.stream(*content_ref, &content.finish());
+        }
+        Ok(pdf.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{text_width, win_ansi, wrap, PaperSize, PdfOptions, PrintedLetters};
+    use crate::crossword::Crossword;
+    use crate::crossword::test_fixtures::demo_answers;
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn test_pdf() {
+        let cw = Crossword::demo_grid();
+        let solution = cw.solution(&demo_answers(false)).unwrap();
+
+        let pdf = cw.to_pdf("Demo", &PdfOptions::default(), Some(&solution)).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(contains(&pdf, b"/MediaBox [0 0 595.28 841.89]"));
+        assert!(contains(&pdf, b"(I spent it in good company (8)) Tj"));
+        assert!(!contains(&pdf, b"(F) Tj"));
+
+        let options = PdfOptions { paper: PaperSize::Letter, letters: PrintedLetters::Solution };
+        let pdf = cw.to_pdf("Demo", &options, Some(&solution)).unwrap();
+        assert!(contains(&pdf, b"/MediaBox [0 0 612 792]"));
+        assert!(contains(&pdf, b"(F) Tj"));
+    }
+
+    #[test]
+    fn test_styles() {
+        let mut cw = Crossword::demo_grid();
+        let pdf = cw.to_pdf("Demo", &PdfOptions::default(), None).unwrap();
+        assert!(!contains(&pdf, b"0.85 g"));
+        assert!(!contains(&pdf, b" c\n"));
+
+        cw.styles = serde_json::from_str(r#"[{"x":0,"y":0,"circled":true,"shaded":true},{"x":1,"y":0,"bar_right":true}]"#).unwrap();
+        let pdf = cw.to_pdf("Demo", &PdfOptions::default(), None).unwrap();
+        assert!(contains(&pdf, b"0.85 g"));
+        assert!(contains(&pdf, b" c\n"));
+    }
+
+    #[test]
+    fn test_many_clues_run_onto_more_pages() {
+        let clues: String = (1..=120).map(|i| format!("\"{i}a\":{{\"hint\":\"A clue that is long enough to need wrapping onto a second line\",\"cells\":[{{\"x\":{},\"y\":{}, \"c\":\" \"}}]}}", i % 20 * 2, i / 20 * 2)).collect::<Vec<_>>().join(",");
+        let cw: Crossword = serde_json::from_str(&format!("{{\"across\":{{{clues}}},\"down\":{{}}}}")).unwrap();
+        let pdf = cw.to_pdf("Long", &PdfOptions::default(), None).unwrap();
+        assert!(contains(&pdf, b"/Count 2\n"));
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(win_ansi("e’er – ü 日"), b"e\x92er \x96 \xfc ?");
+        assert_eq!(text_width("AA", 10.0), 13.34);
+        assert_eq!(wrap("I spent it in good company", 60.0, 9.0), ["I spent it in", "good company"]);
+    }
+}
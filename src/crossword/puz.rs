@@ -0,0 +1,638 @@
+use std::{collections::HashMap, fmt::{self, Display}};
+
+use super::{CellStyle, CellValue, Crossword, Direction, Enumeration, Grid, GridSizeError, PuzzleMetadata, Solution};
+
+const MAGIC: &[u8; 12] = b"ACROSS&DOWN\0";
+const HEADER_LEN: usize = 0x34;
+const MASK: &[u8; 8] = b"ICHEATED";
+
+/// Whether the header says the solution is there, scrambled or missing.
+const SOLUTION_PLAIN: u16 = 0x0000;
+const SOLUTION_NONE: u16 = 0x0002;
+const SOLUTION_SCRAMBLED: u16 = 0x0004;
+
+/// The GEXT flag for a circled square.
+const CIRCLED: u8 = 0x80;
+
+/// An Across Lite `.puz` file. Squares are stored row by row. Text in the
+/// file is ISO-8859-1, so anything outside it is written as `?`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puz {
+    pub width: usize,
+    pub height: usize,
+    /// The answer for each square, `.` for blocks.
+    pub solution: Vec<u8>,
+    /// What's been filled in, `-` for empty squares and `.` for blocks.
+    pub fill: Vec<u8>,
+    pub title: String,
+    pub author: String,
+    pub copyright: String,
+    /// Clues in the order the file keeps them: by number, across first.
+    pub clues: Vec<String>,
+    pub notes: String,
+    pub solution_state: SolutionState,
+    /// Answers longer than one letter, by square (the GRBS and RTBL sections).
+    pub rebus: HashMap<usize, String>,
+    /// Flags for each square from the GEXT section, e.g. `0x80` for a circle.
+    /// Empty when the file doesn't have one.
+    pub markup: Vec<u8>,
+    /// The solver's timer from the LTIM section.
+    pub timer: Option<Timer>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolutionState {
+    Plain,
+    Scrambled,
+    Missing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timer {
+    pub seconds: u32,
+    pub stopped: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PuzError {
+    NotAPuz,
+    Truncated,
+    Checksum(&'static str),
+    ClueCount { expected: usize, found: usize },
+    Unsupported(&'static str),
+    /// Writing a puzzle with more of something than the format has room for.
+    TooMany { what: &'static str, max: usize },
+}
+
+impl Display for PuzError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PuzError::NotAPuz => write!(f, "the file isn't an Across Lite puzzle"),
+            PuzError::Truncated => write!(f, "the file ends too soon"),
+            PuzError::Checksum(part) => write!(f, "the {part} checksum doesn't match"),
+            PuzError::ClueCount { expected, found } => write!(f, "the grid needs {expected} clues but the file has {found}"),
+            PuzError::Unsupported(what) => write!(f, "{what} puzzles aren't supported"),
+            PuzError::TooMany { what, max } => write!(f, "the file can't hold more than {max} {what}"),
+        }
+    }
+}
+
+impl std::error::Error for PuzError {}
+
+/// The checksum used throughout the format: rotate right, then add.
+fn checksum(data: &[u8], start: u16) -> u16 {
+    data.iter().fold(start, |sum, b| sum.rotate_right(1).wrapping_add(*b as u16))
+}
+
+fn from_latin1(data: &[u8]) -> String {
+    data.iter().map(|b| *b as char).collect()
+}
+
+fn to_latin1(s: &str) -> Vec<u8> {
+    s.chars().map(|c| u8::try_from(c as u32).unwrap_or(b'?')).collect()
+}
+
+fn with_nul(s: &str) -> Vec<u8> {
+    let mut data = to_latin1(s);
+    data.push(0);
+    data
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], PuzError> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len()).ok_or(PuzError::Truncated)?;
+        let taken = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16, PuzError> {
+        let data = self.take(2)?;
+        Ok(u16::from_le_bytes([data[0], data[1]]))
+    }
+
+    fn string(&mut self) -> Result<String, PuzError> {
+        let rest = &self.data[self.pos..];
+        let len = rest.iter().position(|b| *b == 0).ok_or(PuzError::Truncated)?;
+        let s = from_latin1(&rest[..len]);
+        self.pos += len + 1;
+        Ok(s)
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+}
+
+impl Puz {
+    /// Whether the data looks like a `.puz` file.
+    pub fn is_puz(data: &[u8]) -> bool {
+        data.get(2..2 + MAGIC.len()) == Some(MAGIC)
+    }
+
+    /// Reads a `.puz` file, checking every checksum in it.
+    pub fn parse(data: &[u8]) -> Result<Self, PuzError> {
+        if !Self::is_puz(data) {
+            return Err(PuzError::NotAPuz)
+        }
+        let mut reader = Reader { data, pos: 0 };
+        let header = reader.take(HEADER_LEN)?;
+        let file_checksum = u16::from_le_bytes([header[0x00], header[0x01]]);
+        let cib_checksum = u16::from_le_bytes([header[0x0E], header[0x0F]]);
+        let version = &header[0x18..0x1C];
+        let width = header[0x2C] as usize;
+        let height = header[0x2D] as usize;
+        let clue_count = u16::from_le_bytes([header[0x2E], header[0x2F]]) as usize;
+        let puzzle_type = u16::from_le_bytes([header[0x30], header[0x31]]);
+        let solution_state = match u16::from_le_bytes([header[0x32], header[0x33]]) {
+            SOLUTION_SCRAMBLED => SolutionState::Scrambled,
+            SOLUTION_NONE => SolutionState::Missing,
+            _ => SolutionState::Plain,
+        };
+        if puzzle_type == 0x0401 {
+            return Err(PuzError::Unsupported("diagramless"))
+        }
+
+        let solution = reader.take(width * height)?.to_vec();
+        let fill = reader.take(width * height)?.to_vec();
+        let title = reader.string()?;
+        let author = reader.string()?;
+        let copyright = reader.string()?;
+        let clues = (0..clue_count).map(|_| reader.string()).collect::<Result<Vec<String>, PuzError>>()?;
+        // files from before notes existed can stop after the clues.
+        let notes = match reader.remaining() {
+            0 => String::new(),
+            _ => reader.string()?,
+        };
+
+        let puz_without_extensions = Self {
+            width, height, solution, fill, title, author, copyright, clues, notes, solution_state,
+            rebus: HashMap::new(), markup: Vec::new(), timer: None,
+        };
+        let sums = puz_without_extensions.checksums(version, &header[0x2C..HEADER_LEN]);
+        if sums.cib != cib_checksum {
+            return Err(PuzError::Checksum("header"))
+        }
+        if sums.file != file_checksum {
+            return Err(PuzError::Checksum("file"))
+        }
+        if sums.masked() != header[0x10..0x18] {
+            return Err(PuzError::Checksum("masked"))
+        }
+
+        let mut puz = puz_without_extensions;
+        let mut rebus_squares = Vec::new();
+        let mut rebus_table = HashMap::new();
+        while reader.remaining() >= 8 {
+            let name = reader.take(4)?;
+            let len = reader.u16()? as usize;
+            let section_checksum = reader.u16()?;
+            let section = reader.take(len)?;
+            if reader.remaining() > 0 {
+                reader.take(1)?;
+            }
+            if checksum(section, 0) != section_checksum {
+                return Err(PuzError::Checksum(match name {
+                    b"GRBS" => "GRBS",
+                    b"RTBL" => "RTBL",
+                    b"GEXT" => "GEXT",
+                    b"LTIM" => "LTIM",
+                    _ => "extension",
+                }))
+            }
+            match name {
+                b"GRBS" if section.len() == width * height => rebus_squares = section.to_vec(),
+                b"RTBL" => rebus_table = parse_rebus_table(&from_latin1(section)),
+                b"GEXT" if section.len() == width * height => puz.markup = section.to_vec(),
+                b"LTIM" => puz.timer = parse_timer(&from_latin1(section)),
+                _ => (),
+            }
+        }
+        puz.rebus = rebus_squares.iter()
+            .enumerate()
+            .filter(|(_, key)| **key != 0)
+            .filter_map(|(i, key)| rebus_table.get(&(key - 1)).map(|answer| (i, answer.clone())))
+            .collect();
+        Ok(puz)
+    }
+
+    /// Writes the puzzle out with fresh checksums. Fails if the grid, the
+    /// clues or the rebus answers won't fit in the format's counts.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, PuzError> {
+        let version = b"1.3\0";
+        let board_header = self.board_header()?;
+        let sums = self.checksums(version, &board_header);
+
+        let mut data = Vec::new();
+        data.extend(sums.file.to_le_bytes());
+        data.extend(MAGIC);
+        data.extend(sums.cib.to_le_bytes());
+        data.extend(sums.masked());
+        data.extend(version);
+        data.extend([0; 2]);
+        data.extend([0; 2]);
+        data.extend([0; 12]);
+        data.extend(board_header);
+        data.extend(&self.solution);
+        data.extend(&self.fill);
+        data.extend(with_nul(&self.title));
+        data.extend(with_nul(&self.author));
+        data.extend(with_nul(&self.copyright));
+        for clue in &self.clues {
+            data.extend(with_nul(clue));
+        }
+        data.extend(with_nul(&self.notes));
+
+        if !self.rebus.is_empty() {
+            let mut answers: Vec<&String> = self.rebus.values().collect();
+            answers.sort();
+            answers.dedup();
+            // keys are stored one more than their place in the table, so 0 can mean no rebus.
+            let too_many = PuzError::TooMany { what: "different rebus answers", max: u8::MAX as usize };
+            let squares: Vec<u8> = (0..self.width * self.height)
+                .map(|i| match self.rebus.get(&i) {
+                    Some(answer) => answers.iter().position(|x| *x == answer)
+                        .map_or(Ok(0), |key| u8::try_from(key + 1).map_err(|_| too_many.clone())),
+                    None => Ok(0),
+                })
+                .collect::<Result<_, _>>()?;
+            let table: String = answers.iter().enumerate().map(|(key, answer)| format!("{key:>2}:{answer};")).collect();
+            write_section(&mut data, b"GRBS", &squares);
+            write_section(&mut data, b"RTBL", &to_latin1(&table));
+        }
+        if let Some(timer) = self.timer {
+            write_section(&mut data, b"LTIM", format!("{},{}", timer.seconds, timer.stopped as u8).as_bytes());
+        }
+        if self.markup.iter().any(|flags| *flags != 0) {
+            write_section(&mut data, b"GEXT", &self.markup);
+        }
+        Ok(data)
+    }
+
+    /// The part of the header the CIB checksum covers.
+    fn board_header(&self) -> Result<Vec<u8>, PuzError> {
+        let state = match self.solution_state {
+            SolutionState::Plain => SOLUTION_PLAIN,
+            SolutionState::Scrambled => SOLUTION_SCRAMBLED,
+            SolutionState::Missing => SOLUTION_NONE,
+        };
+        let width = u8::try_from(self.width).map_err(|_| PuzError::TooMany { what: "columns", max: u8::MAX as usize })?;
+        let height = u8::try_from(self.height).map_err(|_| PuzError::TooMany { what: "rows", max: u8::MAX as usize })?;
+        let clues = u16::try_from(self.clues.len()).map_err(|_| PuzError::TooMany { what: "clues", max: u16::MAX as usize })?;
+        let mut data = vec![width, height];
+        data.extend(clues.to_le_bytes());
+        data.extend(1u16.to_le_bytes());
+        data.extend(state.to_le_bytes());
+        Ok(data)
+    }
+
+    fn checksums(&self, version: &[u8], board_header: &[u8]) -> Checksums {
+        let has_notes = version >= b"1.3".as_slice();
+        let mut text = 0;
+        for s in [&self.title, &self.author, &self.copyright] {
+            if !s.is_empty() {
+                text = checksum(&with_nul(s), text);
+            }
+        }
+        for clue in &self.clues {
+            text = checksum(&to_latin1(clue), text);
+        }
+        if has_notes && !self.notes.is_empty() {
+            text = checksum(&with_nul(&self.notes), text);
+        }
+
+        let cib = checksum(board_header, 0);
+        let solution = checksum(&self.solution, 0);
+        let fill = checksum(&self.fill, 0);
+
+        let mut file = checksum(&self.solution, cib);
+        file = checksum(&self.fill, file);
+        for s in [&self.title, &self.author, &self.copyright] {
+            if !s.is_empty() {
+                file = checksum(&with_nul(s), file);
+            }
+        }
+        for clue in &self.clues {
+            file = checksum(&to_latin1(clue), file);
+        }
+        if has_notes && !self.notes.is_empty() {
+            file = checksum(&with_nul(&self.notes), file);
+        }
+
+        Checksums { file, cib, solution, fill, text }
+    }
+}
+
+struct Checksums {
+    file: u16,
+    cib: u16,
+    solution: u16,
+    fill: u16,
+    text: u16,
+}
+
+impl Checksums {
+    /// The low then high bytes of each checksum, xored with "ICHEATED".
+    fn masked(&self) -> [u8; 8] {
+        let sums = [self.cib, self.solution, self.fill, self.text];
+        let mut masked = [0; 8];
+        for (i, sum) in sums.iter().enumerate() {
+            masked[i] = MASK[i] ^ (sum & 0xFF) as u8;
+            masked[i + 4] = MASK[i + 4] ^ (sum >> 8) as u8;
+        }
+        masked
+    }
+}
+
+fn write_section(data: &mut Vec<u8>, name: &[u8; 4], section: &[u8]) {
+    data.extend(name);
+    data.extend((section.len() as u16).to_le_bytes());
+    data.extend(checksum(section, 0).to_le_bytes());
+    data.extend(section);
+    data.push(0);
+}
+
+/// Reads entries like ` 0:HEART; 1:CLUB;`.
+fn parse_rebus_table(table: &str) -> HashMap<u8, String> {
+    table.split(';')
+        .filter_map(|entry| entry.split_once(':'))
+        .filter_map(|(key, answer)| key.trim().parse().ok().map(|key| (key, answer.to_string())))
+        .collect()
+}
+
+fn parse_timer(timer: &str) -> Option<Timer> {
+    let (seconds, stopped) = timer.split_once(',')?;
+    Some(Timer { seconds: seconds.trim().parse().ok()?, stopped: stopped.trim() == "1" })
+}
+
+impl Crossword {
+    /// Builds a crossword from a `.puz` file, along with its solution when
+    /// the file has one that isn't scrambled. Rebus answers are kept whole,
+    /// but a rebus the solver filled in only keeps its first letter, as the
+    /// RUSR section isn't read. Circled squares from the GEXT section are
+    /// kept, but the timer has nowhere to go yet.
+    pub fn from_puz(puz: &Puz) -> Result<(Crossword, Option<Solution>), PuzError> {
+        let mut grid = Grid::new(puz.width, puz.height);
+        for y in 0..puz.height {
+            for x in 0..puz.width {
+                let i = y * puz.width + x;
+                grid.set_block(x, y, puz.solution[i] == b'.');
+                if !matches!(puz.fill[i], b'-' | b'.') {
+                    grid.set_letter(x, y, (puz.fill[i] as char).into());
+                }
+                if puz.markup.get(i).is_some_and(|flags| flags & CIRCLED != 0) {
+                    grid.set_style(x, y, CellStyle { circled: true, ..CellStyle::default() });
+                }
+            }
+        }
+
+        let mut crossword = Crossword::from_grid(&grid);
+        let entries = grid.entries();
+        if entries.len() != puz.clues.len() {
+            return Err(PuzError::ClueCount { expected: entries.len(), found: puz.clues.len() })
+        }
+        for (entry, hint) in entries.iter().zip(&puz.clues) {
+            let clues = match entry.direction {
+                Direction::Across => &mut crossword.across,
+                Direction::Down => &mut crossword.down,
+            };
+            if let Some(clue) = clues.get_mut(&entry.id()) {
+                (clue.hint, clue.enumeration) = Enumeration::split_hint(hint);
+            }
+        }
+        crossword.set_metadata(PuzzleMetadata {
+            author: Some(puz.author.clone()).filter(|x| !x.is_empty()),
+            copyright: Some(puz.copyright.clone()).filter(|x| !x.is_empty()),
+            notes: Some(puz.notes.clone()).filter(|x| !x.is_empty()),
+            ..PuzzleMetadata::default()
+        });
+
+        let solution = match puz.solution_state {
+            SolutionState::Plain => Some((0..puz.height)
+                .flat_map(|y| (0..puz.width).map(move |x| (x, y)))
+                .filter(|(x, y)| !grid.is_block(*x, *y))
+                .map(|(x, y)| {
+                    let i = y * puz.width + x;
+                    let c = puz.rebus.get(&i)
+                        .and_then(|answer| CellValue::try_from(answer.clone()).ok())
+                        .filter(|answer| !answer.is_blank())
+                        .unwrap_or_else(|| (puz.solution[i] as char).into());
+                    ((x, y), c)
+                })
+                .collect()),
+            SolutionState::Scrambled | SolutionState::Missing => None,
+        };
+        Ok((crossword, solution))
+    }
+
+    /// Writes the crossword as a `.puz` file, renumbering it the standard
+    /// way. Without a solution the file says it has none. Rebus answers go
+    /// in the GRBS and RTBL sections, but only the first letter of a rebus
+    /// that's been filled in is written. Circles go in the GEXT section;
+    /// the format has no shading or bars, so they're left out.
+    pub fn to_puz(&self, title: &str, solution: Option<&Solution>) -> Result<Puz, GridSizeError> {
+        let crossword = self.renumbered()?;
+        let grid = crossword.grid()?;
+        let (width, height) = (grid.width(), grid.height());
+        let squares = || (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)));
+
+        let latin1 = |c: &CellValue| c.as_str().chars().next().map_or(b'?', |c| u8::try_from(c as u32).unwrap_or(b'?'));
+        let answers = squares()
+            .map(|(x, y)| match grid.is_block(x, y) {
+                true => b'.',
+                false => solution.and_then(|s| s.letter(x, y)).map_or(b'X', latin1),
+            })
+            .collect();
+        let fill = squares()
+            .map(|(x, y)| match grid.letter(x, y) {
+                None => b'.',
+                Some(c) if c.is_blank() => b'-',
+                Some(c) => latin1(c),
+            })
+            .collect();
+        let rebus = squares()
+            .enumerate()
+            .filter_map(|(i, (x, y))| solution.and_then(|s| s.letter(x, y)).map(|c| (i, c)))
+            .filter(|(_, c)| c.letter_count() > 1)
+            .map(|(i, c)| (i, c.to_string()))
+            .collect();
+        let markup: Vec<u8> = squares()
+            .map(|(x, y)| match grid.style(x, y).circled {
+                true => CIRCLED,
+                false => 0,
+            })
+            .collect();
+        // the format has nowhere else for enumerations or links, so they're
+        // written into the clues as they'd be printed.
+        let linked_from: HashMap<&str, &str> = crossword.across.iter()
+            .chain(crossword.down.iter())
+            .flat_map(|(id, clue)| clue.linked.iter().map(move |linked| (linked.as_str(), id.as_str())))
+            .collect();
+        let clues = grid.entries().iter()
+            .filter_map(|entry| {
+                let clues = match entry.direction {
+                    Direction::Across => &crossword.across,
+                    Direction::Down => &crossword.down,
+                };
+                let id = entry.id();
+                clues.get(&id).map(|clue| match (&clue.enumeration, linked_from.get(id.as_str())) {
+                    (_, Some(head)) if clue.hint.trim().is_empty() => format!("See {}", head.trim_end_matches(|c: char| !c.is_ascii_digit())),
+                    (Some(enumeration), _) => format!("{} ({enumeration})", clue.hint),
+                    (None, _) => clue.hint.clone(),
+                })
+            })
+            .collect();
+        let metadata = self.metadata();
+
+        Ok(Puz {
+            width,
+            height,
+            solution: answers,
+            fill,
+            title: title.to_string(),
+            author: metadata.author.clone().unwrap_or_default(),
+            copyright: metadata.copyright.clone().unwrap_or_default(),
+            clues,
+            notes: metadata.notes.clone().unwrap_or_default(),
+            solution_state: match solution {
+                Some(_) => SolutionState::Plain,
+                None => SolutionState::Missing,
+            },
+            rebus,
+            markup: match markup.contains(&CIRCLED) {
+                true => markup,
+                false => Vec::new(),
+            },
+            timer: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{checksum, Puz, PuzError, SolutionState, Timer};
+    use crate::crossword::Crossword;
+    use crate::crossword::test_fixtures::{assert_same_hints, demo_answers};
+
+    /// A 3x3 grid with the middle square blocked out.
+    fn ring() -> Puz {
+        Puz {
+            width: 3,
+            height: 3,
+            solution: b"CATO.ETEN".to_vec(),
+            fill: b"C--O.E---".to_vec(),
+            title: "Ring".to_string(),
+            author: "Bob".to_string(),
+            copyright: "".to_string(),
+            clues: vec!["Pet".to_string(), "Baby bed".to_string(), "Ten".to_string(), "Digit".to_string()],
+            notes: "Caf\u{e9}".to_string(),
+            solution_state: SolutionState::Plain,
+            rebus: HashMap::new(),
+            markup: Vec::new(),
+            timer: None,
+        }
+    }
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(checksum(&[], 0), 0);
+        assert_eq!(checksum(&[1, 2], 0), 0x8002);
+        assert_eq!(checksum(&[1], 3), 0x8002);
+    }
+
+    #[test]
+    fn test_too_big_to_write() {
+        let mut puz = ring();
+        puz.width = 256;
+        assert_eq!(puz.to_bytes(), Err(PuzError::TooMany { what: "columns", max: 255 }));
+
+        let mut puz = ring();
+        (puz.width, puz.height) = (16, 16);
+        puz.rebus = (0..256).map(|i| (i, format!("R{i}"))).collect();
+        assert_eq!(puz.to_bytes(), Err(PuzError::TooMany { what: "different rebus answers", max: 255 }));
+        puz.rebus.remove(&0);
+        assert!(puz.to_bytes().is_ok());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut puz = ring();
+        puz.rebus = HashMap::from([(0, "CAT".to_string())]);
+        puz.timer = Some(Timer { seconds: 42, stopped: true });
+        puz.markup = vec![0x80, 0, 0, 0, 0, 0, 0, 0, 0];
+        let data = puz.to_bytes().unwrap();
+        assert!(Puz::is_puz(&data));
+        assert_eq!(Puz::parse(&data), Ok(puz));
+    }
+
+    #[test]
+    fn test_bad_checksums() {
+        let mut data = ring().to_bytes().unwrap();
+        let last_clue = data.len() - 8;
+        data[last_clue] ^= 1;
+        assert_eq!(Puz::parse(&data), Err(PuzError::Checksum("file")));
+        assert_eq!(Puz::parse(b"not a puzzle"), Err(PuzError::NotAPuz));
+        assert_eq!(Puz::parse(&ring().to_bytes().unwrap()[..60]), Err(PuzError::Truncated));
+    }
+
+    #[test]
+    fn test_to_crossword() {
+        let (cw, solution) = Crossword::from_puz(&ring()).unwrap();
+        let hints: HashMap<&str, &str> = cw.hints().collect();
+        assert_eq!(hints["1a"], "Pet");
+        assert_eq!(hints["1d"], "Baby bed");
+        assert_eq!(hints["2d"], "Ten");
+        assert_eq!(hints["3a"], "Digit");
+        assert_eq!(cw.letter(0, 1), Some(&'O'.into()));
+        assert!(cw.letter(1, 0).is_some_and(|x| x.is_blank()));
+        assert_eq!(cw.metadata().author.as_deref(), Some("Bob"));
+        let solution = solution.unwrap();
+        assert_eq!(solution.letter(2, 2), Some(&'N'.into()));
+        assert!(solution.fits(&cw).is_ok());
+
+        let mut puz = ring();
+        puz.markup = vec![0, 0, 0x80, 0, 0, 0, 0, 0, 0];
+        let (cw, _) = Crossword::from_puz(&puz).unwrap();
+        assert!(cw.grid().unwrap().style(2, 0).circled);
+        assert_eq!(cw.to_puz("Ring", None).unwrap().markup, puz.markup);
+
+        let mut puz = ring();
+        puz.clues[0] = "Pet (3)".to_string();
+        let (cw, _) = Crossword::from_puz(&puz).unwrap();
+        assert_eq!(cw.across["1a"].hint, "Pet");
+        assert_eq!(cw.to_puz("Ring", None).unwrap().clues[0], "Pet (3)");
+
+        let mut puz = ring();
+        puz.clues.pop();
+        assert_eq!(Crossword::from_puz(&puz).unwrap_err(), PuzError::ClueCount { expected: 4, found: 3 });
+    }
+
+    #[test]
+    fn test_from_crossword() {
+        let cw = Crossword::demo_grid();
+        let puz = cw.to_puz("Demo", None).unwrap();
+        assert_eq!((puz.width, puz.height), (8, 8));
+        assert_eq!(puz.solution_state, SolutionState::Missing);
+        assert_eq!(puz.clues[0], "For all the money that e'er I had");
+        assert_eq!(puz.clues.len(), 4);
+
+        let mut linked = cw.clone();
+        linked.across.get_mut("1a").unwrap().linked = vec!["3a".to_string()];
+        linked.across.get_mut("3a").unwrap().hint = String::new();
+        assert_eq!(linked.to_puz("Demo", None).unwrap().clues[3], "See 1");
+
+        let solution = cw.solution(&demo_answers(true)).unwrap();
+        let puz = Puz::parse(&cw.to_puz("Demo", Some(&solution)).unwrap().to_bytes().unwrap()).unwrap();
+        assert_eq!(puz.rebus, HashMap::from([(0, "FOR".to_string())]));
+        let (back, back_solution) = Crossword::from_puz(&puz).unwrap();
+        assert_eq!(back_solution, Some(solution));
+        assert_same_hints(&cw, &back);
+    }
+}
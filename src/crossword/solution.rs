@@ -0,0 +1,347 @@
+use std::{collections::HashMap, fmt::{self, Display}};
+
+use serde::{Deserialize, Serialize};
+
+use super::{cell_value::{split_squares, BLANK}, Cell, CellValue, CellValueError, Crossword, ValidationError, MAX_CELL_LENGTH};
+
+/// The correct letters for every square in a crossword. It is kept apart
+/// from the crossword itself so it is never sent to solvers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Solution {
+    letters: HashMap<(usize, usize), CellValue>,
+}
+
+/// A solution as it is uploaded: letters for individual squares, whole
+/// answers for clues, or a mix of the two. A rebus square in an answer is
+/// written in brackets, e.g. `[STAR]TLE`.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SolutionInput {
+    #[serde(default)]
+    cells: Vec<Cell>,
+    #[serde(default)]
+    clues: HashMap<String, String>,
+}
+
+/// The part of the grid to check or reveal.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "TargetBody")]
+pub enum Target {
+    Cell(usize, usize),
+    Clue(String),
+    Grid,
+}
+
+/// A target is sent as `{"x": 0, "y": 2}`, `{"clue": "3a"}` or `{}`.
+#[derive(Deserialize)]
+struct TargetBody {
+    x: Option<usize>,
+    y: Option<usize>,
+    clue: Option<String>,
+}
+
+impl TryFrom<TargetBody> for Target {
+    type Error = String;
+
+    fn try_from(body: TargetBody) -> Result<Self, Self::Error> {
+        match (body.x, body.y, body.clue) {
+            (Some(x), Some(y), None) => Ok(Target::Cell(x, y)),
+            (None, None, Some(clue)) => Ok(Target::Clue(clue)),
+            (None, None, None) => Ok(Target::Grid),
+            _ => Err("give either a square's x and y, a clue, or nothing for the whole grid".to_string()),
+        }
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Cell(x, y) => write!(f, "cell {x},{y}"),
+            Target::Clue(id) => write!(f, "clue {id}"),
+            Target::Grid => write!(f, "grid"),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Square {
+    pub x: usize,
+    pub y: usize,
+}
+
+/// The squares in a target that have been filled in wrongly. Empty squares
+/// aren't wrong, but a target is only solved once they're all filled in.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub wrong: Vec<Square>,
+    pub solved: bool,
+}
+
+impl Solution {
+    pub fn letter(&self, x: usize, y: usize) -> Option<&CellValue> {
+        self.letters.get(&(x, y))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.letters.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &CellValue)> + '_ {
+        self.letters.iter().map(|(square, c)| (*square, c))
+    }
+
+    /// Checks there's a letter for every square in the crossword and none
+    /// for squares that aren't in it.
+    pub fn fits(&self, crossword: &Crossword) -> Result<(), Vec<ValidationError>> {
+        let mut errors: Vec<ValidationError> = sorted_squares(crossword.index.keys().copied())
+            .into_iter()
+            .filter(|(x, y)| self.letter(*x, *y).is_none())
+            .map(|(x, y)| ValidationError::new(None, Some((x, y)), "the solution has no letter for the square".to_string()))
+            .collect();
+        errors.extend(sorted_squares(self.letters.keys().copied())
+            .into_iter()
+            .filter(|square| !crossword.index.contains_key(square))
+            .map(|square| ValidationError::new(None, Some(square), "the solution has a letter for a square that isn't in the grid".to_string())));
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+}
+
+impl FromIterator<((usize, usize), CellValue)> for Solution {
+    fn from_iter<T: IntoIterator<Item = ((usize, usize), CellValue)>>(iter: T) -> Self {
+        Self { letters: iter.into_iter().collect() }
+    }
+}
+
+fn sorted_squares(squares: impl Iterator<Item = (usize, usize)>) -> Vec<(usize, usize)> {
+    let mut squares: Vec<(usize, usize)> = squares.collect();
+    squares.sort_by_key(|(x, y)| (*y, *x));
+    squares
+}
+
+fn add_letter(letters: &mut HashMap<(usize, usize), CellValue>, errors: &mut Vec<ValidationError>, clue: Option<&str>, square: (usize, usize), c: &CellValue) {
+    if c.is_blank() {
+        errors.push(ValidationError::new(clue, Some(square), "the solution can't have a blank square".to_string()));
+        return
+    }
+    if c.letter_count() > *MAX_CELL_LENGTH {
+        errors.push(ValidationError::new(clue, Some(square), CellValueError::TooLong { value: c.to_string(), max: *MAX_CELL_LENGTH }.to_string()));
+        return
+    }
+    let c = c.to_uppercase();
+    match letters.get(&square) {
+        Some(other) if *other != c => {
+            errors.push(ValidationError::new(clue, Some(square), format!("the solution gives both {other} and {c} for the square")));
+        },
+        Some(_) => (),
+        None => {
+            letters.insert(square, c);
+        },
+    }
+}
+
+impl Crossword {
+    /// Turns an uploaded solution into a letter for each square. Answers
+    /// given for crossing clues, or for a clue and one of its squares, have
+    /// to agree.
+    pub fn solution(&self, input: &SolutionInput) -> Result<Solution, Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        let mut letters: HashMap<(usize, usize), CellValue> = HashMap::new();
+
+        for cell in &input.cells {
+            add_letter(&mut letters, &mut errors, None, (cell.x, cell.y), &cell.c);
+        }
+
+        let mut ids: Vec<&String> = input.clues.keys().collect();
+        ids.sort_by(|a, b| (a.ends_with('d'), super::clue_order(a)).cmp(&(b.ends_with('d'), super::clue_order(b))));
+        for id in ids {
+            let answer = match split_squares(&input.clues[id], *MAX_CELL_LENGTH) {
+                Ok(answer) => answer,
+                Err(e) => {
+                    errors.push(ValidationError::new(Some(id), None, e.to_string()));
+                    continue;
+                }
+            };
+            let clue = match self.across.get(id).or_else(|| self.down.get(id)) {
+                Some(clue) => clue,
+                None => {
+                    errors.push(ValidationError::new(Some(id), None, "the solution is for a clue that isn't in the crossword".to_string()));
+                    continue;
+                }
+            };
+            if answer.len() != clue.cells.len() {
+                errors.push(ValidationError::new(Some(id), None,
+                    format!("the answer fills {} squares but the clue has {}", answer.len(), clue.cells.len())));
+                continue;
+            }
+            for (cell, c) in clue.cells.iter().zip(answer) {
+                add_letter(&mut letters, &mut errors, Some(id), (cell.x, cell.y), &c);
+            }
+        }
+
+        let solution = Solution { letters };
+        if let Err(fit_errors) = solution.fits(self) {
+            errors.extend(fit_errors);
+        }
+
+        match errors.is_empty() {
+            true => Ok(solution),
+            false => Err(errors),
+        }
+    }
+
+    /// What's currently written in a square, if it's in the grid.
+    pub fn letter(&self, x: usize, y: usize) -> Option<&CellValue> {
+        let refs = self.index.get(&(x, y))?;
+        // a square may have been filled in on only one of its clues before they were kept in step.
+        Some(refs.iter()
+            .filter_map(|cell_ref| self.cell(cell_ref))
+            .map(|cell| &cell.c)
+            .find(|c| !c.is_blank())
+            .unwrap_or(&BLANK))
+    }
+
+    /// The squares a target covers, or `None` if it isn't in the crossword.
+    fn target_squares(&self, target: &Target) -> Option<Vec<(usize, usize)>> {
+        match target {
+            Target::Cell(x, y) => self.index.contains_key(&(*x, *y)).then(|| vec![(*x, *y)]),
+            Target::Clue(id) => self.across.get(id)
+                .or_else(|| self.down.get(id))
+                .map(|clue| clue.cells.iter().map(|cell| (cell.x, cell.y)).collect()),
+            Target::Grid => Some(sorted_squares(self.index.keys().copied())),
+        }
+    }
+
+    /// Compares what's been filled in against the solution. Returns `None`
+    /// if the target isn't in the crossword.
+    pub fn check(&self, solution: &Solution, target: &Target) -> Option<CheckResult> {
+        let squares = self.target_squares(target)?;
+        let mut wrong = Vec::new();
+        let mut solved = true;
+        for (x, y) in squares {
+            let (Some(filled), Some(answer)) = (self.letter(x, y), solution.letter(x, y)) else { continue };
+            if filled.is_blank() {
+                solved = false;
+            } else if !filled.same_letters(answer) {
+                wrong.push(Square { x, y });
+            }
+        }
+        solved = solved && wrong.is_empty();
+        Some(CheckResult { wrong, solved })
+    }
+
+    /// Fills in a square or clue from the solution and returns the squares
+    /// as they now are. Returns `None` if the target isn't in the crossword
+    /// or is the whole grid.
+    pub fn reveal(&mut self, solution: &Solution, target: &Target) -> Option<Vec<Cell>> {
+        if *target == Target::Grid {
+            return None
+        }
+        let squares = self.target_squares(target)?;
+        let mut revealed = Vec::new();
+        for (x, y) in squares {
+            if let Some(c) = solution.letter(x, y) {
+                let cell = Cell { x, y, c: c.clone() };
+                self.set_cell(cell.clone());
+                revealed.push(cell);
+            }
+        }
+        Some(revealed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{SolutionInput, Square, Target};
+    use crate::crossword::{Cell, Crossword};
+
+    fn demo_solution() -> SolutionInput {
+        SolutionInput {
+            cells: vec![],
+            clues: HashMap::from([
+                ("1a".to_string(), "forallth".to_string()),
+                ("3a".to_string(), "ISPENTIT".to_string()),
+                ("1d".to_string(), "FANDIFOR".to_string()),
+                ("2d".to_string(), "LIKENESS".to_string()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_solution_from_clues() {
+        let cw = Crossword::demo_grid();
+        let solution = cw.solution(&demo_solution()).unwrap();
+        assert_eq!(solution.letter(0, 0), Some(&'F'.into()));
+        assert_eq!(solution.letter(4, 4), Some(&'N'.into()));
+        assert!(solution.fits(&cw).is_ok());
+    }
+
+    #[test]
+    fn test_solution_errors() {
+        let cw = Crossword::demo_grid();
+        let mut input = demo_solution();
+        input.clues.insert("1d".to_string(), "XANDIFOR".to_string());
+        input.clues.insert("9a".to_string(), "NOPE".to_string());
+        input.clues.remove("2d");
+        let errors = cw.solution(&input).unwrap_err();
+        assert!(errors.iter().any(|e| e.clue.as_deref() == Some("1d") && e.cell == Some((0, 0))));
+        assert!(errors.iter().any(|e| e.clue.as_deref() == Some("9a")));
+        assert!(errors.iter().any(|e| e.cell == Some((4, 1))));
+
+        let input = SolutionInput { cells: vec![Cell { x: 9, y: 9, c: 'Q'.into() }], ..demo_solution() };
+        let errors = cw.solution(&input).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].cell, Some((9, 9)));
+    }
+
+    #[test]
+    fn test_rebus_solution() {
+        let mut cw = Crossword::demo_grid();
+        let mut input = demo_solution();
+        input.clues.insert("1a".to_string(), "[for]orallth".to_string());
+        input.clues.insert("1d".to_string(), "[FOR]ANDIFOR".to_string());
+        let solution = cw.solution(&input).unwrap();
+        assert_eq!(solution.letter(0, 0).map(|x| x.as_str()), Some("FOR"));
+
+        cw.update_cell(Cell { x: 0, y: 0, c: "for".parse().unwrap() }).unwrap();
+        assert!(cw.check(&solution, &Target::Cell(0, 0)).unwrap().solved);
+        cw.update_cell(Cell { x: 0, y: 0, c: 'F'.into() }).unwrap();
+        assert_eq!(cw.check(&solution, &Target::Cell(0, 0)).unwrap().wrong, vec![Square { x: 0, y: 0 }]);
+
+        input.clues.insert("1a".to_string(), "[FOR]ALLTHEMO".to_string());
+        let errors = cw.solution(&input).unwrap_err();
+        assert_eq!(errors[0].reason, "the answer fills 9 squares but the clue has 8");
+    }
+
+    #[test]
+    fn test_target_from_json() {
+        assert_eq!(serde_json::from_str::<Target>(r#"{"x":1,"y":2}"#).unwrap(), Target::Cell(1, 2));
+        assert_eq!(serde_json::from_str::<Target>(r#"{"clue":"1a"}"#).unwrap(), Target::Clue("1a".to_string()));
+        assert_eq!(serde_json::from_str::<Target>("{}").unwrap(), Target::Grid);
+        assert!(serde_json::from_str::<Target>(r#"{"x":1}"#).is_err());
+    }
+
+    #[test]
+    fn test_check_and_reveal() {
+        let mut cw = Crossword::demo_grid();
+        let solution = cw.solution(&demo_solution()).unwrap();
+
+        cw.update_cell(Cell { x: 0, y: 0, c: 'f'.into() }).unwrap();
+        cw.update_cell(Cell { x: 1, y: 0, c: 'X'.into() }).unwrap();
+        let check = cw.check(&solution, &Target::Clue("1a".to_string())).unwrap();
+        assert_eq!(check.wrong, vec![Square { x: 1, y: 0 }]);
+        assert!(!check.solved);
+        assert!(cw.check(&solution, &Target::Cell(2, 2)).is_none());
+
+        let revealed = cw.reveal(&solution, &Target::Clue("1a".to_string())).unwrap();
+        assert_eq!(revealed.len(), 8);
+        assert!(cw.check(&solution, &Target::Clue("1a".to_string())).unwrap().solved);
+        assert_eq!(cw.letter(4, 0), Some(&'L'.into()));
+        assert!(!cw.check(&solution, &Target::Grid).unwrap().solved);
+        assert!(cw.reveal(&solution, &Target::Grid).is_none());
+    }
+}
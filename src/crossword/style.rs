@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+/// How a square is marked out beyond its letter. A bar is a thick line
+/// between two squares that ends an entry the way a block would. Each square
+/// only has the bars on its right and bottom edges, so every edge belongs to
+/// one square.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct CellStyle {
+    #[serde(skip_serializing_if = "is_false")]
+    pub circled: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub shaded: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub bar_right: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub bar_bottom: bool,
+}
+
+fn is_false(x: &bool) -> bool {
+    !x
+}
+
+impl CellStyle {
+    /// Whether the square looks like any other.
+    pub fn is_plain(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A square's style as it's saved and sent, e.g.
+/// `{"x": 0, "y": 2, "circled": true, "bar_right": true}`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StyledSquare {
+    pub x: usize,
+    pub y: usize,
+    #[serde(flatten)]
+    pub style: CellStyle,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CellStyle, StyledSquare};
+
+    #[test]
+    fn test_styled_square_json() {
+        let square: StyledSquare = serde_json::from_str(r#"{"x":1,"y":2,"circled":true}"#).unwrap();
+        assert_eq!(square, StyledSquare { x: 1, y: 2, style: CellStyle { circled: true, ..CellStyle::default() } });
+        assert_eq!(serde_json::to_string(&square).unwrap(), r#"{"x":1,"y":2,"circled":true}"#);
+        assert!(serde_json::from_str::<StyledSquare>(r#"{"y":2,"shaded":true}"#).is_err());
+    }
+}
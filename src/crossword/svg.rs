@@ -0,0 +1,193 @@
+use std::fmt::Write;
+
+use quick_xml::escape::escape;
+
+use super::{Crossword, Direction, GridSizeError};
+
+/// How a crossword is drawn as SVG.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgOptions {
+    /// The width of a square, in pixels.
+    pub cell_size: u32,
+    /// Whether to draw the letters filled in so far.
+    pub fills: bool,
+    /// Whether to draw the numbers in the corners of squares.
+    pub numbers: bool,
+    /// A clue id, e.g. `3a`, whose squares are shaded.
+    pub highlight: Option<String>,
+    /// Whether to list the clues beneath the grid.
+    pub clues: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self { cell_size: 32, fills: true, numbers: true, highlight: None, clues: false }
+    }
+}
+
+impl SvgOptions {
+    /// A small picture of the grid's shape, for lists of puzzles.
+    pub fn thumbnail() -> Self {
+        Self { cell_size: 6, fills: false, numbers: false, highlight: None, clues: false }
+    }
+}
+
+const HIGHLIGHT_COLOUR: &str = "#fde68a";
+const SHADE_COLOUR: &str = "#d1d5db";
+
+/// Splits text into lines of at most `width` characters, breaking between words.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+impl Crossword {
+    /// Draws the crossword as a standalone SVG document.
+    pub fn to_svg(&self, options: &SvgOptions) -> Result<String, GridSizeError> {
+        let grid = self.grid()?;
+        let size = options.cell_size.max(1) as f64;
+        let padding = (size / 4.0).max(1.0);
+        let grid_width = grid.width() as f64 * size;
+        let grid_height = grid.height() as f64 * size;
+
+        let highlighted: Vec<(usize, usize)> = options.highlight.as_ref()
+            .and_then(|id| self.across.get(id).or_else(|| self.down.get(id)))
+            .map(|clue| clue.cells.iter().map(|cell| (cell.x, cell.y)).collect())
+            .unwrap_or_default();
+        let numbers = self.square_numbers();
+
+        let mut body = String::new();
+        // bars are drawn after every square so that none is drawn over them.
+        let mut bars = String::new();
+        let stroke = (size / 16.0).max(0.5);
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                let (left, top) = (padding + x as f64 * size, padding + y as f64 * size);
+                let style = grid.style(x, y);
+                let fill = match (grid.is_block(x, y), highlighted.contains(&(x, y)), style.shaded) {
+                    (true, _, _) => "#000",
+                    (false, true, _) => HIGHLIGHT_COLOUR,
+                    (false, false, true) => SHADE_COLOUR,
+                    (false, false, false) => "#fff",
+                };
+                let _ = write!(body, r##"<rect x="{left}" y="{top}" width="{size}" height="{size}" fill="{fill}" stroke="#000" stroke-width="{stroke}"/>"##);
+                if style.circled {
+                    let _ = write!(body, r##"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="none" stroke="#000" stroke-width="{stroke}"/>"##, left + size / 2.0, top + size / 2.0, size * 0.45);
+                }
+                if style.bar_right {
+                    let _ = write!(bars, r##"<line x1="{0:.1}" y1="{top:.1}" x2="{0:.1}" y2="{1:.1}" stroke="#000" stroke-width="{2:.1}"/>"##, left + size, top + size, stroke * 4.0);
+                }
+                if style.bar_bottom {
+                    let _ = write!(bars, r##"<line x1="{left:.1}" y1="{1:.1}" x2="{0:.1}" y2="{1:.1}" stroke="#000" stroke-width="{2:.1}"/>"##, left + size, top + size, stroke * 4.0);
+                }
+                if options.numbers {
+                    if let Some(number) = numbers.get(&(x, y)) {
+                        let _ = write!(body, r#"<text x="{:.1}" y="{:.1}" font-size="{:.1}">{}</text>"#, left + size * 0.06, top + size * 0.32, size * 0.3, escape(number));
+                    }
+                }
+                if let Some(c) = grid.letter(x, y).filter(|c| options.fills && !c.is_blank()) {
+                    // a rebus is shrunk to fit across the square.
+                    let font_size = size * 0.6 * (1.6 / c.letter_count() as f64).min(1.0);
+                    let _ = write!(body, r#"<text x="{:.1}" y="{:.1}" font-size="{font_size:.1}" text-anchor="middle">{}</text>"#, left + size / 2.0, top + size * 0.85, escape(c.as_str()));
+                }
+            }
+        }
+        body.push_str(&bars);
+
+        let mut width = grid_width + 2.0 * padding;
+        let mut height = grid_height + 2.0 * padding;
+        if options.clues {
+            let font_size = (size * 0.4).max(10.0);
+            let line_height = font_size * 1.3;
+            let column_width = (grid_width / 2.0).max(font_size * 18.0);
+            let chars_per_line = ((column_width - padding) / (font_size * 0.55)).max(10.0) as usize;
+            let top = padding + grid_height + line_height * 1.5;
+            let mut bottom = top;
+            for (column, (direction, title)) in [(Direction::Across, "Across"), (Direction::Down, "Down")].into_iter().enumerate() {
+                let left = padding + column as f64 * column_width;
+                let mut y = top;
+                let _ = write!(body, r#"<text x="{left:.1}" y="{y:.1}" font-size="{font_size:.1}" font-weight="bold">{title}</text>"#);
+                for (number, text) in self.printed_clues(direction) {
+                    let text = format!("{number} {text}");
+                    for line in wrap(&text, chars_per_line) {
+                        y += line_height;
+                        let _ = write!(body, r#"<text x="{left:.1}" y="{y:.1}" font-size="{font_size:.1}">{}</text>"#, escape(line.as_str()));
+                    }
+                }
+                bottom = bottom.max(y);
+            }
+            width = width.max(2.0 * column_width + 2.0 * padding);
+            height = bottom + padding + line_height / 2.0;
+        }
+
+        Ok(format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.1}" height="{height:.1}" viewBox="0 0 {width:.1} {height:.1}" font-family="sans-serif"><rect width="100%" height="100%" fill="#fff"/>{body}</svg>"##
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{wrap, SvgOptions};
+    use crate::crossword::{Cell, CellStyle, Crossword, StyledSquare};
+
+    #[test]
+    fn test_svg() {
+        let mut cw = Crossword::demo_grid();
+        cw.update_cell(Cell { x: 0, y: 0, c: 'F'.into() }).unwrap();
+        let svg = cw.to_svg(&SvgOptions::default()).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<rect").count(), 1 + 64);
+        assert!(svg.contains(">F</text>"));
+        assert!(svg.contains(">3</text>"));
+
+        cw.update_cell(Cell { x: 4, y: 0, c: "STAR".parse().unwrap() }).unwrap();
+        assert!(cw.to_svg(&SvgOptions::default()).unwrap().contains(r#"font-size="7.7" text-anchor="middle">STAR</text>"#));
+        assert!(!svg.contains("Across"));
+
+        let options = SvgOptions { fills: false, highlight: Some("3a".to_string()), clues: true, ..SvgOptions::default() };
+        let svg = cw.to_svg(&options).unwrap();
+        assert!(!svg.contains(">F</text>"));
+        assert_eq!(svg.matches("#fde68a").count(), 8);
+        assert!(svg.contains("e&apos;er I</text>"));
+        assert!(svg.contains(">had (8)</text>"));
+
+        cw.styles = vec![
+            StyledSquare { x: 0, y: 0, style: CellStyle { circled: true, ..CellStyle::default() } },
+            StyledSquare { x: 1, y: 0, style: CellStyle { shaded: true, bar_right: true, ..CellStyle::default() } },
+        ];
+        let svg = cw.to_svg(&SvgOptions::default()).unwrap();
+        assert_eq!(svg.matches("<circle").count(), 1);
+        assert_eq!(svg.matches("#d1d5db").count(), 1);
+        assert!(svg.ends_with(r##"<line x1="72.0" y1="8.0" x2="72.0" y2="40.0" stroke="#000" stroke-width="8.0"/></svg>"##));
+
+        let thumbnail = cw.to_svg(&SvgOptions::thumbnail()).unwrap();
+        assert!(thumbnail.contains(r#"width="51.0""#));
+        assert!(!thumbnail.contains("<text"));
+
+        // clue ids are checked when saved, but may not have been when this was drawn.
+        let cw: Crossword = serde_json::from_str(r#"{"across":{"<script>1a":{"hint":"Pet","cells":[{"x":0,"y":0,"c":" "},{"x":1,"y":0,"c":" "}]}},"down":{}}"#).unwrap();
+        let svg = cw.to_svg(&SvgOptions::default()).unwrap();
+        assert!(svg.contains(">&lt;script&gt;1</text>"));
+        assert!(!svg.contains("<script>"));
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("I spent it in good company", 10), ["I spent it", "in good", "company"]);
+        assert_eq!(wrap("", 10), [""]);
+    }
+}
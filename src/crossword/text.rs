@@ -0,0 +1,168 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{CellStyle, CellValue, Crossword, Direction, GridSizeError};
+
+/// How a crossword is written out as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextOptions {
+    /// Whether to use terminal colours to pick out numbers and letters.
+    pub colour: bool,
+    /// Whether to list the clues after the grid.
+    pub clues: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self { colour: false, clues: true }
+    }
+}
+
+const DIM: &str = "\x1b[2m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// The width of a square inside its borders.
+const SQUARE_WIDTH: usize = 3;
+
+/// A square's letters centred in its width. A rebus too long to fit is cut
+/// short and ends with `…`. A circled square's letter is put in brackets and
+/// a shaded square is padded with `░`.
+fn square_text(value: &CellValue, style: CellStyle) -> String {
+    let letters: Vec<&str> = value.as_str().graphemes(true).collect();
+    match letters.len() {
+        0 | 1 if style.circled => format!("({:1})", value.as_str()),
+        0 | 1 if style.shaded => format!("░{:1}░", value.as_str()),
+        0 | 1 => format!(" {:1} ", value.as_str()),
+        n if n <= SQUARE_WIDTH => format!("{}{}", value.as_str(), " ".repeat(SQUARE_WIDTH - n)),
+        _ => format!("{}…", letters[..SQUARE_WIDTH - 1].concat()),
+    }
+}
+
+/// Puzzle text made safe to print to a terminal: control characters, which
+/// could move the cursor or change its colours, become spaces.
+pub fn printable(text: &str) -> String {
+    text.chars().map(|c| if c.is_control() { ' ' } else { c }).collect()
+}
+
+fn paint(text: &str, style: &str, colour: bool) -> String {
+    match colour && !text.trim().is_empty() {
+        true => format!("{style}{text}{RESET}"),
+        false => text.to_string(),
+    }
+}
+
+impl Crossword {
+    /// Draws the grid with box-drawing characters, two lines to a row: the
+    /// numbers, then the letters filled in so far. Bars are drawn with heavy
+    /// lines. The clues follow.
+    pub fn to_text(&self, options: &TextOptions) -> Result<String, GridSizeError> {
+        let grid = self.grid()?;
+        let numbers = self.square_numbers();
+        // the border below row `y`, or above the grid for `None`.
+        let border = |y: Option<usize>, left: char, middle: char, right: char| {
+            let line = (0..grid.width())
+                .map(|x| match y.is_some_and(|y| grid.style(x, y).bar_bottom) {
+                    true => "━".repeat(SQUARE_WIDTH),
+                    false => "─".repeat(SQUARE_WIDTH),
+                })
+                .collect::<Vec<_>>()
+                .join(&middle.to_string());
+            format!("{left}{line}{right}\n")
+        };
+
+        let mut out = String::new();
+        if grid.width() == 0 {
+            return Ok(out)
+        }
+        out.push_str(&border(None, '┌', '┬', '┐'));
+        for y in 0..grid.height() {
+            let mut number_line = String::from("│");
+            let mut letter_line = String::from("│");
+            for x in 0..grid.width() {
+                match grid.is_block(x, y) {
+                    true => {
+                        number_line.push_str(&"█".repeat(SQUARE_WIDTH));
+                        letter_line.push_str(&"█".repeat(SQUARE_WIDTH));
+                    },
+                    false => {
+                        let number = numbers.get(&(x, y)).copied().unwrap_or_default();
+                        number_line.push_str(&paint(&format!("{:<SQUARE_WIDTH$}", printable(number)), DIM, options.colour));
+                        let letter = grid.letter(x, y).map(|c| printable(&square_text(c, grid.style(x, y)))).unwrap_or_default();
+                        letter_line.push_str(&paint(&letter, BOLD, options.colour));
+                    },
+                }
+                let edge = match grid.style(x, y).bar_right {
+                    true => '┃',
+                    false => '│',
+                };
+                number_line.push(edge);
+                letter_line.push(edge);
+            }
+            out.push_str(&number_line);
+            out.push('\n');
+            out.push_str(&letter_line);
+            out.push('\n');
+            match y + 1 == grid.height() {
+                true => out.push_str(&border(Some(y), '└', '┴', '┘')),
+                false => out.push_str(&border(Some(y), '├', '┼', '┤')),
+            }
+        }
+
+        if options.clues {
+            for (direction, heading) in [(Direction::Across, "Across"), (Direction::Down, "Down")] {
+                out.push('\n');
+                out.push_str(&paint(heading, BOLD, options.colour));
+                out.push('\n');
+                for (number, text) in self.printed_clues(direction) {
+                    let label = format!("{:>4}", printable(&number));
+                    out.push_str(&format!("{} {}\n", paint(&label, BOLD, options.colour), printable(&text)));
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TextOptions;
+    use crate::crossword::{Cell, Crossword};
+
+    #[test]
+    fn test_text() {
+        let mut cw = Crossword::demo_grid();
+        cw.update_cell(Cell { x: 0, y: 0, c: 'F'.into() }).unwrap();
+        cw.update_cell(Cell { x: 1, y: 0, c: 'O'.into() }).unwrap();
+        let text = cw.to_text(&TextOptions::default()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "┌───┬───┬───┬───┬───┬───┬───┬───┐");
+        assert_eq!(lines[1], "│1  │   │   │   │2  │   │   │   │");
+        assert_eq!(lines[2], "│ F │ O │   │   │   │   │   │   │");
+        assert_eq!(lines[3], "├───┼───┼───┼───┼───┼───┼───┼───┤");
+        assert_eq!(lines[4], "│   │███│███│███│   │███│███│███│");
+        assert_eq!(lines[24], "└───┴───┴───┴───┴───┴───┴───┴───┘");
+        assert!(text.contains("\nAcross\n   1 For all the money that e'er I had (8)\n   3 I spent it in good company (8)\n"));
+        assert!(!text.contains('\x1b'));
+
+        let text = cw.to_text(&TextOptions { colour: true, clues: false }).unwrap();
+        assert!(text.contains("\x1b[1m F \x1b[0m"));
+        assert!(!text.contains("Across"));
+
+        cw.update_cell(Cell { x: 0, y: 4, c: "NO".parse().unwrap() }).unwrap();
+        cw.update_cell(Cell { x: 4, y: 4, c: "STAR".parse().unwrap() }).unwrap();
+        let text = cw.to_text(&TextOptions::default()).unwrap();
+        assert_eq!(text.lines().nth(14), Some("│NO │   │   │   │ST…│   │   │   │"));
+
+        cw.styles = serde_json::from_str(r#"[{"x":0,"y":0,"circled":true,"bar_bottom":true},{"x":1,"y":0,"shaded":true},{"x":2,"y":0,"bar_right":true}]"#).unwrap();
+        let text = cw.to_text(&TextOptions::default()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[2], "│(F)│░O░│   ┃   │   │   │   │   │");
+        assert_eq!(lines[3], "├━━━┼───┼───┼───┼───┼───┼───┼───┤");
+
+        cw.across.get_mut("1a").unwrap().hint = "Red \x1b[31malert\x07".to_string();
+        let text = cw.to_text(&TextOptions::default()).unwrap();
+        assert!(text.contains("   1 Red  [31malert  (8)\n"));
+        assert!(!text.contains('\x1b'));
+        assert!(!text.contains('\x07'));
+    }
+}
@@ -1,11 +1,19 @@
-use std::{env, fs::{self, File}, io::{Error, ErrorKind, Read, Write}, path::Path};
+use std::{env, fmt::{self, Display}, fs::{self, File}, io::{Error, ErrorKind, Read, Write}, path::Path, str::FromStr};
+use base64::{engine::general_purpose, Engine as _};
+use argon2::{password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString}, Argon2};
 use log::{error, info, trace, warn};
 use lazy_static::lazy_static;
-use rusqlite::{named_params, Connection};
-use serde::Serialize;
+use rand::rngs::OsRng;
+use rusqlite::{named_params, types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef}, Connection, ToSql};
+use serde::{Deserialize, Serialize};
 use sha256::digest;
+use subtle::ConstantTimeEq;
 
-use crate::crossword::Crossword;
+use crate::crossword::{CellValue, Crossword, PuzzleMetadata, Solution};
+
+/// How long a session lasts without being used. Every authenticated request
+/// pushes the expiry back by this much.
+pub const SESSION_LIFETIME_SECONDS: i64 = 60 * 60 * 24 * 14;
 
 lazy_static! {
     static ref PUZZLE_DIR_PATH: String = env::var("PUZZLE_PATH").unwrap_or("./puzzles".to_string());
@@ -17,60 +25,355 @@ lazy_static! {
 
 #[derive(Debug,Serialize)]
 pub struct PuzzleDbData {
-    id: usize,
+    pub id: usize,
     pub name: String,
     file: String,
-    deleted: usize
+    deleted: usize,
+    pub owner_id: Option<i64>,
+    pub visibility: Visibility,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub metadata: PuzzleMetadata,
+}
+
+/// Who can find and open a puzzle. Unlisted puzzles can be opened by anyone
+/// with the link, but only appear in their owner's listings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    #[default]
+    Public,
+    Unlisted,
+    Private,
+}
+
+impl FromStr for Visibility {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "public" => Ok(Self::Public),
+            "unlisted" => Ok(Self::Unlisted),
+            "private" => Ok(Self::Private),
+            s => Err(format!("{s} is not a valid visibility")),
+        }
+    }
+}
+
+impl Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Visibility::Public => write!(f, "public"),
+            Visibility::Unlisted => write!(f, "unlisted"),
+            Visibility::Private => write!(f, "private"),
+        }
+    }
+}
+
+impl ToSql for Visibility {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for Visibility {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Visibility::from_str(value.as_str()?).map_err(|e| FromSqlError::Other(e.into()))
+    }
+}
+
+/// Metadata is stored as JSON. Puzzles saved before it existed have none.
+impl ToSql for PuzzleMetadata {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let json = serde_json::to_string(self).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+        Ok(ToSqlOutput::from(json))
+    }
+}
+
+impl FromSql for PuzzleMetadata {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Null => Ok(PuzzleMetadata::default()),
+            value => serde_json::from_str(value.as_str()?).map_err(|e| FromSqlError::Other(e.into())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+    pub is_admin: bool,
+}
+
+impl User {
+    fn from_row(row: &rusqlite::Row<'_>) -> Result<User, rusqlite::Error> {
+        Ok(User { id: row.get(0)?, username: row.get(1)?, is_admin: row.get(2)? })
+    }
 }
 
 impl PuzzleDbData {
+    /// Private puzzles can only be seen by their owner and admins.
+    pub fn can_view(&self, user: Option<&User>) -> bool {
+        match self.visibility {
+            Visibility::Public | Visibility::Unlisted => true,
+            Visibility::Private => user.is_some_and(|user| self.can_edit(user)),
+        }
+    }
 
-    fn from_row(row: &rusqlite::Row<'_>) ->Result<PuzzleDbData, rusqlite::Error> {
-        let id = match row.get(0){
-            Ok(val) => val,
-            Err(rusqlite::Error::InvalidColumnIndex(idx)) => {
-                error!("While trying to parse a row from the database into a PuzzleDbData struct, we attempted to find 'ID' at Column index {0}, but {0} is an invalid Column Index", idx);
-                return Err(rusqlite::Error::InvalidColumnIndex(idx))
-            },
-            Err(err) => {
-                error!("{0}",err);
-                return Err(err)
-            }
-        };
-        let name = match row.get(1){
-            Ok(val) => val,
-            Err(rusqlite::Error::InvalidColumnIndex(idx)) => {
-                error!("While trying to parse a row from the database into a PuzzleDbData struct, we attempted to find 'name' at Column index {0}, but {0} is an invalid Column Index", idx);
-                return Err(rusqlite::Error::InvalidColumnIndex(idx))
-            },
-            Err(err) => {
-                error!("{0}",err);
-                return Err(err)
-            }
-        };
-        let file = match row.get(2){
-            Ok(val) => val,
-            Err(rusqlite::Error::InvalidColumnIndex(idx)) => {
-                error!("While trying to parse a row from the database into a PuzzleDbData struct, we attempted to find 'file' at Column index {0}, but {0} is an invalid Column Index", idx);
-                return Err(rusqlite::Error::InvalidColumnIndex(idx))
-            },
-            Err(err) => {
-                error!("{0}",err);
-                return Err(err)
-            }
+    pub fn can_edit(&self, user: &User) -> bool {
+        user.is_admin || self.owner_id == Some(user.id)
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted != 0
+    }
+}
+
+/// What a personal API token may be used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenScope {
+    Read,
+    Upload,
+    Delete,
+}
+
+impl FromStr for TokenScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "read" => Ok(Self::Read),
+            "upload" => Ok(Self::Upload),
+            "delete" => Ok(Self::Delete),
+            s => Err(format!("{s} is not a valid token scope")),
+        }
+    }
+}
+
+impl Display for TokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenScope::Read => write!(f, "read"),
+            TokenScope::Upload => write!(f, "upload"),
+            TokenScope::Delete => write!(f, "delete"),
+        }
+    }
+}
+
+/// The column puzzle listings are ordered by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PuzzleSort {
+    Name,
+    #[default]
+    Created,
+    Updated,
+}
+
+impl FromStr for PuzzleSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(Self::Name),
+            "created" => Ok(Self::Created),
+            "updated" => Ok(Self::Updated),
+            s => Err(format!("{s} is not a valid sort, expected name, created or updated")),
+        }
+    }
+}
+
+impl Display for PuzzleSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PuzzleSort::Name => write!(f, "name"),
+            PuzzleSort::Created => write!(f, "created"),
+            PuzzleSort::Updated => write!(f, "updated"),
+        }
+    }
+}
+
+impl PuzzleSort {
+    fn column(&self) -> &'static str {
+        match self {
+            PuzzleSort::Name => "name collate nocase",
+            PuzzleSort::Created => "created_at",
+            PuzzleSort::Updated => "updated_at",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "asc" => Ok(Self::Asc),
+            "desc" => Ok(Self::Desc),
+            s => Err(format!("{s} is not a valid order, expected asc or desc")),
+        }
+    }
+}
+
+impl Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortOrder::Asc => write!(f, "asc"),
+            SortOrder::Desc => write!(f, "desc"),
+        }
+    }
+}
+
+/// A search over the puzzles someone can browse. Pages start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PuzzleQuery {
+    pub q: Option<String>,
+    /// Only puzzles with this tag.
+    pub tag: Option<String>,
+    /// Only puzzles in the collection with this ID.
+    pub collection: Option<i64>,
+    pub sort: PuzzleSort,
+    pub order: SortOrder,
+    pub page: usize,
+    pub per_page: usize,
+}
+
+impl PuzzleQuery {
+    pub const DEFAULT_PER_PAGE: usize = 20;
+    pub const MAX_PER_PAGE: usize = 100;
+}
+
+impl Default for PuzzleQuery {
+    fn default() -> Self {
+        PuzzleQuery { q: None, tag: None, collection: None, sort: PuzzleSort::default(), order: SortOrder::default(), page: 1, per_page: Self::DEFAULT_PER_PAGE }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagCount {
+    pub name: String,
+    /// How many public puzzles have the tag. Tags only used on private,
+    /// unlisted or deleted puzzles aren't listed.
+    pub puzzles: usize,
+}
+
+/// A named group of puzzles put together by a user.
+#[derive(Debug, Serialize)]
+pub struct Collection {
+    pub id: i64,
+    pub owner_id: i64,
+    pub name: String,
+    pub created_at: i64,
+    pub puzzle_ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PuzzlePage {
+    pub puzzles: Vec<PuzzleDbData>,
+    /// How many puzzles match the search across every page.
+    pub total: usize,
+    pub page: usize,
+    pub per_page: usize,
+}
+
+/// A clue whose hint matched a search. Matching words in `snippet` are
+/// wrapped in `<mark>` and the rest of the hint is HTML escaped.
+#[derive(Debug, Serialize)]
+pub struct ClueMatch {
+    pub puzzle_id: i64,
+    pub puzzle_name: String,
+    pub clue_id: String,
+    pub snippet: String,
+}
+
+/// How much help solvers have had with a puzzle.
+#[derive(Debug, Serialize)]
+pub struct PuzzleStats {
+    pub has_solution: bool,
+    pub reveals: usize,
+    pub revealed_squares: usize,
+    /// Signed in solvers who have revealed at least one square.
+    pub assisted_solvers: usize,
+    pub last_reveal_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    /// `None` means the token is not restricted to any scope.
+    pub scopes: Option<Vec<TokenScope>>,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    pub revoked: bool,
+}
+
+impl ApiToken {
+    pub fn allows(&self, scope: TokenScope) -> bool {
+        match &self.scopes {
+            Some(scopes) => scopes.contains(&scope),
+            None => true,
+        }
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> Result<ApiToken, rusqlite::Error> {
+        let scopes: Option<String> = row.get(3)?;
+        let scopes = match scopes {
+            Some(scopes) => Some(scopes
+                .split(',')
+                .filter(|x| !x.is_empty())
+                .map(|x| TokenScope::from_str(x).map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, e.into())))
+                .try_collect()?),
+            None => None,
         };
-        let deleted = match row.get(3){
-            Ok(val) => val,
+        Ok(ApiToken {
+            id: row.get(0)?,
+            user_id: row.get(1)?,
+            name: row.get(2)?,
+            scopes,
+            created_at: row.get(4)?,
+            last_used_at: row.get(5)?,
+            revoked: row.get(6)?,
+        })
+    }
+}
+
+impl PuzzleDbData {
+
+    fn from_row(row: &rusqlite::Row<'_>) ->Result<PuzzleDbData, rusqlite::Error> {
+        let id = Self::get_column(row, 0, "ID")?;
+        let name = Self::get_column(row, 1, "name")?;
+        let file = Self::get_column(row, 2, "file")?;
+        let deleted = Self::get_column(row, 3, "deleted")?;
+        let owner_id = Self::get_column(row, 4, "owner_id")?;
+        let visibility = Self::get_column(row, 5, "visibility")?;
+        let created_at = Self::get_column(row, 6, "created_at")?;
+        let updated_at = Self::get_column(row, 7, "updated_at")?;
+        let metadata = Self::get_column(row, 8, "metadata")?;
+        Ok(PuzzleDbData {id, name, file, deleted, owner_id, visibility, created_at, updated_at, metadata})
+    }
+
+    fn get_column<T: FromSql>(row: &rusqlite::Row<'_>, idx: usize, column: &str) -> Result<T, rusqlite::Error> {
+        match row.get(idx){
+            Ok(val) => Ok(val),
             Err(rusqlite::Error::InvalidColumnIndex(idx)) => {
-                error!("While trying to parse a row from the database into a PuzzleDbData struct, we attempted to find 'deleted' at Column index {0}, but {0} is an invalid Column Index", idx);
-                return Err(rusqlite::Error::InvalidColumnIndex(idx))
+                error!("While trying to parse a row from the database into a PuzzleDbData struct, we attempted to find '{column}' at Column index {0}, but {0} is an invalid Column Index", idx);
+                Err(rusqlite::Error::InvalidColumnIndex(idx))
             },
             Err(err) => {
                 error!("{0}",err);
-                return Err(err)
+                Err(err)
             }
-        };
-        Ok(PuzzleDbData {id, name, file, deleted})
+        }
     }
 }
 
@@ -94,9 +397,23 @@ pub fn init_db() -> Result<(), rusqlite::Error> {
             _ => return Err(e)
         }
     }
+    init_db_v2(&mut conn)?;
+    init_db_v3(&mut conn)?;
+    init_db_v4(&mut conn)?;
+    init_db_v5(&mut conn)?;
+    init_db_v6(&mut conn)?;
+    init_db_v7(&mut conn)?;
+    init_db_v8(&mut conn)?;
+    init_db_v9(&mut conn)?;
     Ok(())
 }
 
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool, rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!("pragma table_info({table})"))?;
+    let columns: Vec<String> = stmt.query_map([], |row| row.get(1))?.try_collect()?;
+    Ok(columns.iter().any(|x| x == column))
+}
+
 fn init_db_v0(conn: &mut Connection) -> Result<(), rusqlite::Error> {
     conn.execute(
         "create table if not exists puzzles (
@@ -126,6 +443,198 @@ fn init_db_v1(conn: &mut Connection) -> Result<(), rusqlite::Error> {
 
 }
 
+fn init_db_v2(conn: &mut Connection) -> Result<(), rusqlite::Error> {
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "create table if not exists sessions (
+             id integer primary key,
+             token_hash text not null unique,
+             user_id integer not null references users(id) on delete cascade,
+             created_at integer not null,
+             expires_at integer not null,
+             last_seen_at integer not null,
+             user_agent text
+         )",()
+    )?;
+    tx.execute("create index if not exists sessions_user_id on sessions (user_id)", [])?;
+    info!("Commiting db v2 transaction");
+    tx.commit()
+
+}
+
+fn init_db_v3(conn: &mut Connection) -> Result<(), rusqlite::Error> {
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "create table if not exists api_tokens (
+             id integer primary key,
+             user_id integer not null references users(id) on delete cascade,
+             name text not null,
+             token_hash text not null unique,
+             scopes text,
+             created_at integer not null,
+             last_used_at integer,
+             revoked integer default 0 not null
+         )",()
+    )?;
+    info!("Commiting db v3 transaction");
+    tx.commit()
+
+}
+
+fn init_db_v4(conn: &mut Connection) -> Result<(), rusqlite::Error> {
+
+    let tx = conn.transaction()?;
+    if !has_column(&tx, "users", "is_admin")? {
+        tx.execute("ALTER TABLE users ADD is_admin INTEGER DEFAULT 0 NOT NULL", [])?;
+    }
+    if !has_column(&tx, "puzzles", "owner_id")? {
+        tx.execute("ALTER TABLE puzzles ADD owner_id INTEGER REFERENCES users(id)", [])?;
+    }
+    if !has_column(&tx, "puzzles", "visibility")? {
+        tx.execute("ALTER TABLE puzzles ADD visibility TEXT DEFAULT 'public' NOT NULL", [])?;
+    }
+
+    // Puzzles uploaded before ownership existed go to the first admin. No one
+    // is made an admin here; until `prune set-admin` makes one they're left
+    // without an owner.
+    adopt_orphans(&tx)?;
+    let orphans: i64 = tx.query_row("select count(*) from puzzles where owner_id is null", [], |row| row.get(0))?;
+    if orphans > 0 {
+        warn!("{orphans} puzzles have no owner and can only be edited by an admin");
+    }
+    info!("Commiting db v4 transaction");
+    tx.commit()
+
+}
+
+/// Gives puzzles without an owner to the admin with the lowest id, if there
+/// is one.
+fn adopt_orphans(conn: &Connection) -> Result<usize, rusqlite::Error> {
+    conn.execute(
+        "update puzzles set owner_id=(select min(id) from users where is_admin != 0)
+         where owner_id is null and exists (select 1 from users where is_admin != 0)",
+        []
+    )
+}
+
+fn init_db_v5(conn: &mut Connection) -> Result<(), rusqlite::Error> {
+
+    let tx = conn.transaction()?;
+    // sqlite won't add a column with a non-constant default, so puzzles that
+    // predate these columns are stamped with the time of the migration.
+    let now = chrono::Utc::now().timestamp();
+    if !has_column(&tx, "puzzles", "created_at")? {
+        tx.execute("ALTER TABLE puzzles ADD created_at INTEGER DEFAULT 0 NOT NULL", [])?;
+        tx.execute("update puzzles set created_at=(:now)", named_params! { ":now": now })?;
+    }
+    if !has_column(&tx, "puzzles", "updated_at")? {
+        tx.execute("ALTER TABLE puzzles ADD updated_at INTEGER DEFAULT 0 NOT NULL", [])?;
+        tx.execute("update puzzles set updated_at=(:now)", named_params! { ":now": now })?;
+    }
+    tx.execute("create index if not exists puzzles_created_at on puzzles (created_at)", [])?;
+    tx.execute("create index if not exists puzzles_updated_at on puzzles (updated_at)", [])?;
+    info!("Commiting db v5 transaction");
+    tx.commit()
+
+}
+
+fn init_db_v6(conn: &mut Connection) -> Result<(), rusqlite::Error> {
+
+    let tx = conn.transaction()?;
+    // puzzles saved before this table existed are only searchable after `prune reindex`.
+    tx.execute(
+        "create virtual table if not exists clue_search using fts5 (
+             hint,
+             puzzle_id unindexed,
+             clue_id unindexed
+         )",()
+    )?;
+    info!("Commiting db v6 transaction");
+    tx.commit()
+
+}
+
+fn init_db_v7(conn: &mut Connection) -> Result<(), rusqlite::Error> {
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "create table if not exists tags (
+             id integer primary key,
+             name text not null unique collate nocase
+         )",()
+    )?;
+    tx.execute(
+        "create table if not exists puzzle_tags (
+             puzzle_id integer not null references puzzles(id) on delete cascade,
+             tag_id integer not null references tags(id) on delete cascade,
+             primary key (puzzle_id, tag_id)
+         )",()
+    )?;
+    tx.execute(
+        "create table if not exists collections (
+             id integer primary key,
+             owner_id integer not null references users(id) on delete cascade,
+             name text not null,
+             created_at integer not null,
+             unique (owner_id, name)
+         )",()
+    )?;
+    tx.execute(
+        "create table if not exists collection_puzzles (
+             collection_id integer not null references collections(id) on delete cascade,
+             puzzle_id integer not null references puzzles(id) on delete cascade,
+             added_at integer not null,
+             primary key (collection_id, puzzle_id)
+         )",()
+    )?;
+    tx.execute("create index if not exists puzzle_tags_tag_id on puzzle_tags (tag_id)", [])?;
+    tx.execute("create index if not exists collection_puzzles_puzzle_id on collection_puzzles (puzzle_id)", [])?;
+    info!("Commiting db v7 transaction");
+    tx.commit()
+
+}
+
+fn init_db_v8(conn: &mut Connection) -> Result<(), rusqlite::Error> {
+
+    let tx = conn.transaction()?;
+    if !has_column(&tx, "puzzles", "metadata")? {
+        tx.execute("ALTER TABLE puzzles ADD metadata TEXT", [])?;
+    }
+    info!("Commiting db v8 transaction");
+    tx.commit()
+
+}
+
+fn init_db_v9(conn: &mut Connection) -> Result<(), rusqlite::Error> {
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "create table if not exists solutions (
+             puzzle_id integer not null references puzzles(id) on delete cascade,
+             x integer not null,
+             y integer not null,
+             letter text not null,
+             primary key (puzzle_id, x, y)
+         )",()
+    )?;
+    tx.execute(
+        "create table if not exists reveals (
+             id integer primary key,
+             puzzle_id integer not null references puzzles(id) on delete cascade,
+             user_id integer references users(id) on delete set null,
+             target text not null,
+             squares integer not null,
+             created_at integer not null
+         )",()
+    )?;
+    tx.execute("create index if not exists reveals_puzzle_id on reveals (puzzle_id)", [])?;
+    info!("Commiting db v9 transaction");
+    tx.commit()
+
+}
+
 fn get_next_id()-> Result<i64, rusqlite::Error> {
     let conn = Connection::open(&*PUZZLE_DB_PATH)?;
 
@@ -141,14 +650,29 @@ fn get_next_id()-> Result<i64, rusqlite::Error> {
     return res
 }
 
-pub fn add_puzzle_to_db(name: &str, file: &str) -> Result<(), rusqlite::Error> {
+pub fn add_puzzle_to_db(name: &str, file: &str, owner_id: i64, visibility: Visibility, metadata: &PuzzleMetadata) -> Result<(), rusqlite::Error> {
     info!("inserting puzzle data");
     let conn = Connection::open(&*PUZZLE_DB_PATH)?;
 
     let mut stmt = conn.prepare(
-        "insert into puzzles (name, file) values (:name, :file)"
+        "insert into puzzles (name, file, owner_id, visibility, created_at, updated_at, metadata)
+         values (:name, :file, :owner_id, :visibility, :now, :now, :metadata)"
     )?;
-    stmt.execute(named_params! { ":name": name, ":file": file})?;
+    let now = chrono::Utc::now().timestamp();
+    stmt.execute(named_params! { ":name": name, ":file": file, ":owner_id": owner_id, ":visibility": visibility, ":now": now, ":metadata": metadata})?;
+
+    Ok(())
+}
+
+pub fn update_puzzle_details(puzzle_id: i64, name: Option<&str>, visibility: Option<Visibility>) -> Result<(), rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+
+    let mut stmt = conn.prepare(
+        "update puzzles set name=coalesce(:name, name), visibility=coalesce(:visibility, visibility), updated_at=(:now) where id=(:id)"
+    )?;
+
+    let now = chrono::Utc::now().timestamp();
+    stmt.execute(named_params! { ":name": name, ":visibility": visibility, ":now": now, ":id": puzzle_id})?;
 
     Ok(())
 }
@@ -169,7 +693,7 @@ pub fn add_user(username: &str, password: &str) -> Result<i64, rusqlite::Error>
     info!("inserting data");
     let conn = Connection::open(&*PUZZLE_DB_PATH)?;
 
-    let hash = digest(password);
+    let hash = hash_password(password)?;
 
     let mut stmt = conn.prepare(
         "insert into users (username, password) values (:username, :password)"
@@ -199,53 +723,291 @@ pub fn get_user_password(username: &str) -> Result<SignIn, rusqlite::Error> {
     rows
 }
 
-pub fn set_session(user_id: i64) -> Result<i64, rusqlite::Error> {
+/// Starts a new session for a user and returns the opaque token that
+/// identifies it. Only a digest of the token is kept in the database.
+pub fn create_session(user_id: i64, user_agent: Option<&str>) -> Result<String, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+
+    let token = general_purpose::URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>());
+    let now = chrono::Utc::now().timestamp();
+
+    conn.execute("delete from sessions where expires_at <= (:now)", named_params! { ":now": now })?;
+
+    let mut stmt = conn.prepare(
+        "insert into sessions (token_hash, user_id, created_at, expires_at, last_seen_at, user_agent)
+         values (:token_hash, :user_id, :now, :expires_at, :now, :user_agent)"
+    )?;
+
+    stmt.execute(named_params! {
+        ":token_hash": digest(token.as_str()),
+        ":user_id": user_id,
+        ":now": now,
+        ":expires_at": now + SESSION_LIFETIME_SECONDS,
+        ":user_agent": user_agent
+    })?;
+
+    Ok(token)
+}
+
+/// Looks up the user a session token belongs to. Successful checks slide the
+/// session's expiry forward. Unknown or expired tokens give `QueryReturnedNoRows`.
+pub fn check_session(token: &str) -> Result<User, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+
+    let token_hash = digest(token);
+    let now = chrono::Utc::now().timestamp();
+
+    let mut stmt = conn.prepare(
+        "select users.id, users.username, users.is_admin from sessions
+         join users on users.id = sessions.user_id
+         where sessions.token_hash=(:token_hash) and sessions.expires_at > (:now)"
+    )?;
+
+    let user = stmt.query_row(named_params! { ":token_hash": token_hash, ":now": now }, User::from_row)?;
+
+    conn.execute(
+        "update sessions set last_seen_at=(:now), expires_at=(:expires_at) where token_hash=(:token_hash)",
+        named_params! { ":now": now, ":expires_at": now + SESSION_LIFETIME_SECONDS, ":token_hash": token_hash }
+    )?;
+
+    Ok(user)
+}
+
+pub fn delete_session(token: &str) -> Result<(), rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    conn.execute("delete from sessions where token_hash=(:token_hash)", named_params! { ":token_hash": digest(token) })?;
+    Ok(())
+}
+
+/// Ends every session the user has, on every device. Returns how many there were.
+pub fn delete_user_sessions(user_id: i64) -> Result<usize, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    conn.execute("delete from sessions where user_id=(:user_id)", named_params! { ":user_id": user_id })
+}
+
+pub fn get_user(username: &str) -> Result<User, rusqlite::Error> {
     let conn = Connection::open(&*PUZZLE_DB_PATH)?;
 
-    let session: i64 = rand::random();
+    let mut stmt = conn.prepare(
+        "select id, username, is_admin from users where username=(:username)"
+    )?;
+
+    stmt.query_row(named_params! { ":username": username }, User::from_row)
+}
+
+/// Grants or takes away admin rights. Granting them also hands puzzles
+/// without an owner, left from before puzzles had owners, to the first admin.
+pub fn set_admin(user_id: i64, is_admin: bool) -> Result<(), rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    conn.execute(
+        "update users set is_admin=(:is_admin) where id=(:user_id)",
+        named_params! { ":is_admin": is_admin, ":user_id": user_id }
+    )?;
+    if is_admin {
+        adopt_orphans(&conn)?;
+    }
+    Ok(())
+}
+
+/// Creates a personal API token. The plain token is returned once and only a
+/// digest of it is stored, so it cannot be shown again.
+pub fn create_api_token(user_id: i64, name: &str, scopes: Option<&[TokenScope]>) -> Result<(ApiToken, String), rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+
+    let token = format!("cwt_{}", general_purpose::URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>()));
+    let scopes = scopes.map(|scopes| scopes.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(","));
+
+    let mut stmt = conn.prepare(
+        "insert into api_tokens (user_id, name, token_hash, scopes, created_at)
+         values (:user_id, :name, :token_hash, :scopes, :created_at)"
+    )?;
+
+    stmt.execute(named_params! {
+        ":user_id": user_id,
+        ":name": name,
+        ":token_hash": digest(token.as_str()),
+        ":scopes": scopes,
+        ":created_at": chrono::Utc::now().timestamp()
+    })?;
+
+    let id = conn.last_insert_rowid();
+    let api_token = conn.query_row(
+        "select id, user_id, name, scopes, created_at, last_used_at, revoked from api_tokens where id=(:id)",
+        named_params! { ":id": id },
+        ApiToken::from_row
+    )?;
+
+    Ok((api_token, token))
+}
+
+pub fn get_api_tokens(user_id: i64) -> Result<Vec<ApiToken>, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
 
     let mut stmt = conn.prepare(
-        "update users set session=(:session) where id=(:user_id)"
+        "select id, user_id, name, scopes, created_at, last_used_at, revoked from api_tokens where user_id=(:user_id)"
     )?;
 
-    stmt.execute(named_params! { ":session": session, ":user_id": user_id})?;
+    let rows: Result<Vec<ApiToken>, rusqlite::Error> = stmt.query_map(named_params! { ":user_id": user_id }, ApiToken::from_row)?
+        .collect();
+    rows
+}
+
+pub fn get_api_token(id: i64) -> Result<ApiToken, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    conn.query_row(
+        "select id, user_id, name, scopes, created_at, last_used_at, revoked from api_tokens where id=(:id)",
+        named_params! { ":id": id },
+        ApiToken::from_row
+    )
+}
 
-    Ok(session)
+pub fn revoke_api_token(id: i64) -> Result<(), rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    conn.execute("update api_tokens set revoked=1 where id=(:id)", named_params! { ":id": id })?;
+    Ok(())
 }
 
-pub fn check_session(id: i64, session: i64) -> Result<bool, rusqlite::Error> {
+/// Looks up the user a bearer token belongs to. Unknown and revoked tokens
+/// give `QueryReturnedNoRows`.
+pub fn check_api_token(token: &str) -> Result<(User, ApiToken), rusqlite::Error> {
     let conn = Connection::open(&*PUZZLE_DB_PATH)?;
 
+    let token_hash = digest(token);
+
     let mut stmt = conn.prepare(
-        "select session from users where id=(:user_id)"
+        "select api_tokens.id, api_tokens.user_id, api_tokens.name, api_tokens.scopes, api_tokens.created_at,
+                api_tokens.last_used_at, api_tokens.revoked, users.username, users.is_admin
+         from api_tokens join users on users.id = api_tokens.user_id
+         where api_tokens.token_hash=(:token_hash) and api_tokens.revoked=0"
     )?;
 
-    let session_db: i64 = stmt.query_row(&[(":user_id", &id.to_string())], |row| {
-        row.get(0)
+    let (user, api_token) = stmt.query_row(named_params! { ":token_hash": token_hash }, |row| {
+        let api_token = ApiToken::from_row(row)?;
+        let user = User { id: api_token.user_id, username: row.get(7)?, is_admin: row.get(8)? };
+        Ok((user, api_token))
     })?;
 
-    Ok(session_db == session)
+    conn.execute(
+        "update api_tokens set last_used_at=(:now) where id=(:id)",
+        named_params! { ":now": chrono::Utc::now().timestamp(), ":id": api_token.id }
+    )?;
+
+    Ok((user, api_token))
 }
 
+/// Hashes a password with Argon2id and a random salt. The result is a PHC
+/// string, so the algorithm, parameters and salt are stored alongside the hash.
+pub fn hash_password(password: &str) -> Result<String, rusqlite::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    match Argon2::default().hash_password(password.as_bytes(), &salt) {
+        Ok(hash) => Ok(hash.to_string()),
+        Err(e) => {
+            error!("Failed to hash password: {e}");
+            Err(rusqlite::Error::ToSqlConversionFailure(Box::new(Error::new(ErrorKind::Other, e.to_string()))))
+        }
+    }
+}
+
+/// Checks a plain text password against a stored hash. Both PHC strings and
+/// the unsalted SHA-256 digests written by older versions are accepted.
 pub fn validate_password(plain: &str, hashed: &str) -> Result<(), () > {
-    let hash = digest(plain);
-    if hashed == hash {Ok(())} else { Err(()) }
+    if needs_rehash(hashed) {
+        let hash = digest(plain);
+        return if bool::from(hash.as_bytes().ct_eq(hashed.as_bytes())) { Ok(()) } else { Err(()) }
+    }
+
+    let parsed = match PasswordHash::new(hashed) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            error!("Stored password hash is not a valid PHC string: {e}");
+            return Err(())
+        }
+    };
+    Argon2::default().verify_password(plain.as_bytes(), &parsed).map_err(|_| ())
 }
 
+/// Legacy hashes are bare SHA-256 hex digests rather than PHC strings.
+pub fn needs_rehash(hashed: &str) -> bool {
+    !hashed.starts_with('$')
+}
 
-pub fn get_all_puzzle_db() -> Result<Vec<PuzzleDbData>, rusqlite::Error> {
+pub fn update_password(user_id: i64, password: &str) -> Result<(), rusqlite::Error> {
     let conn = Connection::open(&*PUZZLE_DB_PATH)?;
 
+    let hash = hash_password(password)?;
+
     let mut stmt = conn.prepare(
-        "select id, name, file, deleted from puzzles where deleted=0"
+        "update users set password=(:password) where id=(:user_id)"
+    )?;
+
+    stmt.execute(named_params! { ":password": hash, ":user_id": user_id})?;
+
+    Ok(())
+}
+
+
+/// Which puzzles someone can browse: every public puzzle, plus any unlisted or
+/// private puzzles they own. `q` matches anywhere in the name.
+const LISTING_FILTER: &str = "where deleted=0 and (visibility='public' or owner_id=(:viewer))
+     and (:pattern is null or name like (:pattern) escape '\\')
+     and (:tag is null or id in (
+         select puzzle_tags.puzzle_id from puzzle_tags join tags on tags.id = puzzle_tags.tag_id where tags.name=(:tag)))
+     and (:collection is null or id in (
+         select puzzle_id from collection_puzzles where collection_id=(:collection)))";
+
+/// Lists a page of the puzzles someone can browse.
+pub fn list_puzzles(viewer: Option<&User>, query: &PuzzleQuery) -> Result<PuzzlePage, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+
+    let total: usize = conn.query_row(
+        &format!("select count(*) from puzzles {LISTING_FILTER}"),
+        named_params! {
+            ":viewer": viewer.map(|x| x.id),
+            ":pattern": query.q.as_deref().map(like_pattern),
+            ":tag": query.tag,
+            ":collection": query.collection
+        },
+        |row| row.get(0)
     )?;
-    
-    let rows: Result<Vec<PuzzleDbData>, rusqlite::Error> = stmt.query_map([], |row| {
-        PuzzleDbData::from_row(row)
-    })?
-    .collect();
 
-   rows
+    let per_page = query.per_page.clamp(1, PuzzleQuery::MAX_PER_PAGE);
+    let page = query.page.max(1);
+    let puzzles = select_puzzles(&conn, viewer, query, per_page as i64, (page - 1) * per_page)?;
+
+    Ok(PuzzlePage { puzzles, total, page, per_page })
+}
+
+/// Lists every puzzle someone can browse that matches the search, without paging.
+pub fn list_all_puzzles(viewer: Option<&User>, query: &PuzzleQuery) -> Result<Vec<PuzzleDbData>, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    // sqlite reads a negative limit as no limit.
+    select_puzzles(&conn, viewer, query, -1, 0)
+}
+
+fn select_puzzles(conn: &Connection, viewer: Option<&User>, query: &PuzzleQuery, limit: i64, offset: usize) -> Result<Vec<PuzzleDbData>, rusqlite::Error> {
+    // the sort column and order come from enums, never from the request.
+    let order = query.order;
+    let mut stmt = conn.prepare(&format!(
+        "select id, name, file, deleted, owner_id, visibility, created_at, updated_at, metadata from puzzles {LISTING_FILTER}
+         order by {} {order}, id {order} limit (:limit) offset (:offset)", query.sort.column()
+    ))?;
+
+    let puzzles: Result<Vec<PuzzleDbData>, rusqlite::Error> = stmt.query_map(named_params! {
+        ":viewer": viewer.map(|x| x.id),
+        ":pattern": query.q.as_deref().map(like_pattern),
+        ":tag": query.tag,
+        ":collection": query.collection,
+        ":limit": limit,
+        ":offset": offset,
+    }, PuzzleDbData::from_row)?
+    .try_collect();
+    puzzles
+}
+
+/// Escapes the wildcards in a search so it is matched literally by `like`.
+fn like_pattern(q: &str) -> String {
+    let escaped = q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{escaped}%")
 }
 
 pub fn get_puzzle_db(id: &i64) -> Result<PuzzleDbData, rusqlite::Error> {
@@ -254,7 +1016,7 @@ pub fn get_puzzle_db(id: &i64) -> Result<PuzzleDbData, rusqlite::Error> {
     let conn = Connection::open(&*PUZZLE_DB_PATH)?;
 
     let mut stmt = conn.prepare(
-        "select id, name, file, deleted from puzzles where id=:id"
+        "select id, name, file, deleted, owner_id, visibility, created_at, updated_at, metadata from puzzles where id=:id"
     )?;
     
     let rows = stmt.query_row(&[(":id", id)], |row| {
@@ -270,7 +1032,7 @@ pub fn get_soft_delete_puzzles() -> Result<Vec<PuzzleDbData>, rusqlite::Error> {
     let conn = Connection::open(&*PUZZLE_DB_PATH)?;
 
     let mut stmt = conn.prepare(
-        "select id, name, file, deleted from puzzles where deleted != 0"
+        "select id, name, file, deleted, owner_id, visibility, created_at, updated_at, metadata from puzzles where deleted != 0"
     )?;
     
     let rows: Result<Vec<PuzzleDbData>, rusqlite::Error> = stmt.query_map([], |row| {
@@ -299,8 +1061,11 @@ pub fn batch_delete() -> anyhow::Result<()> {
         fs::remove_file(&data.file)
     })?;
 
-    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
-    conn.execute("DELETE FROM puzzles WHERE deleted=1",[])?;
+    let mut conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let tx = conn.transaction()?;
+    puzzles.iter().try_for_each(|data| remove_puzzle_references(&tx, &(data.id as i64)))?;
+    tx.execute("DELETE FROM puzzles WHERE deleted=1",[])?;
+    tx.commit()?;
 
     Ok(())
 }
@@ -308,10 +1073,189 @@ pub fn batch_delete() -> anyhow::Result<()> {
 pub fn delete_puzzle(id: &i64) -> anyhow::Result<()> {
     let data = get_puzzle_db(id)?;
     fs::remove_file(&data.file)?;
+    let mut conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let tx = conn.transaction()?;
+    remove_puzzle_references(&tx, id)?;
+    tx.execute("DELETE FROM puzzles WHERE id=:id",&[(":id", id)])?;
+    tx.commit()?;
+    Ok(())
+
+}
+
+/// Foreign keys aren't enforced, so rows that point at a purged puzzle have
+/// to be removed by hand.
+fn remove_puzzle_references(conn: &Connection, id: &i64) -> Result<(), rusqlite::Error> {
+    conn.execute("delete from clue_search where puzzle_id=(:id)", named_params! { ":id": id })?;
+    conn.execute("delete from puzzle_tags where puzzle_id=(:id)", named_params! { ":id": id })?;
+    conn.execute("delete from collection_puzzles where puzzle_id=(:id)", named_params! { ":id": id })?;
+    conn.execute("delete from solutions where puzzle_id=(:id)", named_params! { ":id": id })?;
+    conn.execute("delete from reveals where puzzle_id=(:id)", named_params! { ":id": id })?;
+    Ok(())
+}
+
+/// The longest tag or collection name that will be stored.
+pub const MAX_LABEL_LEN: usize = 64;
+
+pub fn get_puzzle_tags(puzzle_id: &i64) -> Result<Vec<String>, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let mut stmt = conn.prepare(
+        "select tags.name from tags join puzzle_tags on tags.id = puzzle_tags.tag_id
+         where puzzle_tags.puzzle_id=(:id) order by tags.name collate nocase"
+    )?;
+    let tags: Result<Vec<String>, rusqlite::Error> = stmt.query_map(named_params! { ":id": puzzle_id }, |row| row.get(0))?.try_collect();
+    tags
+}
+
+/// Tags a puzzle, creating the tag if nobody has used it before. Tags are
+/// matched ignoring case, so the first spelling used is kept.
+pub fn tag_puzzle(puzzle_id: &i64, tag: &str) -> Result<(), rusqlite::Error> {
+    let mut conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let tx = conn.transaction()?;
+    tx.execute("insert or ignore into tags (name) values (:name)", named_params! { ":name": tag })?;
+    tx.execute(
+        "insert or ignore into puzzle_tags (puzzle_id, tag_id) select (:id), id from tags where name=(:name)",
+        named_params! { ":id": puzzle_id, ":name": tag }
+    )?;
+    tx.commit()
+}
+
+/// Returns whether the puzzle had the tag.
+pub fn untag_puzzle(puzzle_id: &i64, tag: &str) -> Result<bool, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let removed = conn.execute(
+        "delete from puzzle_tags where puzzle_id=(:id) and tag_id in (select id from tags where name=(:name))",
+        named_params! { ":id": puzzle_id, ":name": tag }
+    )?;
+    Ok(removed > 0)
+}
+
+pub fn get_tags() -> Result<Vec<TagCount>, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let mut stmt = conn.prepare(
+        "select tags.name, count(puzzle_tags.puzzle_id) from tags
+         join puzzle_tags on tags.id = puzzle_tags.tag_id
+         join puzzles on puzzles.id = puzzle_tags.puzzle_id
+         where puzzles.deleted=0 and puzzles.visibility='public'
+         group by tags.id order by tags.name collate nocase"
+    )?;
+    let tags: Result<Vec<TagCount>, rusqlite::Error> = stmt.query_map([], |row| {
+        Ok(TagCount { name: row.get(0)?, puzzles: row.get(1)? })
+    })?.try_collect();
+    tags
+}
+
+pub fn create_collection(owner_id: i64, name: &str) -> Result<Collection, rusqlite::Error> {
     let conn = Connection::open(&*PUZZLE_DB_PATH)?;
-    conn.execute("DELETE FROM puzzles WHERE id=:id",&[(":id", id)])?;
+    let now = chrono::Utc::now().timestamp();
+    conn.execute(
+        "insert into collections (owner_id, name, created_at) values (:owner_id, :name, :now)",
+        named_params! { ":owner_id": owner_id, ":name": name, ":now": now }
+    )?;
+    Ok(Collection { id: conn.last_insert_rowid(), owner_id, name: name.to_string(), created_at: now, puzzle_ids: Vec::new() })
+}
+
+pub fn get_collections(owner_id: i64) -> Result<Vec<Collection>, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let mut stmt = conn.prepare(
+        "select id from collections where owner_id=(:owner_id) order by name collate nocase"
+    )?;
+    let ids: Result<Vec<i64>, rusqlite::Error> = stmt.query_map(named_params! { ":owner_id": owner_id }, |row| row.get(0))?.try_collect();
+    ids?.iter().map(get_collection).try_collect()
+}
+
+pub fn get_collection(id: &i64) -> Result<Collection, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let mut stmt = conn.prepare(
+        "select puzzle_id from collection_puzzles where collection_id=(:id) order by added_at, puzzle_id"
+    )?;
+    let puzzle_ids: Result<Vec<i64>, rusqlite::Error> = stmt.query_map(named_params! { ":id": id }, |row| row.get(0))?.try_collect();
+    let puzzle_ids = puzzle_ids?;
+
+    conn.query_row(
+        "select id, owner_id, name, created_at from collections where id=(:id)",
+        named_params! { ":id": id },
+        |row| Ok(Collection { id: row.get(0)?, owner_id: row.get(1)?, name: row.get(2)?, created_at: row.get(3)?, puzzle_ids })
+    )
+}
+
+pub fn add_to_collection(collection_id: &i64, puzzle_id: &i64) -> Result<(), rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let now = chrono::Utc::now().timestamp();
+    conn.execute(
+        "insert or ignore into collection_puzzles (collection_id, puzzle_id, added_at) values (:collection_id, :puzzle_id, :now)",
+        named_params! { ":collection_id": collection_id, ":puzzle_id": puzzle_id, ":now": now }
+    )?;
     Ok(())
+}
 
+/// Returns whether the puzzle was in the collection.
+pub fn remove_from_collection(collection_id: &i64, puzzle_id: &i64) -> Result<bool, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let removed = conn.execute(
+        "delete from collection_puzzles where collection_id=(:collection_id) and puzzle_id=(:puzzle_id)",
+        named_params! { ":collection_id": collection_id, ":puzzle_id": puzzle_id }
+    )?;
+    Ok(removed > 0)
+}
+
+/// Replaces a puzzle's solution. `None` removes it.
+pub fn set_solution(puzzle_id: &i64, solution: Option<&Solution>) -> Result<(), rusqlite::Error> {
+    let mut conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let tx = conn.transaction()?;
+    tx.execute("delete from solutions where puzzle_id=(:id)", named_params! { ":id": puzzle_id })?;
+    if let Some(solution) = solution {
+        let mut stmt = tx.prepare(
+            "insert into solutions (puzzle_id, x, y, letter) values (:id, :x, :y, :letter)"
+        )?;
+        for ((x, y), letter) in solution.iter() {
+            stmt.execute(named_params! { ":id": puzzle_id, ":x": x, ":y": y, ":letter": letter.as_str() })?;
+        }
+    }
+    tx.commit()
+}
+
+pub fn get_solution(puzzle_id: &i64) -> Result<Option<Solution>, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let mut stmt = conn.prepare("select x, y, letter from solutions where puzzle_id=(:id)")?;
+    let solution: Solution = stmt.query_map(named_params! { ":id": puzzle_id }, |row| {
+        let letter: String = row.get(2)?;
+        Ok(((row.get(0)?, row.get(1)?), CellValue::try_from(letter).unwrap_or_default()))
+    })?.try_collect()?;
+    match solution.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(solution)),
+    }
+}
+
+/// Notes that a solver was shown part of the answer.
+pub fn record_reveal(puzzle_id: &i64, user_id: Option<i64>, target: &str, squares: usize) -> Result<(), rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let now = chrono::Utc::now().timestamp();
+    conn.execute(
+        "insert into reveals (puzzle_id, user_id, target, squares, created_at) values (:id, :user_id, :target, :squares, :now)",
+        named_params! { ":id": puzzle_id, ":user_id": user_id, ":target": target, ":squares": squares, ":now": now }
+    )?;
+    Ok(())
+}
+
+pub fn get_puzzle_stats(puzzle_id: &i64) -> Result<PuzzleStats, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let has_solution: bool = conn.query_row(
+        "select exists (select 1 from solutions where puzzle_id=(:id))",
+        named_params! { ":id": puzzle_id },
+        |row| row.get(0)
+    )?;
+    conn.query_row(
+        "select count(*), coalesce(sum(squares), 0), count(distinct user_id), max(created_at) from reveals where puzzle_id=(:id)",
+        named_params! { ":id": puzzle_id },
+        |row| Ok(PuzzleStats {
+            has_solution,
+            reveals: row.get(0)?,
+            revealed_squares: row.get(1)?,
+            assisted_solvers: row.get(2)?,
+            last_reveal_at: row.get(3)?,
+        })
+    )
 }
 
 pub fn get_puzzle(id: &i64) -> Result<Option<Crossword>, Error> {
@@ -337,6 +1281,17 @@ pub fn get_puzzle(id: &i64) -> Result<Option<Crossword>, Error> {
 }
 
 
+/// Keeps the database's copy of a puzzle's details in step with its file.
+fn record_puzzle_save(id: &i64, cw: &Crossword) -> Result<(), rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let now = chrono::Utc::now().timestamp();
+    conn.execute(
+        "update puzzles set updated_at=(:now), metadata=(:metadata) where id=(:id)",
+        named_params! { ":now": now, ":metadata": cw.metadata(), ":id": id }
+    )?;
+    Ok(())
+}
+
 pub fn save_puzzle(id: &i64, cw: &Crossword) -> Result<(), Error> {
 
     match get_puzzle_db(id) {
@@ -353,6 +1308,8 @@ pub fn save_puzzle(id: &i64, cw: &Crossword) -> Result<(), Error> {
             info!("writing crossword to {:?}", file);
             let cw_data = serde_json::to_string(cw)?;
             File::write_all(&mut file, cw_data.as_bytes())?;
+            record_puzzle_save(id, cw).map_err(|e| Error::new(ErrorKind::Other, format!("Database error: {}", e)))?;
+            index_puzzle(id, cw).map_err(|e| Error::new(ErrorKind::Other, format!("Database error: {}", e)))?;
             Ok(())
         }
         Err(rusqlite::Error::QueryReturnedNoRows) => {
@@ -366,7 +1323,7 @@ pub fn save_puzzle(id: &i64, cw: &Crossword) -> Result<(), Error> {
 
 }
 
-pub fn create_new_puzzle(name: &str, cw: &Crossword) -> Result<i64, Error> {
+pub fn create_new_puzzle(name: &str, cw: &Crossword, owner_id: i64, visibility: Visibility) -> Result<i64, Error> {
 
     let data = serde_json::to_string(cw)?;
 
@@ -383,7 +1340,7 @@ pub fn create_new_puzzle(name: &str, cw: &Crossword) -> Result<i64, Error> {
         || Error::new(ErrorKind::Other, format!("Path must be valid utf-8"))
     )?;
 
-    add_puzzle_to_db(name, puzzle_path_str).map_err(|e| Error::new(ErrorKind::Other, format!("Database error: {}", e)))?;
+    add_puzzle_to_db(name, puzzle_path_str, owner_id, visibility, cw.metadata()).map_err(|e| Error::new(ErrorKind::Other, format!("Database error: {}", e)))?;
 
 
     let mut file = match File::options().write(true).create(true).open(&puzzle_path) {
@@ -401,6 +1358,403 @@ pub fn create_new_puzzle(name: &str, cw: &Crossword) -> Result<i64, Error> {
     file.set_len(0)?;
     info!("writing crossword to {:?}", file);
     File::write_all(&mut file, data.as_bytes())?;
+    index_puzzle(&id, cw).map_err(|e| Error::new(ErrorKind::Other, format!("Database error: {}", e)))?;
 
     Ok(id)
-}
\ No newline at end of file
+}
+
+/// Replaces a puzzle's clues in the search index.
+pub fn index_puzzle(id: &i64, cw: &Crossword) -> Result<(), rusqlite::Error> {
+    let mut conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let tx = conn.transaction()?;
+    index_puzzle_hints(&tx, id, cw)?;
+    tx.commit()
+}
+
+fn index_puzzle_hints(conn: &Connection, id: &i64, cw: &Crossword) -> Result<(), rusqlite::Error> {
+    conn.execute("delete from clue_search where puzzle_id=(:id)", named_params! { ":id": id })?;
+    let mut stmt = conn.prepare(
+        "insert into clue_search (hint, puzzle_id, clue_id) values (:hint, :id, :clue_id)"
+    )?;
+    for (clue_id, hint) in cw.hints() {
+        stmt.execute(named_params! { ":hint": hint, ":id": id, ":clue_id": clue_id })?;
+    }
+    Ok(())
+}
+
+/// The ID of every puzzle, including soft deleted ones.
+pub fn get_puzzle_ids() -> Result<Vec<i64>, rusqlite::Error> {
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let mut stmt = conn.prepare("select id from puzzles order by id")?;
+    let ids: Result<Vec<i64>, rusqlite::Error> = stmt.query_map([], |row| row.get(0))?.try_collect();
+    ids
+}
+
+/// Rebuilds the search index from every puzzle's file, returning how many
+/// puzzles were indexed. Puzzles whose file can't be read are skipped.
+pub fn reindex_clues() -> anyhow::Result<usize> {
+    let ids = get_puzzle_ids()?;
+
+    let mut conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let tx = conn.transaction()?;
+    tx.execute("delete from clue_search", [])?;
+    let mut indexed = 0;
+    for id in ids {
+        match get_puzzle(&id) {
+            Ok(Some(cw)) => {
+                index_puzzle_hints(&tx, &id, &cw)?;
+                indexed += 1;
+            },
+            Ok(None) => warn!("Puzzle {id} has no crossword data, so it was not indexed"),
+            Err(e) => warn!("Could not read puzzle {id}, so it was not indexed: {e}"),
+        }
+    }
+    tx.commit()?;
+    Ok(indexed)
+}
+
+const SNIPPET_START: &str = "\u{2}";
+const SNIPPET_END: &str = "\u{3}";
+
+/// Searches clue hints in the puzzles someone can browse, best matches first.
+/// Every word in `q` must appear in the hint.
+pub fn search_clues(viewer: Option<&User>, q: &str, limit: usize) -> Result<Vec<ClueMatch>, rusqlite::Error> {
+    let query = match fts_query(q) {
+        Some(query) => query,
+        None => return Ok(Vec::new()),
+    };
+
+    let conn = Connection::open(&*PUZZLE_DB_PATH)?;
+    let mut stmt = conn.prepare(
+        "select clue_search.puzzle_id, puzzles.name, clue_search.clue_id,
+                snippet(clue_search, 0, :start, :end, '…', 16)
+         from clue_search join puzzles on puzzles.id = clue_search.puzzle_id
+         where clue_search match (:query) and puzzles.deleted=0
+             and (puzzles.visibility='public' or puzzles.owner_id=(:viewer))
+         order by clue_search.rank limit (:limit)"
+    )?;
+
+    let matches = stmt.query_map(named_params! {
+        ":start": SNIPPET_START,
+        ":end": SNIPPET_END,
+        ":query": query,
+        ":viewer": viewer.map(|x| x.id),
+        ":limit": limit,
+    }, |row| {
+        let snippet: String = row.get(3)?;
+        Ok(ClueMatch {
+            puzzle_id: row.get(0)?,
+            puzzle_name: row.get(1)?,
+            clue_id: row.get(2)?,
+            snippet: html_escape(&snippet).replace(SNIPPET_START, "<mark>").replace(SNIPPET_END, "</mark>"),
+        })
+    })?
+    .try_collect();
+    matches
+}
+
+/// Turns a search into an FTS5 query that treats each word literally, so
+/// punctuation in a remembered clue can't be read as query syntax.
+fn fts_query(q: &str) -> Option<String> {
+    let terms: Vec<String> = q.split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    match terms.is_empty() {
+        true => None,
+        false => Some(terms.join(" ")),
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, sync::{Mutex, MutexGuard, Once}};
+
+    use sha256::digest;
+
+    use rusqlite::Connection;
+
+    use crate::crossword::PuzzleMetadata;
+
+    use super::{add_puzzle_to_db, add_to_collection, add_user, batch_delete, batch_restore, check_api_token, check_session, create_api_token, create_collection, create_session, delete_puzzle, delete_session, delete_user_sessions, fts_query, get_api_token, get_api_tokens, get_collection, get_collections, get_puzzle_db, get_puzzle_tags, get_soft_delete_puzzles, get_tags, get_user, hash_password, html_escape, init_db, like_pattern, list_all_puzzles, list_puzzles, needs_rehash, remove_from_collection, restore_puzzle, revoke_api_token, set_admin, soft_delete_puzzle, tag_puzzle, untag_puzzle, update_puzzle_details, validate_password, PuzzleDbData, PuzzleQuery, TokenScope, User, Visibility, PUZZLE_DB_PATH, PUZZLE_DIR_PATH};
+
+    /// Points the database at a new file in the temp directory. The tests
+    /// share it, so each uses its own names, and they take turns.
+    fn test_db() -> MutexGuard<'static, ()> {
+        static INIT: Once = Once::new();
+        static LOCK: Mutex<()> = Mutex::new(());
+        INIT.call_once(|| {
+            let dir = env::temp_dir().join(format!("cw_grid_test_{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            env::set_var("PUZZLE_PATH", &dir);
+            init_db().unwrap();
+        });
+        LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn new_user(username: &str) -> User {
+        add_user(username, "correct horse").unwrap();
+        get_user(username).unwrap()
+    }
+
+    /// Adds a puzzle with an empty file and returns its row.
+    fn add_puzzle(name: &str, owner: &User, visibility: Visibility) -> PuzzleDbData {
+        let file = format!("{}/{name}.json", *PUZZLE_DIR_PATH);
+        fs::write(&file, "{}").unwrap();
+        add_puzzle_to_db(name, &file, owner.id, visibility, &PuzzleMetadata::default()).unwrap();
+        find_puzzles(Some(owner), name).pop().unwrap()
+    }
+
+    fn find_puzzles(viewer: Option<&User>, name: &str) -> Vec<PuzzleDbData> {
+        let query = PuzzleQuery { q: Some(name.to_string()), ..Default::default() };
+        list_puzzles(viewer, &query).unwrap().puzzles
+    }
+
+    #[test]
+    fn test_hash_is_phc_string() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(!needs_rehash(&hash));
+    }
+
+    #[test]
+    fn test_hashes_are_salted() {
+        let a = hash_password("correct horse").unwrap();
+        let b = hash_password("correct horse").unwrap();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_validate_password() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(validate_password("correct horse", &hash).is_ok());
+        assert!(validate_password("battery staple", &hash).is_err());
+    }
+
+    #[test]
+    fn test_validate_legacy_password() {
+        let legacy = digest("correct horse");
+        assert!(needs_rehash(&legacy));
+        assert!(validate_password("correct horse", &legacy).is_ok());
+        assert!(validate_password("battery staple", &legacy).is_err());
+    }
+
+    #[test]
+    fn test_like_pattern_escapes_wildcards() {
+        assert_eq!(like_pattern("big"), "%big%");
+        assert_eq!(like_pattern("100%_\\"), "%100\\%\\_\\\\%");
+    }
+
+    #[test]
+    fn test_fts_query_quotes_each_word() {
+        assert_eq!(fts_query("good company"), Some(r#""good" "company""#.to_string()));
+        assert_eq!(fts_query(r#"e'er "I"#), Some(r#""e'er" """I""#.to_string()));
+        assert_eq!(fts_query("   "), None);
+    }
+
+    #[test]
+    fn test_sessions() {
+        let _db = test_db();
+        let id = add_user("session_user", "correct horse").unwrap();
+        let first = create_session(id, Some("curl")).unwrap();
+        let second = create_session(id, None).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(check_session(&first).unwrap().username, "session_user");
+
+        delete_session(&first).unwrap();
+        assert!(matches!(check_session(&first), Err(rusqlite::Error::QueryReturnedNoRows)));
+        assert_eq!(check_session(&second).unwrap().id, id);
+
+        create_session(id, None).unwrap();
+        assert_eq!(delete_user_sessions(id).unwrap(), 2);
+        assert!(check_session(&second).is_err());
+        assert!(check_session("not a token").is_err());
+    }
+
+    #[test]
+    fn test_api_tokens() {
+        let _db = test_db();
+        let id = add_user("token_user", "correct horse").unwrap();
+        let (api_token, token) = create_api_token(id, "laptop", Some(&[TokenScope::Read, TokenScope::Upload])).unwrap();
+        assert!(token.starts_with("cwt_"));
+        assert_eq!(api_token.scopes, Some(vec![TokenScope::Read, TokenScope::Upload]));
+        assert_eq!(api_token.last_used_at, None);
+
+        let (user, checked) = check_api_token(&token).unwrap();
+        assert_eq!((user.id, user.username.as_str()), (id, "token_user"));
+        assert_eq!(checked.id, api_token.id);
+        assert!(get_api_token(api_token.id).unwrap().last_used_at.is_some());
+
+        let (unscoped, _) = create_api_token(id, "script", None).unwrap();
+        assert_eq!(unscoped.scopes, None);
+        assert_eq!(get_api_tokens(id).unwrap().len(), 2);
+
+        revoke_api_token(api_token.id).unwrap();
+        assert!(get_api_token(api_token.id).unwrap().revoked);
+        assert!(matches!(check_api_token(&token), Err(rusqlite::Error::QueryReturnedNoRows)));
+    }
+
+    #[test]
+    fn test_visibility() {
+        let _db = test_db();
+        let owner = new_user("visibility_owner");
+        let other = new_user("visibility_other");
+        let puzzle = add_puzzle("visibility_puzzle", &owner, Visibility::Private);
+        assert_eq!(puzzle.owner_id, Some(owner.id));
+        assert!(puzzle.can_edit(&owner));
+        assert!(!puzzle.can_view(Some(&other)));
+        assert!(find_puzzles(Some(&other), "visibility_puzzle").is_empty());
+        assert!(find_puzzles(None, "visibility_puzzle").is_empty());
+
+        update_puzzle_details(puzzle.id as i64, Some("visibility_puzzle renamed"), Some(Visibility::Unlisted)).unwrap();
+        let puzzle = find_puzzles(Some(&owner), "visibility_puzzle").pop().unwrap();
+        assert_eq!((puzzle.name.as_str(), puzzle.visibility), ("visibility_puzzle renamed", Visibility::Unlisted));
+        assert!(puzzle.can_view(None));
+        assert!(find_puzzles(None, "visibility_puzzle").is_empty());
+
+        update_puzzle_details(puzzle.id as i64, None, Some(Visibility::Public)).unwrap();
+        assert_eq!(find_puzzles(None, "visibility_puzzle").len(), 1);
+
+        assert!(!puzzle.can_edit(&other));
+        set_admin(other.id, true).unwrap();
+        let admin = get_user("visibility_other").unwrap();
+        assert!(admin.is_admin && puzzle.can_edit(&admin));
+    }
+
+    #[test]
+    fn test_migration_grants_no_admin() {
+        let _db = test_db();
+        add_user("migration_user", "correct horse").unwrap();
+        let conn = Connection::open(&*PUZZLE_DB_PATH).unwrap();
+        conn.execute("update users set is_admin=0", []).unwrap();
+        conn.execute("insert into puzzles (name, file) values ('migration_orphan', 'migration_orphan.json')", []).unwrap();
+
+        let owner = || -> Option<i64> { conn.query_row("select owner_id from puzzles where name='migration_orphan'", [], |row| row.get(0)).unwrap() };
+
+        init_db().unwrap();
+        let admins: i64 = conn.query_row("select count(*) from users where is_admin != 0", [], |row| row.get(0)).unwrap();
+        assert_eq!((admins, owner()), (0, None));
+        let user = get_user("migration_user").unwrap();
+        assert!(!user.is_admin);
+
+        set_admin(user.id, true).unwrap();
+        assert_eq!(owner(), Some(user.id));
+
+        conn.execute("update puzzles set owner_id=null where name='migration_orphan'", []).unwrap();
+        init_db().unwrap();
+        assert_eq!(owner(), Some(user.id));
+        set_admin(user.id, false).unwrap();
+        conn.execute("delete from puzzles where name='migration_orphan'", []).unwrap();
+    }
+
+    #[test]
+    fn test_soft_delete_restore_and_purge() {
+        let _db = test_db();
+        let owner = new_user("deleting_owner");
+        let first = add_puzzle("deleting_first", &owner, Visibility::Public);
+        let second = add_puzzle("deleting_second", &owner, Visibility::Public);
+        let deleted = || get_soft_delete_puzzles().unwrap().iter().filter(|x| x.name.starts_with("deleting_")).count();
+
+        soft_delete_puzzle(first.id as i64).unwrap();
+        soft_delete_puzzle(second.id as i64).unwrap();
+        assert_eq!(deleted(), 2);
+        assert!(find_puzzles(Some(&owner), "deleting_").is_empty());
+        assert!(get_puzzle_db(&(first.id as i64)).unwrap().is_deleted());
+
+        restore_puzzle(&(first.id as i64)).unwrap();
+        assert_eq!(deleted(), 1);
+        batch_restore().unwrap();
+        assert_eq!(deleted(), 0);
+        assert_eq!(find_puzzles(Some(&owner), "deleting_").len(), 2);
+
+        tag_puzzle(&(first.id as i64), "deleting").unwrap();
+        delete_puzzle(&(first.id as i64)).unwrap();
+        assert!(get_puzzle_db(&(first.id as i64)).is_err());
+        assert!(get_puzzle_tags(&(first.id as i64)).unwrap().is_empty());
+        assert!(!std::path::Path::new(&first.file).exists());
+
+        soft_delete_puzzle(second.id as i64).unwrap();
+        batch_delete().unwrap();
+        assert!(get_puzzle_db(&(second.id as i64)).is_err());
+        assert!(!std::path::Path::new(&second.file).exists());
+    }
+
+    #[test]
+    fn test_listing_pages() {
+        let _db = test_db();
+        let owner = new_user("listing_owner");
+        for name in ["listing_a", "listing_b", "listing_c"] {
+            add_puzzle(name, &owner, Visibility::Public);
+        }
+        let query = PuzzleQuery { q: Some("listing_".to_string()), per_page: 2, page: 2, ..Default::default() };
+        let page = list_puzzles(None, &query).unwrap();
+        assert_eq!((page.puzzles.len(), page.total), (1, 3));
+        assert_eq!(list_all_puzzles(None, &query).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_tags() {
+        let _db = test_db();
+        let owner = new_user("tagging_owner");
+        let public = add_puzzle("tagging_public", &owner, Visibility::Public);
+        let private = add_puzzle("tagging_private", &owner, Visibility::Private);
+        let deleted = add_puzzle("tagging_deleted", &owner, Visibility::Public);
+        tag_puzzle(&(public.id as i64), "Tagging").unwrap();
+        tag_puzzle(&(public.id as i64), "tagging").unwrap();
+        tag_puzzle(&(private.id as i64), "tagging").unwrap();
+        tag_puzzle(&(private.id as i64), "tagging secret").unwrap();
+        tag_puzzle(&(deleted.id as i64), "tagging").unwrap();
+        soft_delete_puzzle(deleted.id as i64).unwrap();
+        assert_eq!(get_puzzle_tags(&(public.id as i64)).unwrap(), ["Tagging"]);
+
+        let tags = get_tags().unwrap();
+        let counts: Vec<(&str, usize)> = tags.iter().filter(|x| x.name.starts_with("Tagging") || x.name.starts_with("tagging"))
+            .map(|x| (x.name.as_str(), x.puzzles)).collect();
+        assert_eq!(counts, [("Tagging", 1)]);
+
+        let query = PuzzleQuery { tag: Some("tagging".to_string()), ..Default::default() };
+        assert_eq!(list_puzzles(Some(&owner), &query).unwrap().total, 2);
+        assert!(untag_puzzle(&(public.id as i64), "TAGGING").unwrap());
+        assert!(!untag_puzzle(&(public.id as i64), "tagging").unwrap());
+        assert!(get_puzzle_tags(&(public.id as i64)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_collections() {
+        let _db = test_db();
+        let owner = new_user("collecting_owner");
+        let first = add_puzzle("collecting_first", &owner, Visibility::Public);
+        let second = add_puzzle("collecting_second", &owner, Visibility::Private);
+        let collection = create_collection(owner.id, "Sunday").unwrap();
+        assert!(create_collection(owner.id, "Sunday").is_err());
+        create_collection(owner.id, "Monday").unwrap();
+
+        add_to_collection(&collection.id, &(first.id as i64)).unwrap();
+        add_to_collection(&collection.id, &(second.id as i64)).unwrap();
+        add_to_collection(&collection.id, &(first.id as i64)).unwrap();
+        assert_eq!(get_collection(&collection.id).unwrap().puzzle_ids, [first.id as i64, second.id as i64]);
+        let names: Vec<String> = get_collections(owner.id).unwrap().into_iter().map(|x| x.name).collect();
+        assert_eq!(names, ["Monday", "Sunday"]);
+
+        let query = PuzzleQuery { collection: Some(collection.id), ..Default::default() };
+        assert_eq!(list_puzzles(Some(&owner), &query).unwrap().total, 2);
+        assert_eq!(list_puzzles(None, &query).unwrap().total, 1);
+
+        assert!(remove_from_collection(&collection.id, &(second.id as i64)).unwrap());
+        assert!(!remove_from_collection(&collection.id, &(second.id as i64)).unwrap());
+        assert_eq!(get_collection(&collection.id).unwrap().puzzle_ids, [first.id as i64]);
+    }
+
+    #[test]
+    fn test_html_escape() {
+        assert_eq!(html_escape("<b>Tom & Jerry's</b>"), "&lt;b&gt;Tom &amp; Jerry&#39;s&lt;/b&gt;");
+    }
+}
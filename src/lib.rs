@@ -10,7 +10,7 @@ use std::{
     collections::HashMap, fmt, io::{prelude::*, BufReader, Error, ErrorKind}, net::TcpStream, sync::{mpsc::{self}, Arc, Mutex}, thread
 };
 use log::{error, info, trace, warn};
-use response::SetCookie;
+use response::{Cookies, SameSite, SetCookie};
 
 type Job = Box<dyn FnOnce() + Send + 'static>;
 
@@ -108,6 +108,56 @@ impl StatusLine {
         Ok(StatusLine{ protocol: protocol, verb, route: route })
 
     }
+
+    /// The route without its query string.
+    pub fn path(&self) -> &str {
+        match self.route.split_once('?') {
+            Some((path, _)) => path,
+            None => &self.route,
+        }
+    }
+
+    /// The decoded query string parameters. Parameters without a value are
+    /// skipped, and the last of any repeated parameter wins.
+    pub fn query(&self) -> HashMap<String, String> {
+        let query = match self.route.split_once('?') {
+            Some((_, query)) => query,
+            None => return HashMap::new(),
+        };
+        query.split('&')
+            .filter_map(|x| x.split_once('='))
+            .filter(|(k, v)| !k.is_empty() && !v.is_empty())
+            .map(|(k, v)| (url_decode(k), url_decode(v)))
+            .collect()
+    }
+}
+
+/// Decodes `application/x-www-form-urlencoded` text. Malformed escapes are
+/// left as they are.
+pub fn url_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes.get(i..i+3) {
+            Some([b'%', hi, lo]) => hex_value(*hi).zip(hex_value(*lo)),
+            _ => None,
+        };
+        match (escaped, bytes[i]) {
+            (Some((hi, lo)), _) => {
+                decoded.push(hi << 4 | lo);
+                i += 2;
+            },
+            (None, b'+') => decoded.push(b' '),
+            (None, b) => decoded.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|x| x as u8)
 }
 
 impl fmt::Display for StatusLine {
@@ -135,7 +185,11 @@ impl HttpRequest {
             },
             HttpVerb::Post  => {
                 let headers = Self::process_headers(&mut buf_reader)?;
-                let len = headers["Content-Length"].parse::<usize>().map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+                // a POST without a body, like a revoke or log out, may not send a length.
+                let len = match headers.get("Content-Length") {
+                    Some(len) => len.parse::<usize>().map_err(|err| Error::new(ErrorKind::InvalidData, err))?,
+                    None => 0
+                };
                 let mut buf = vec![0; len];
                 let _ = buf_reader.read_exact(&mut buf);
                 Ok( Self::Post{status_line, headers, body:buf} )
@@ -196,71 +250,88 @@ impl fmt::Display for HttpRequest {
     }
 }
 
-pub fn get_login_cookies(session: i64, username: i64) -> (SetCookie<String>, SetCookie<String>) {
-    let mut session_cookie = SetCookie::new("session-id".to_string(), session.to_string());
-    session_cookie.set_max_age(chrono::Duration::minutes(60));
+pub fn get_login_cookies(token: &str) -> SetCookie<String> {
+    let mut session_cookie = SetCookie::new("session-id".to_string(), token.to_string());
+    session_cookie.set_max_age(chrono::Duration::seconds(db::SESSION_LIFETIME_SECONDS));
+    secure_cookie(&mut session_cookie);
+    session_cookie
+}
 
-    let mut username_cookie = SetCookie::new("user-id".to_string(), username.to_string());
-    username_cookie.set_max_age(chrono::Duration::minutes(60));
-    (session_cookie, username_cookie)
+pub fn get_logout_cookies() -> SetCookie<String> {
+    let mut session_cookie = SetCookie::new("session-id".to_string(), "".to_string());
+    session_cookie.set_max_age(chrono::Duration::seconds(0));
+    secure_cookie(&mut session_cookie);
+    session_cookie
 }
 
-pub fn is_authorised(headers: &HashMap<String,String>) -> Result<(),String> {
+fn secure_cookie(cookie: &mut SetCookie<String>) {
+    cookie.set_http_only(true);
+    cookie.set_secure(true);
+    cookie.set_same_site(SameSite::Lax);
+    cookie.set_path("/");
+}
 
-    #[derive(PartialEq, Eq)]
-    struct Cookie<'a> {
-        name: &'a str,
-        value: &'a str
-    }
+pub fn get_session_token(headers: &HashMap<String,String>) -> Option<String> {
+    Cookies::from_headers(headers)
+        .get("session-id")
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_string())
+}
 
-    let cookies = headers.get("Cookie");
-    if cookies.is_none() {
-        info!("Missing cookie header");
-        return Err("missing session-id or user-id".to_string())       
-    }
-    let cookies: Vec<Cookie> = cookies.unwrap().split(";").map(|x| {
-        let mut c = x.split("=");
-        let name = c.next().unwrap().trim();
-        let value = c.next().unwrap().trim();
-        Cookie{ name, value}
-    }).collect();
-
-    let session_cookie: Option<&Cookie> = cookies.iter().find(|x| x.name=="session-id");
-    let user_id_cookie: Option<&Cookie> = cookies.iter().find(|x| x.name=="user-id");
-
-    let session: i64 = match session_cookie {
-        Some(c) => {
-            match c.value.parse() {
-                Ok(x) => x,
-                Err(e) => {
-                    info!("Can't parse session cookie as an int");
-                    return Err(format!("Session must be an integer - found {}", e))
-                }
-            }
-        },
-        None => return Err("missing session-id".to_string()),
+pub fn get_bearer_token(headers: &HashMap<String,String>) -> Option<&str> {
+    headers.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("Authorization"))
+        .and_then(|(_, v)| v.trim().strip_prefix("Bearer "))
+        .map(|token| token.trim())
+}
+
+/// Authenticates a request made with either an `Authorization: Bearer` token
+/// or a session cookie, and checks it may be used for `scope`. A bearer token
+/// is never ignored in favour of a cookie, and sessions carry every scope.
+pub fn is_authorised_for(headers: &HashMap<String,String>, scope: db::TokenScope) -> Result<db::User,String> {
+
+    let token = match get_bearer_token(headers) {
+        Some(token) => token,
+        None => return is_authorised(headers)
     };
 
-    let id: i64 = match user_id_cookie {
-        Some(c) => {
-            match c.value.parse() {
-                Ok(x) => x,
-                Err(e) => {
-                    info!("Can't parse user-id cookie as an int");
-                    return Err(format!("Session must be an integer - found {}", e))
-                }
-            }
+    match db::check_api_token(token) {
+        Ok((user, api_token)) if api_token.allows(scope) => {
+            trace!("User {} authorised with token {}", user.id, api_token.id);
+            Ok(user)
         },
-        None => return Err("missing user-id".to_string()),
-    };
+        Ok((_, api_token)) => {
+            info!("Token {} does not have the {scope} scope", api_token.id);
+            Err(format!("token does not have the {scope} scope"))
+        },
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err("invalid or revoked token".to_string()),
+        Err(e) => {
+            error!("Failed to check api token: {e}");
+            Err("Failed to validate api token".to_string())
+        },
+    }
+}
+
+pub fn is_authorised(headers: &HashMap<String,String>) -> Result<db::User,String> {
 
+    let token = match get_session_token(headers) {
+        Some(token) => token,
+        None => {
+            info!("Missing session cookie");
+            return Err("missing session-id".to_string())
+        }
+    };
 
-    match db::check_session(id,session) {
-        Ok(_) => return {
-            trace!("User signed in");
-            Ok(())
+    match db::check_session(&token) {
+        Ok(user) => {
+            trace!("User {} signed in", user.id);
+            Ok(user)
+        },
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err("Session has expired".to_string()),
+        Err(e) => {
+            error!("Failed to check session: {e}");
+            Err("Failed to validate user session".to_string())
         },
-        Err(_) => return Err("Failed to validate user session".to_string()),
     }
 }
 
@@ -365,7 +436,7 @@ impl Drop for ThreadPool {
 mod tests {
     use std::{collections::HashMap, io::ErrorKind};
 
-    use crate::get_form_data;
+    use crate::{get_form_data, url_decode, StatusLine};
 
 
     #[test]
@@ -421,4 +492,30 @@ mod tests {
         let x = get_form_data("=c");
         assert_eq!(x.map_err(|e| e.kind()),Err(ErrorKind::InvalidData))
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_url_decode() {
+        assert_eq!(url_decode("big+puzzle%21"), "big puzzle!");
+        assert_eq!(url_decode("caf%C3%A9"), "café");
+    }
+
+    #[test]
+    fn test_url_decode_malformed_escape() {
+        assert_eq!(url_decode("100%"), "100%");
+        assert_eq!(url_decode("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn test_status_line_query() {
+        let status_line = StatusLine::new("GET /puzzle/list?q=big+one&page=2&sort= HTTP/1.1").unwrap();
+        assert_eq!(status_line.path(), "/puzzle/list");
+        assert_eq!(status_line.query(), HashMap::from([("q".to_string(), "big one".to_string()), ("page".to_string(), "2".to_string())]));
+    }
+
+    #[test]
+    fn test_status_line_without_query() {
+        let status_line = StatusLine::new("GET /puzzle/list HTTP/1.1").unwrap();
+        assert_eq!(status_line.path(), "/puzzle/list");
+        assert!(status_line.query().is_empty());
+    }
+}
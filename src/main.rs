@@ -1,5 +1,5 @@
 use cw_grid_server::{
-    crossword::{Cell, Crossword}, db::{add_user, create_new_puzzle, create_puzzle_dir, get_all_puzzle_db, get_puzzle, get_puzzle_db, get_user_password, init_db, save_puzzle, set_session, soft_delete_puzzle, validate_password}, get_form_data, get_login_cookies, is_authorised, response::{internal_error_response, ResponseBuilder, StatusCode}, websockets::{close_websocket_message, decode_client_frame, websocket_handshake, Message, OpCode}, HttpRequest, ThreadPool
+    crossword::{text::printable, Cell, CellError, CheckResult, ClueList, Crossword, Ipuz, Jpz, PaperSize, PdfOptions, PrintedLetters, Puz, PuzzleMetadata, Solution, SolutionInput, SvgOptions, Target, TextOptions, ValidationError}, db::{add_to_collection, add_user, batch_delete, batch_restore, create_api_token, create_collection, create_new_puzzle, create_puzzle_dir, create_session, delete_puzzle, delete_session, delete_user_sessions, get_api_token, get_api_tokens, get_collection, get_collections, get_puzzle, get_puzzle_db, get_puzzle_stats, get_puzzle_tags, get_solution, get_soft_delete_puzzles, get_tags, get_user, get_user_password, init_db, list_all_puzzles, list_puzzles, needs_rehash, record_reveal, remove_from_collection, restore_puzzle, revoke_api_token, save_puzzle, search_clues, set_solution, soft_delete_puzzle, tag_puzzle, untag_puzzle, update_password, update_puzzle_details, validate_password, Collection, PuzzleDbData, PuzzleQuery, SignIn, TokenScope, User, Visibility, MAX_LABEL_LEN}, get_form_data, get_login_cookies, get_logout_cookies, get_session_token, is_authorised, is_authorised_for, response::{internal_error_response, ResponseBuilder, SetCookie, StatusCode}, websockets::{close_websocket_message, decode_client_frame, websocket_handshake, Message, OpCode}, HttpRequest, StatusLine, ThreadPool
 };
 use lazy_static::lazy_static;
 use log::{error, info, trace, warn};
@@ -65,17 +65,51 @@ fn main() {
 
 
     routes.insert(r"^/puzzle/\d+$", puzzle_handler);
+    routes.insert(r"^/puzzle/\d+\.svg$", puzzle_svg_handler);
+    routes.insert(r"^/puzzle/\d+\.txt$", puzzle_text_handler);
     routes.insert(r"^/puzzle/\d+/data$", puzzle_handler_data);
     routes.insert(r"^/puzzle/\d+/live$", puzzle_handler_live);
     routes.insert(r"^/puzzle/\d+/delete$", puzzle_soft_delete_handler);
+    routes.insert(r"^/puzzle/\d+/edit$", puzzle_edit_handler);
+    routes.insert(r"^/puzzle/\d+/tags$", puzzle_tags_handler);
+    routes.insert(r"^/puzzle/\d+/tags/remove$", puzzle_untag_handler);
+    routes.insert(r"^/puzzle/\d+/check$", puzzle_check_handler);
+    routes.insert(r"^/puzzle/\d+/reveal$", puzzle_reveal_handler);
+    routes.insert(r"^/puzzle/\d+/stats$", puzzle_stats_handler);
+    routes.insert(r"^/puzzle/\d+/export\.puz$", puzzle_export_puz_handler);
+    routes.insert(r"^/puzzle/\d+/export\.ipuz$", puzzle_export_ipuz_handler);
+    routes.insert(r"^/puzzle/\d+/export\.jpz$", puzzle_export_jpz_handler);
+    routes.insert(r"^/puzzle/\d+/export\.txt$", puzzle_export_clue_list_handler);
+    routes.insert(r"^/puzzle/\d+/print\.pdf$", puzzle_print_handler);
 
     routes.insert(r"^/puzzle/add", puzzle_add_handler);
+    routes.insert(r"^/puzzle/import$", puzzle_import_handler);
     routes.insert(r"^/puzzle/list$", puzzle_list_handler);
 
+    routes.insert(r"^/api/v1/auth/login$", api_log_in_handler);
+    routes.insert(r"^/api/v1/auth/signup$", api_sign_up_handler);
+    routes.insert(r"^/api/v1/auth/logout$", api_log_out_handler);
+    routes.insert(r"^/api/v1/auth/me$", api_me_handler);
+    routes.insert(r"^/api/v1/search$", api_search_handler);
+    routes.insert(r"^/api/v1/tags$", api_tags_handler);
+    routes.insert(r"^/api/v1/collections$", api_collections_handler);
+    routes.insert(r"^/api/v1/collections/\d+$", api_collection_handler);
+    routes.insert(r"^/api/v1/collections/\d+/add$", api_collection_add_handler);
+    routes.insert(r"^/api/v1/collections/\d+/remove$", api_collection_remove_handler);
+    routes.insert(r"^/api/v1/tokens$", api_tokens_handler);
+    routes.insert(r"^/api/v1/tokens/\d+/revoke$", api_token_revoke_handler);
+
+    routes.insert(r"^/admin/puzzles$", admin_puzzles_handler);
+    routes.insert(r"^/admin/puzzles/\d+/restore$", admin_restore_handler);
+    routes.insert(r"^/admin/puzzles/\d+/purge$", admin_purge_handler);
+    routes.insert(r"^/admin/puzzles/batch-restore$", admin_batch_restore_handler);
+    routes.insert(r"^/admin/puzzles/batch-delete$", admin_batch_delete_handler);
+
     // routes.insert(r"^/login", login_handler);
     routes.insert(r"^/sign-up", sign_up_handler);
     routes.insert(r"^/log-in", log_in_handler);
-    routes.insert(r"^/log-out", log_out_handler);
+    routes.insert(r"^/log-out$", log_out_handler);
+    routes.insert(r"^/log-out-everywhere$", log_out_everywhere_handler);
 
     routes.insert(r"^/client-test", client_test_handler);
     routes.insert(r"^/add-client-test", add_client_test_handler);
@@ -156,12 +190,12 @@ impl Api {
             HttpRequest::Get {
                 status_line,
                 headers: _,
-            } => status_line.route.as_str(),
+            } => status_line.path(),
             HttpRequest::Post {
                 status_line,
                 headers: _,
                 body: _,
-            } => status_line.route.as_str(),
+            } => status_line.path(),
         };
 
         info!("{req}");
@@ -247,32 +281,49 @@ fn server_error(tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerErr
 fn index_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerError> {
 
     match req {
-        HttpRequest::Get { status_line: _, headers } => {
-            
-            let mut context = tera::Context::new();
-            let puzzle_data = match get_all_puzzle_db(){
-                Ok(puzzle_data) => puzzle_data,
-                Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+        HttpRequest::Get { status_line, headers } => {
+
+            let query = match puzzle_query(status_line) {
+                Ok(query) => query,
+                Err(message) => return bad_request(tera, stream, &message)
             };
 
-            match is_authorised(headers) {
-                Ok(_) => {
+            let mut context = tera::Context::new();
+            let mut response = ResponseBuilder::new();
+
+            let user = match is_authorised(headers) {
+                Ok(user) => {
                     context.insert("logged_in", &true);
-                    context.insert("data", "Logged In");
+                    context.insert("data", &format!("Logged in as {}", user.username));
+                    context.insert("is_admin", &user.is_admin);
+                    // the session's expiry slides forward, so the cookie's should too.
+                    if let Some(token) = get_session_token(headers) {
+                        response.add_cookie(get_login_cookies(&token));
+                    }
+                    Some(user)
                 },
                 Err(e) => {
-                    context.insert("data", &e)
+                    context.insert("data", &e);
+                    None
                 },
             };
 
-            
-            context.insert("puzzles", &puzzle_data);
+            match can_list_collection(&query, user.as_ref()) {
+                Ok(true) => (),
+                Ok(false) => return not_found(tera, stream, Some("No such collection")),
+                Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+            }
+
+            if let Err(error) = insert_puzzle_page(&mut context, user.as_ref(), &query) {
+                return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+            };
+
             let contents = match tera.render("index.html", &context){
                 Ok(contents) => contents,
                 Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
             };
 
-            let response = ResponseBuilder::new()
+            let response = response
                 .set_status_code(StatusCode::Ok)
                 .set_html_content(contents)
                 .build();
@@ -348,6 +399,26 @@ fn not_authorised(tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerE
     }
 }
 
+fn forbidden(tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerError> {
+    let mut context = tera::Context::new();
+    context.insert("status", "403");
+    context.insert("message", "Forbidden" );
+    let contents = match tera.render("error.html", &context){
+        Ok(contents) => contents,
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let response = ResponseBuilder::new()
+        .set_status_code(StatusCode::Forbidden)
+        .set_html_content(contents)
+        .build();
+
+    match stream.write_all(response.as_bytes()) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(HandlerError::new(stream, error))
+    }
+}
+
 fn crossword_js(_req: &HttpRequest, _: Arc<Tera>, stream: TcpStream)  -> Result<(), HandlerError> {
     static_file_handler(stream, "static/crossword.js","text/javascript")
 }
@@ -467,7 +538,7 @@ fn sign_up_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) ->
                 Err(error) => Err(HandlerError::new(stream, error))
             }
         },
-        HttpRequest::Post { status_line: _, headers: _, body } => {
+        HttpRequest::Post { status_line: _, headers, body } => {
             let body = match std::str::from_utf8(&body) {
                 Ok(s) => s,
                 Err(_) => {
@@ -502,46 +573,36 @@ fn sign_up_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) ->
                 None => return bad_request(tera, stream, "Missing repeat password field")
             };
 
-            if password != repeat_password {
-                return bad_request(tera, stream, "Passwords did not match")
-            }
-
-
-            let user_id = match add_user(username, password) {
+            let user_id = match register_user(username, password, repeat_password) {
                 Ok(x) => x,
-                Err(error) => {
-                    match error {
-                        rusqlite::Error::SqliteFailure(_, _) =>  return bad_request(tera, stream, "Username is not unique"),
-                        _ => return Err(HandlerError::new(stream, Error::new(ErrorKind::InvalidData, error))),
-                    }
-                }
+                Err(SignUpError::PasswordMismatch) => return bad_request(tera, stream, "Passwords did not match"),
+                Err(SignUpError::UsernameTaken) => return bad_request(tera, stream, "Username is not unique"),
+                Err(SignUpError::Database(error)) => return Err(HandlerError::new(stream, Error::new(ErrorKind::InvalidData, error))),
             };
 
-            let session = match set_session(user_id) {
+            let session = match create_session(user_id, headers.get("User-Agent").map(|x| x.as_str())) {
                 Ok(x) => x,
                 Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
             };
 
             let mut context = tera::Context::new();
-            let puzzle_data = match get_all_puzzle_db(){
-                Ok(puzzle_data) => puzzle_data,
-                Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+            let user = User { id: user_id, username: username.to_string(), is_admin: false };
+            if let Err(error) = insert_puzzle_page(&mut context, Some(&user), &PuzzleQuery::default()) {
+                return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
             };
             context.insert("logged_in", &true);
             context.insert("data", &format!("Welcome back {}",username));
-            context.insert("puzzles", &puzzle_data);
             let contents = match tera.render("index_content.html", &context){
                 Ok(contents) => contents,
                 Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
             };
 
-            let (session_cookie, username_cookie) = get_login_cookies(session, user_id);
+            let session_cookie = get_login_cookies(&session);
 
             let response = ResponseBuilder::new()
                 .set_status_code(StatusCode::Accepted)
                 .set_html_content(contents)
                 .add_cookie(session_cookie)
-                .add_cookie(username_cookie)
                 .build();
             
             match stream.write_all(response.as_bytes()) {
@@ -553,27 +614,34 @@ fn sign_up_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) ->
     }    
 }
 
-fn log_out_handler(_req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerError> {
+fn log_out_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerError> {
+
+    // only POST ends sessions, so a link or an image can't log anyone out.
+    let headers = match req {
+        HttpRequest::Get { .. } => return bad_request(tera, stream, "Unsupported http method"),
+        HttpRequest::Post { headers, .. } => headers,
+    };
+
+    if let Some(token) = get_session_token(headers) {
+        if let Err(error) = delete_session(&token) {
+            return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+        }
+    }
 
     let mut context = tera::Context::new();
-    let puzzle_data = match get_all_puzzle_db(){
-        Ok(puzzle_data) => puzzle_data,
-        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    if let Err(error) = insert_puzzle_page(&mut context, None, &PuzzleQuery::default()) {
+        return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
     };
     context.insert("logged_in", &false);
-    context.insert("puzzles", &puzzle_data);
     let contents = match tera.render("index_content.html", &context){
         Ok(contents) => contents,
         Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
     };
 
-    let (session_cookie, username_cookie) = get_login_cookies(-1, -1);
-
     let response = ResponseBuilder::new()
                 .set_status_code(StatusCode::Accepted)
                 .set_html_content(contents)
-                .add_cookie(session_cookie)
-                .add_cookie(username_cookie)
+                .add_cookie(get_logout_cookies())
                 .build();
 
     match stream.write_all(response.as_bytes()) {
@@ -582,6 +650,26 @@ fn log_out_handler(_req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) -
     }
 }
 
+fn log_out_everywhere_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+
+    let headers = match req {
+        HttpRequest::Get { .. } => return bad_request(tera, stream, "Unsupported http method"),
+        HttpRequest::Post { headers, .. } => headers,
+    };
+
+    let user = match is_authorised(headers) {
+        Ok(user) => user,
+        Err(_) => return not_authorised(tera, stream)
+    };
+
+    match delete_user_sessions(user.id) {
+        Ok(n) => info!("Ended {n} sessions for user {}", user.id),
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
+    log_out_handler(req, tera, stream)
+}
+
 fn log_in_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerError> {
 
     match req {
@@ -602,7 +690,7 @@ fn log_in_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) ->
                 Err(error) => Err(HandlerError::new(stream, error))
             }
         },
-        HttpRequest::Post { status_line: _, headers: _, body } => {
+        HttpRequest::Post { status_line: _, headers, body } => {
             let body = match std::str::from_utf8(&body) {
                 Ok(s) => s,
                 Err(_) => {
@@ -629,47 +717,40 @@ fn log_in_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) ->
                 None => return bad_request(tera, stream, "Missing password field")
             };
 
-            let sign_in = match get_user_password(username) {
-                Ok(s) => {
-                    info!("Successfully got password");
-                    s
-                },
-                Err(e) => {
-                    info!("{:?}",e);
-                    return bad_request(tera, stream, &format!("{} Incorrect password",username))
-                }
+            let sign_in = match check_credentials(username, password) {
+                Ok(s) => s,
+                Err(LogInError::UnknownUser) => return bad_request(tera, stream, &format!("{} Incorrect password",username)),
+                Err(LogInError::WrongPassword) => return bad_request(tera, stream, &format!("Wrong password")),
             };
 
-            if let Err(_) = validate_password(password, &sign_in.password) {
-                return bad_request(tera, stream, &format!("Wrong password"))
-            }
-
-            let session = match set_session(sign_in.id) {
+            let session = match create_session(sign_in.id, headers.get("User-Agent").map(|x| x.as_str())) {
                 Ok(x) => x,
                 Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
             };
 
 
             let mut context = tera::Context::new();
-            let puzzle_data = match get_all_puzzle_db(){
-                Ok(puzzle_data) => puzzle_data,
+            let user = match get_user(username) {
+                Ok(user) => user,
                 Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
             };
+            if let Err(error) = insert_puzzle_page(&mut context, Some(&user), &PuzzleQuery::default()) {
+                return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+            };
             context.insert("data", &format!("Welcome back {}",username));
             context.insert("logged_in", &true);
-            context.insert("puzzles", &puzzle_data);
+            context.insert("is_admin", &user.is_admin);
             let contents = match tera.render("index_content.html", &context){
                 Ok(contents) => contents,
                 Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
             };
 
-            let (session_cookie, username_cookie) = get_login_cookies(session, sign_in.id);
+            let session_cookie = get_login_cookies(&session);
 
             let response = ResponseBuilder::new()
                 .set_status_code(StatusCode::Accepted)
                 .set_html_content(contents)
                 .add_cookie(session_cookie)
-                .add_cookie(username_cookie)
                 .build();
             
             match stream.write_all(response.as_bytes()) {
@@ -680,6 +761,214 @@ fn log_in_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) ->
     }    
 }
 
+enum LogInError {
+    UnknownUser,
+    WrongPassword,
+}
+
+/// Checks a username and password, upgrading legacy password hashes on success.
+fn check_credentials(username: &str, password: &str) -> Result<SignIn, LogInError> {
+    let sign_in = match get_user_password(username) {
+        Ok(s) => {
+            info!("Successfully got password");
+            s
+        },
+        Err(e) => {
+            info!("{:?}",e);
+            return Err(LogInError::UnknownUser)
+        }
+    };
+
+    if validate_password(password, &sign_in.password).is_err() {
+        return Err(LogInError::WrongPassword)
+    }
+
+    if needs_rehash(&sign_in.password) {
+        info!("Upgrading legacy password hash for user {}", sign_in.id);
+        if let Err(e) = update_password(sign_in.id, password) {
+            warn!("Failed to upgrade legacy password hash: {e}");
+        }
+    }
+    Ok(sign_in)
+}
+
+enum SignUpError {
+    PasswordMismatch,
+    UsernameTaken,
+    Database(rusqlite::Error),
+}
+
+fn register_user(username: &str, password: &str, repeat_password: &str) -> Result<i64, SignUpError> {
+    if password != repeat_password {
+        return Err(SignUpError::PasswordMismatch)
+    }
+
+    match add_user(username, password) {
+        Ok(x) => Ok(x),
+        Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ErrorCode::ConstraintViolation => Err(SignUpError::UsernameTaken),
+        Err(error) => Err(SignUpError::Database(error)),
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct ApiError<'a> {
+    error: &'a str,
+    message: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct InvalidCrossword<'a> {
+    error: &'a str,
+    message: &'a str,
+    errors: &'a [ValidationError],
+}
+
+/// Rejects an upload with everything that is wrong with its crossword.
+fn invalid_crossword(tera: Arc<Tera>, stream: TcpStream, errors: &[ValidationError]) -> Result<(), HandlerError> {
+    let data = InvalidCrossword { error: "invalid_crossword", message: "The crossword is not valid", errors };
+    json_response(tera, stream, StatusCode::UnprocessableContent, &data)
+}
+
+/// Sends a JSON error with a machine-readable code, e.g. `bad_credentials`.
+fn json_error(tera: Arc<Tera>, stream: TcpStream, status_code: StatusCode, error: &str, message: &str) -> Result<(), HandlerError> {
+    json_response(tera, stream, status_code, &ApiError { error, message })
+}
+
+fn json_with_cookie<T: Serialize>(tera: Arc<Tera>, mut stream: TcpStream, status_code: StatusCode, data: &T, cookie: SetCookie<String>) -> Result<(), HandlerError> {
+    let contents = match serde_json::to_string(data){
+        Ok(s) => s,
+        Err(error) => {
+            error!("Unsuccessfully serialised response, {}", error);
+            return server_error(tera, stream)
+        },
+    };
+
+    let response = ResponseBuilder::new()
+        .set_status_code(status_code)
+        .set_json_content(contents)
+        .add_cookie(cookie)
+        .build();
+
+    match stream.write_all(response.as_bytes()) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(HandlerError::new(stream, error))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct LogInBody {
+    username: String,
+    password: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct SignUpBody {
+    username: String,
+    password: String,
+    repeat_password: String,
+}
+
+fn api_log_in_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+
+    let (headers, body) = match req {
+        HttpRequest::Get { .. } => return json_error(tera, stream, StatusCode::MethodNotAllowed, "method_not_allowed", "Use POST to log in"),
+        HttpRequest::Post { headers, body, .. } => (headers, body),
+    };
+
+    let request_data: LogInBody = match serde_json::from_slice(body) {
+        Ok(s) => s,
+        Err(e) => return json_error(tera, stream, StatusCode::BadRequest, "invalid_body", &e.to_string()),
+    };
+
+    let sign_in = match check_credentials(&request_data.username, &request_data.password) {
+        Ok(s) => s,
+        Err(_) => return json_error(tera, stream, StatusCode::Unauthorized, "bad_credentials", "Incorrect username or password"),
+    };
+
+    let session = match create_session(sign_in.id, headers.get("User-Agent").map(|x| x.as_str())) {
+        Ok(x) => x,
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let user = match get_user(&request_data.username) {
+        Ok(user) => user,
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+    json_with_cookie(tera, stream, StatusCode::Ok, &user, get_login_cookies(&session))
+}
+
+fn api_sign_up_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+
+    let (headers, body) = match req {
+        HttpRequest::Get { .. } => return json_error(tera, stream, StatusCode::MethodNotAllowed, "method_not_allowed", "Use POST to sign up"),
+        HttpRequest::Post { headers, body, .. } => (headers, body),
+    };
+
+    let request_data: SignUpBody = match serde_json::from_slice(body) {
+        Ok(s) => s,
+        Err(e) => return json_error(tera, stream, StatusCode::BadRequest, "invalid_body", &e.to_string()),
+    };
+
+    if request_data.username.is_empty() {
+        return json_error(tera, stream, StatusCode::BadRequest, "empty_username", "Username must not be empty")
+    }
+    if request_data.password.is_empty() {
+        return json_error(tera, stream, StatusCode::BadRequest, "empty_password", "Password must not be empty")
+    }
+
+    let user_id = match register_user(&request_data.username, &request_data.password, &request_data.repeat_password) {
+        Ok(x) => x,
+        Err(SignUpError::PasswordMismatch) => return json_error(tera, stream, StatusCode::BadRequest, "password_mismatch", "Passwords did not match"),
+        Err(SignUpError::UsernameTaken) => return json_error(tera, stream, StatusCode::Conflict, "username_taken", "Username is not unique"),
+        Err(SignUpError::Database(error)) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error)))),
+    };
+
+    let session = match create_session(user_id, headers.get("User-Agent").map(|x| x.as_str())) {
+        Ok(x) => x,
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let user = User { id: user_id, username: request_data.username, is_admin: false };
+    json_with_cookie(tera, stream, StatusCode::Created, &user, get_login_cookies(&session))
+}
+
+fn api_log_out_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+
+    let headers = match req {
+        HttpRequest::Get { .. } => return json_error(tera, stream, StatusCode::MethodNotAllowed, "method_not_allowed", "Use POST to log out"),
+        HttpRequest::Post { headers, .. } => headers,
+    };
+
+    let token = match get_session_token(headers) {
+        Some(token) => token,
+        None => return json_error(tera, stream, StatusCode::Unauthorized, "not_authenticated", "No session to log out of"),
+    };
+
+    if let Err(error) = delete_session(&token) {
+        return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
+    #[derive(Serialize)]
+    struct LoggedOut {
+        logged_out: bool
+    }
+
+    json_with_cookie(tera, stream, StatusCode::Ok, &LoggedOut { logged_out: true }, get_logout_cookies())
+}
+
+fn api_me_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+
+    let headers = match req {
+        HttpRequest::Get { headers, .. } => headers,
+        HttpRequest::Post { .. } => return json_error(tera, stream, StatusCode::MethodNotAllowed, "method_not_allowed", "Use GET to see who is logged in"),
+    };
+
+    match is_authorised_for(headers, TokenScope::Read) {
+        Ok(user) => json_response(tera, stream, StatusCode::Ok, &user),
+        Err(e) => json_error(tera, stream, StatusCode::Unauthorized, "not_authenticated", &e),
+    }
+}
+
 fn client_test_handler(_: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerError> {
 
     let mut context = tera::Context::new();
@@ -731,11 +1020,15 @@ fn add_client_test_handler(_: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStre
 
 fn puzzle_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerError> {
     // acquire the html of the page.
-    let status_line = match req {
-        HttpRequest::Get { status_line, .. } => status_line,
-        HttpRequest::Post { status_line, .. } => status_line,
+    let (status_line, headers) = match req {
+        HttpRequest::Get { status_line, headers } => (status_line, headers),
+        HttpRequest::Post { status_line, headers, .. } => (status_line, headers),
     };
 
+    if prefers_plain_text(headers) {
+        return puzzle_text_handler(req, tera, stream)
+    }
+
     let path_info = Regex::new(r"(?<num>\d+)").expect("Invalid regular expression");
     let caps = match path_info.captures(&status_line.route) {
         Some(caps) => caps,
@@ -745,9 +1038,9 @@ fn puzzle_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) ->
 
     let mut context = tera::Context::new();
     context.insert("src", &format!("/puzzle/{puzzle_num}"));
-    let data = match get_puzzle_db(&puzzle_num) {
-        Ok(data) => data,
-        Err( error) if error == rusqlite::Error::QueryReturnedNoRows => {
+    let data = match find_viewable_puzzle(puzzle_num, headers) {
+        Ok(Some(data)) => data,
+        Ok(None) => {
             return not_found(tera, stream, Some(&format!("No puzzle with ID {puzzle_num}")))
         },
         Err(error) => {
@@ -756,6 +1049,7 @@ fn puzzle_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) ->
     };
 
     context.insert("name", &format!("{}",data.name));
+    context.insert("metadata", &data.metadata);
     let contents = match tera.render("crossword.html", &context){
         Ok(contents) => contents,
         Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
@@ -772,11 +1066,11 @@ fn puzzle_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) ->
     }
 }
 
-fn puzzle_handler_data(req: &HttpRequest, _tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError>  {
+fn puzzle_handler_data(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError>  {
 
-    let status_line = match req {
-        HttpRequest::Get { status_line, .. } => status_line,
-        HttpRequest::Post { status_line, .. } => status_line,
+    let (status_line, headers) = match req {
+        HttpRequest::Get { status_line, headers } => (status_line, headers),
+        HttpRequest::Post { status_line, headers, .. } => (status_line, headers),
     };
 
     let path_info = Regex::new(r"(?<num>\d+)/data").expect("Invalid regular expression");
@@ -787,6 +1081,12 @@ fn puzzle_handler_data(req: &HttpRequest, _tera: Arc<Tera>, stream: TcpStream) -
 
     let puzzle_num = caps["num"].parse().unwrap();
 
+    match find_viewable_puzzle(puzzle_num, headers) {
+        Ok(Some(_)) => (),
+        Ok(None) => return not_found(tera, stream, Some(&format!("Can't find puzzle {puzzle_num}"))),
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
     match PUZZLEPOOL.lock(){
         Ok(mut mut_guard) => return mut_guard.get_grid_data(puzzle_num , stream),
         Err(e) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}", e))))
@@ -800,8 +1100,9 @@ fn puzzle_soft_delete_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: Tc
         HttpRequest::Post { status_line, headers, .. } => (status_line, headers),
     };
 
-    if let Err(_) =  is_authorised(headers) {
-        return not_authorised(tera, stream)
+    let user = match is_authorised_for(headers, TokenScope::Delete) {
+        Ok(user) => user,
+        Err(_) => return not_authorised(tera, stream)
     };
 
 
@@ -813,6 +1114,15 @@ fn puzzle_soft_delete_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: Tc
 
     let puzzle_num = caps["num"].parse().unwrap();
 
+    match get_puzzle_db(&puzzle_num) {
+        Ok(data) if data.can_edit(&user) => (),
+        Ok(data) if data.can_view(Some(&user)) => return forbidden(tera, stream),
+        Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return not_found(tera, stream, Some(&format!("No puzzle with ID {puzzle_num}")))
+        },
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
     if let Err(error) = soft_delete_puzzle(puzzle_num){
         return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
     };
@@ -831,9 +1141,9 @@ fn puzzle_soft_delete_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: Tc
 
 fn puzzle_handler_live(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerError> {
 
-    let status_line = match req {
-        HttpRequest::Get { status_line, .. } => status_line,
-        HttpRequest::Post { status_line, .. } => status_line,
+    let (status_line, headers) = match req {
+        HttpRequest::Get { status_line, headers } => (status_line, headers),
+        HttpRequest::Post { status_line, headers, .. } => (status_line, headers),
     };
 
     let path_info = Regex::new(r"(?<num>\d+)/live").expect("Invalid regular expression");
@@ -843,6 +1153,13 @@ fn puzzle_handler_live(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream
     };
     let puzzle_num = caps["num"].parse().unwrap();
 
+    match find_viewable_puzzle(puzzle_num, headers) {
+        Ok(Some(_)) => (),
+        Ok(None) => return not_found(tera, stream, Some(&format!("Can't find puzzle {puzzle_num}"))),
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+    let user_id = is_authorised_for(headers, TokenScope::Read).ok().map(|user| user.id);
+
     let handshake = match websocket_handshake(req){
         Ok(handshake) => handshake,
         Err(_) => return bad_request(tera, stream, "malformed handshake")
@@ -855,7 +1172,7 @@ fn puzzle_handler_live(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream
 
     match PUZZLEPOOL.lock(){
         Ok(mut mut_guard) => {
-            match mut_guard.connect_client(puzzle_num, stream) {
+            match mut_guard.connect_client(puzzle_num, stream, user_id) {
                 Ok(stream) => {
                     return Ok(stream)
                 }
@@ -876,7 +1193,11 @@ fn puzzle_handler_live(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream
 #[derive(Serialize, Deserialize, Debug)]
 struct AddPuzzleBody{
     name: String,
-    crossword: Crossword
+    crossword: Crossword,
+    #[serde(default)]
+    visibility: Visibility,
+    /// Kept on the server for checking and revealing, never sent to solvers.
+    solution: Option<SolutionInput>,
 }
 
 fn puzzle_add_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerError> {
@@ -885,8 +1206,9 @@ fn puzzle_add_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream)
         HttpRequest::Get {  .. } => return bad_request(tera, stream, "Unsupported http method"),
         HttpRequest::Post { status_line: _, headers , body} => {
 
-            if let Err(_) =  is_authorised(headers) {
-                return not_authorised(tera, stream)
+            let user = match is_authorised_for(headers, TokenScope::Upload) {
+                Ok(user) => user,
+                Err(_) => return not_authorised(tera, stream)
             };
 
             let body = match std::str::from_utf8(&body) {
@@ -903,11 +1225,25 @@ fn puzzle_add_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream)
                 },
             };
 
-            let id = match create_new_puzzle(&request_data.name, &request_data.crossword) {
+            if let Err(errors) = request_data.crossword.validate() {
+                return invalid_crossword(tera, stream, &errors)
+            }
+
+            let solution = match request_data.solution.as_ref().map(|input| request_data.crossword.solution(input)) {
+                Some(Ok(solution)) => Some(solution),
+                Some(Err(errors)) => return invalid_crossword(tera, stream, &errors),
+                None => None,
+            };
+
+            let id = match create_new_puzzle(&request_data.name, &request_data.crossword, user.id, request_data.visibility) {
                 Ok(id) => id,
                 Err(error) => return Err(HandlerError::new(stream, error))
             };
 
+            if let Err(error) = set_solution(&id, solution.as_ref()) {
+                return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("Database error: {}", error))))
+            }
+
 
             let puzzle_info = match get_puzzle_db(&id) {
                 Ok(data) => data,
@@ -939,67 +1275,1432 @@ fn puzzle_add_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream)
 
 }
 
-fn puzzle_list_handler(_req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerError> {
-    let puzzle_data = match get_all_puzzle_db(){
-        Ok(puzzle_data) => puzzle_data,
-        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
-    };
+/// A puzzle read from a file in another program's format.
+struct ImportedPuzzle {
+    title: Option<String>,
+    crossword: Crossword,
+    solution: Option<Solution>,
+}
 
-    let contents = match serde_json::to_string(&puzzle_data){
-        Ok(s) => s,
-        Err(error) => {
-            error!("Unsuccessfully serialised puzzle data, {}", error);
-            return server_error(tera, stream)
-        },
-    };
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ImportFormat {
+    Puz,
+    Ipuz,
+    Jpz,
+    ClueList,
+}
 
-    let response = ResponseBuilder::new()
-        .set_json_content(contents)
-        .set_status_code(StatusCode::Ok)
-        .build();
+impl ImportFormat {
+    fn from_extension(name: &str) -> Option<Self> {
+        match name.rsplit('.').next()?.to_ascii_lowercase().as_str() {
+            "puz" => Some(Self::Puz),
+            "ipuz" => Some(Self::Ipuz),
+            "jpz" => Some(Self::Jpz),
+            "txt" => Some(Self::ClueList),
+            _ => None,
+        }
+    }
 
-    match stream.write_all(response.as_bytes()) {
-        Ok(_) => return Ok(()),
-        Err(error) => return Err(HandlerError::new(stream, error))
+    fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+        match mime.as_str() {
+            "application/x-crossword" | "application/x-across-lite" => Some(Self::Puz),
+            "application/x-ipuz" | "application/ipuz" | "application/ipuz+json" => Some(Self::Ipuz),
+            "application/x-jpz" | "application/x-crossword-compiler" => Some(Self::Jpz),
+            "text/plain" => Some(Self::ClueList),
+            _ => None,
+        }
     }
 
+    /// Works out the format from the request, falling back to looking at the
+    /// file itself: first the `Content-Type` header, then the extension of a
+    /// `filename` in the query string.
+    fn detect(headers: &HashMap<String, String>, query: &HashMap<String, String>, data: &[u8]) -> Option<Self> {
+        headers.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("Content-Type"))
+            .and_then(|(_, value)| Self::from_content_type(value))
+            .or_else(|| query.get("filename").and_then(|name| Self::from_extension(name)))
+            .or_else(|| match (Puz::is_puz(data), Ipuz::is_ipuz(data), Jpz::is_jpz(data), ClueList::is_clue_list(data)) {
+                (true, _, _, _) => Some(Self::Puz),
+                (_, true, _, _) => Some(Self::Ipuz),
+                (_, _, true, _) => Some(Self::Jpz),
+                (_, _, _, true) => Some(Self::ClueList),
+                _ => None,
+            })
+    }
 }
 
-#[derive(Debug)]
-struct PuzzlePool {
-    pool: HashMap<i64, Arc<Mutex<PuzzleChannel>>>,
-    tera: Arc<Tera>
+/// Reads a puzzle file in the given format.
+fn read_puzzle_file(data: &[u8], format: ImportFormat) -> Result<ImportedPuzzle, String> {
+    match format {
+        ImportFormat::Puz => {
+            let puz = Puz::parse(data).map_err(|e| e.to_string())?;
+            let (crossword, solution) = Crossword::from_puz(&puz).map_err(|e| e.to_string())?;
+            let title = Some(puz.title).filter(|title| !title.trim().is_empty());
+            Ok(ImportedPuzzle { title, crossword, solution })
+        },
+        ImportFormat::Ipuz => {
+            let text = std::str::from_utf8(data).map_err(|_| "The file isn't valid UTF-8".to_string())?;
+            let ipuz = Ipuz::parse(text).map_err(|e| e.to_string())?;
+            let (crossword, solution) = Crossword::from_ipuz(&ipuz).map_err(|e| e.to_string())?;
+            let title = ipuz.title().map(str::to_string);
+            Ok(ImportedPuzzle { title, crossword, solution })
+        },
+        ImportFormat::Jpz => {
+            let jpz = Jpz::parse(data).map_err(|e| e.to_string())?;
+            let (crossword, solution) = Crossword::from_jpz(&jpz).map_err(|e| e.to_string())?;
+            Ok(ImportedPuzzle { title: jpz.title(), crossword, solution })
+        },
+        ImportFormat::ClueList => {
+            let text = std::str::from_utf8(data).map_err(|_| "The file isn't valid UTF-8".to_string())?;
+            let list = ClueList::parse(text).map_err(|e| e.to_string())?;
+            let (crossword, solution) = Crossword::from_clue_list(&list).map_err(|e| e.to_string())?;
+            let title = list.title().map(str::to_string);
+            Ok(ImportedPuzzle { title, crossword, solution })
+        },
+    }
 }
 
-impl PuzzlePool {
-    fn new() -> Self {
-        let pool = HashMap::new();
+/// Adds a puzzle from a file, e.g. an Across Lite `.puz`, an `.ipuz`, a
+/// Crossword Compiler `.jpz` or a plain-text clue list. The body is the file itself; `name`, `visibility` and `filename` can be given
+/// in the query string.
+fn puzzle_import_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    let (status_line, headers, body) = match req {
+        HttpRequest::Get { .. } => return bad_request(tera, stream, "Unsupported http method"),
+        HttpRequest::Post { status_line, headers, body } => (status_line, headers, body),
+    };
 
-        let tera = Tera::new("templates/**/*").unwrap_or_else(|err| {
-            error!("Sever failed to load templates: {}", err);
-            std::process::exit(1);
-        });
+    let user = match is_authorised_for(headers, TokenScope::Upload) {
+        Ok(user) => user,
+        Err(_) => return not_authorised(tera, stream)
+    };
 
-        Self { pool, tera: Arc::new(tera) }
+    let query = status_line.query();
+    let visibility = match query.get("visibility").map(|x| x.parse::<Visibility>()) {
+        Some(Ok(visibility)) => visibility,
+        Some(Err(e)) => return json_error(tera, stream, StatusCode::BadRequest, "invalid_request", &e),
+        None => Visibility::default(),
+    };
+
+    let format = match ImportFormat::detect(headers, &query, body) {
+        Some(format) => format,
+        None => return json_error(tera, stream, StatusCode::BadRequest, "invalid_file", "The file isn't in a format that can be imported"),
+    };
+
+    let imported = match read_puzzle_file(body, format) {
+        Ok(imported) => imported,
+        Err(e) => return json_error(tera, stream, StatusCode::BadRequest, "invalid_file", &e),
+    };
+
+    if let Err(errors) = imported.crossword.validate() {
+        return invalid_crossword(tera, stream, &errors)
     }
 
-    fn connect_client(&mut self, puzzle_num: i64, stream: TcpStream) -> Result<(), HandlerError> {
+    let name = query.get("name")
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or(imported.title)
+        .unwrap_or_else(|| "Imported puzzle".to_string());
 
+    let id = match create_new_puzzle(&name, &imported.crossword, user.id, visibility) {
+        Ok(id) => id,
+        Err(error) => return Err(HandlerError::new(stream, error))
+    };
 
-        match self.pool.get(&puzzle_num) {
-            Some(puzzle_channel) => {
-                info!("Connecting websocket client to existing puzzle.");
-                route_stream_to_puzzle(puzzle_channel.clone(), stream, self.tera.clone())
-            }
-            None => {
-                info!("No channel found to route websocket client. Creating a new channel");
-                match PuzzleChannel::new(puzzle_num.clone()){
-                    Ok(channel) => {
-                        match channel {
-                            Some(channel) => {
+    if let Err(error) = set_solution(&id, imported.solution.as_ref()) {
+        return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("Database error: {}", error))))
+    }
+
+    match get_puzzle_db(&id) {
+        Ok(data) => json_response(tera, stream, StatusCode::Ok, &data),
+        Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("Database error: {}", error))))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ExportFormat {
+    Puz,
+    Ipuz,
+    Jpz,
+    ClueList,
+}
+
+fn puzzle_export_puz_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    puzzle_export_handler(req, tera, stream, ExportFormat::Puz)
+}
+
+fn puzzle_export_ipuz_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    puzzle_export_handler(req, tera, stream, ExportFormat::Ipuz)
+}
+
+fn puzzle_export_jpz_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    puzzle_export_handler(req, tera, stream, ExportFormat::Jpz)
+}
+
+fn puzzle_export_clue_list_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    puzzle_export_handler(req, tera, stream, ExportFormat::ClueList)
+}
+
+/// A file name for a download, made from the puzzle's name.
+fn export_file_name(name: &str, extension: &str) -> String {
+    let stem: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    let stem = stem.trim_matches('-');
+    match stem.is_empty() {
+        true => format!("puzzle.{extension}"),
+        false => format!("{stem}.{extension}"),
+    }
+}
+
+/// Sends a puzzle as it is now in another program's format. The solution is
+/// only included for people who can edit the puzzle.
+fn puzzle_export_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream, format: ExportFormat) -> Result<(), HandlerError> {
+    let (status_line, headers) = match req {
+        HttpRequest::Get { status_line, headers } => (status_line, headers),
+        HttpRequest::Post { .. } => return bad_request(tera, stream, "Unsupported http method"),
+    };
+
+    let path_info = Regex::new(r"(?<num>\d+)/export").expect("Invalid regular expression");
+    let caps = match path_info.captures(&status_line.route) {
+        Some(caps) => caps,
+        None => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, "api route doesn't match the regex used by the route handler")))
+    };
+    let puzzle_num: i64 = caps["num"].parse().unwrap();
+
+    let data = match find_viewable_puzzle(puzzle_num, headers) {
+        Ok(Some(data)) => data,
+        Ok(None) => return not_found(tera, stream, Some(&format!("Can't find puzzle {puzzle_num}"))),
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let crossword = match PUZZLEPOOL.lock() {
+        Ok(guard) => guard.current_crossword(puzzle_num),
+        Err(e) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}", e))))
+    };
+    let crossword = match crossword {
+        Ok(Some(crossword)) => crossword,
+        Ok(None) => return not_found(tera, stream, Some(&format!("Can't find puzzle {puzzle_num}"))),
+        Err(error) => return Err(HandlerError::new(stream, error))
+    };
+
+    let user = is_authorised_for(headers, TokenScope::Read).ok();
+    let solution = match user.filter(|user| data.can_edit(user)) {
+        Some(_) => match get_solution(&puzzle_num) {
+            Ok(solution) => solution,
+            Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+        },
+        None => None,
+    };
+
+    let exported = match format {
+        ExportFormat::Puz => crossword.to_puz(&data.name, solution.as_ref()).map_err(anyhow::Error::from)
+            .and_then(|x| x.to_bytes().map_err(anyhow::Error::from))
+            .map(|x| (x, "application/x-crossword", "puz")),
+        ExportFormat::Ipuz => crossword.to_ipuz(&data.name, solution.as_ref()).map_err(anyhow::Error::from)
+            .map(|x| (x.to_json().into_bytes(), "application/x-ipuz", "ipuz")),
+        ExportFormat::Jpz => crossword.to_jpz(&data.name, solution.as_ref()).map_err(anyhow::Error::from)
+            .map(|x| (x.to_xml().into_bytes(), "application/x-jpz", "jpz")),
+        ExportFormat::ClueList => crossword.to_clue_list(&data.name, solution.as_ref()).map_err(anyhow::Error::from)
+            .map(|x| (x.to_string().into_bytes(), "text/plain; charset=utf-8", "txt")),
+    };
+    let (contents, content_type, extension) = match exported {
+        Ok(exported) => exported,
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let disposition = HashMap::from([
+        ("Content-Disposition".to_string(), format!("attachment; filename=\"{}\"", export_file_name(&data.name, extension)))
+    ]);
+    let response = ResponseBuilder::new()
+        .set_status_code(StatusCode::Ok)
+        .addHeaders(&disposition)
+        .build_binary(&contents, content_type);
+
+    match stream.write_all(&response) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(HandlerError::new(stream, error))
+    }
+}
+
+/// Lays a puzzle out for printing. `paper` is `a4` or `letter` and `letters`
+/// is `none`, `fills` or `solution`; only people who can edit the puzzle can
+/// print the solution.
+fn puzzle_print_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerError> {
+    let (status_line, headers) = match req {
+        HttpRequest::Get { status_line, headers } => (status_line, headers),
+        HttpRequest::Post { .. } => return bad_request(tera, stream, "Unsupported http method"),
+    };
+
+    let path_info = Regex::new(r"(?<num>\d+)/print").expect("Invalid regular expression");
+    let caps = match path_info.captures(&status_line.route) {
+        Some(caps) => caps,
+        None => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, "api route doesn't match the regex used by the route handler")))
+    };
+    let puzzle_num: i64 = caps["num"].parse().unwrap();
+
+    let query = status_line.query();
+    let paper = match query.get("paper").map(|x| x.parse::<PaperSize>()) {
+        Some(Ok(paper)) => paper,
+        Some(Err(e)) => return json_error(tera, stream, StatusCode::BadRequest, "invalid_request", &e),
+        None => PaperSize::default(),
+    };
+    let letters = match query.get("letters").map(|x| x.parse::<PrintedLetters>()) {
+        Some(Ok(letters)) => letters,
+        Some(Err(e)) => return json_error(tera, stream, StatusCode::BadRequest, "invalid_request", &e),
+        None => PrintedLetters::default(),
+    };
+
+    let data = match find_viewable_puzzle(puzzle_num, headers) {
+        Ok(Some(data)) => data,
+        Ok(None) => return not_found(tera, stream, Some(&format!("Can't find puzzle {puzzle_num}"))),
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let solution = match letters {
+        PrintedLetters::Solution => {
+            let user = is_authorised_for(headers, TokenScope::Read).ok();
+            if !user.is_some_and(|user| data.can_edit(&user)) {
+                return json_error(tera, stream, StatusCode::Forbidden, "forbidden", "Only people who can edit the puzzle can print its solution")
+            }
+            match get_solution(&puzzle_num) {
+                Ok(Some(solution)) => Some(solution),
+                Ok(None) => return json_error(tera, stream, StatusCode::NotFound, "no_solution", "The puzzle doesn't have a solution"),
+                Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+            }
+        },
+        _ => None,
+    };
+
+    let crossword = match PUZZLEPOOL.lock() {
+        Ok(guard) => guard.current_crossword(puzzle_num),
+        Err(e) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}", e))))
+    };
+    let crossword = match crossword {
+        Ok(Some(crossword)) => crossword,
+        Ok(None) => return not_found(tera, stream, Some(&format!("Can't find puzzle {puzzle_num}"))),
+        Err(error) => return Err(HandlerError::new(stream, error))
+    };
+
+    let pdf = match crossword.to_pdf(&data.name, &PdfOptions { paper, letters }, solution.as_ref()) {
+        Ok(pdf) => pdf,
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+    let disposition = HashMap::from([
+        ("Content-Disposition".to_string(), format!("inline; filename=\"{}\"", export_file_name(&data.name, "pdf")))
+    ]);
+    let response = ResponseBuilder::new()
+        .set_status_code(StatusCode::Ok)
+        .addHeaders(&disposition)
+        .build_binary(&pdf, "application/pdf");
+
+    match stream.write_all(&response) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(HandlerError::new(stream, error))
+    }
+}
+
+/// Whether the request asks for plain text rather than a web page, e.g.
+/// `curl -H "Accept: text/plain"`.
+fn prefers_plain_text(headers: &HashMap<String, String>) -> bool {
+    headers.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("Accept"))
+        .is_some_and(|(_, accept)| accept.contains("text/plain") && !accept.contains("text/html"))
+}
+
+/// Writes a puzzle as it is now as plain text, for reading in a terminal.
+/// `colour` and `clues` can be turned on or off in the query string.
+fn puzzle_text_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerError> {
+    let (status_line, headers) = match req {
+        HttpRequest::Get { status_line, headers } => (status_line, headers),
+        HttpRequest::Post { .. } => return bad_request(tera, stream, "Unsupported http method"),
+    };
+
+    let path_info = Regex::new(r"(?<num>\d+)").expect("Invalid regular expression");
+    let caps = match path_info.captures(&status_line.route) {
+        Some(caps) => caps,
+        None => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, "api route doesn't match the regex used by the route handler")))
+    };
+    let puzzle_num: i64 = caps["num"].parse().unwrap();
+
+    let query = status_line.query();
+    let options = match (query_flag(&query, "colour", false), query_flag(&query, "clues", true)) {
+        (Ok(colour), Ok(clues)) => TextOptions { colour, clues },
+        (Err(e), _) | (_, Err(e)) => return json_error(tera, stream, StatusCode::BadRequest, "invalid_request", &e),
+    };
+
+    let data = match find_viewable_puzzle(puzzle_num, headers) {
+        Ok(Some(data)) => data,
+        Ok(None) => return not_found(tera, stream, Some(&format!("Can't find puzzle {puzzle_num}"))),
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let crossword = match PUZZLEPOOL.lock() {
+        Ok(guard) => guard.current_crossword(puzzle_num),
+        Err(e) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}", e))))
+    };
+    let crossword = match crossword {
+        Ok(Some(crossword)) => crossword,
+        Ok(None) => return not_found(tera, stream, Some(&format!("Can't find puzzle {puzzle_num}"))),
+        Err(error) => return Err(HandlerError::new(stream, error))
+    };
+
+    let mut contents = printable(&data.name);
+    if let Some(author) = &crossword.metadata().author {
+        contents.push_str(&format!(" by {}", printable(author)));
+    }
+    contents.push_str("\n\n");
+    match crossword.to_text(&options) {
+        Ok(text) => contents.push_str(&text),
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let response = ResponseBuilder::new()
+        .set_status_code(StatusCode::Ok)
+        .build_binary(contents.as_bytes(), "text/plain; charset=utf-8");
+
+    match stream.write_all(&response) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(HandlerError::new(stream, error))
+    }
+}
+
+/// Reads an on/off option from the query string.
+fn query_flag(query: &HashMap<String, String>, name: &str, default: bool) -> Result<bool, String> {
+    match query.get(name).map(|x| x.as_str()) {
+        None => Ok(default),
+        Some("true") | Some("1") => Ok(true),
+        Some("false") | Some("0") => Ok(false),
+        Some(other) => Err(format!("{name} should be true or false, not {other}")),
+    }
+}
+
+/// Reads the options for drawing a puzzle from the query string: `thumbnail`,
+/// `fills`, `numbers`, `clues`, `highlight` (a clue id) and `size` (the
+/// width of a square in pixels).
+fn svg_options(query: &HashMap<String, String>, crossword: &Crossword) -> Result<SvgOptions, String> {
+    let defaults = match query_flag(query, "thumbnail", false)? {
+        true => SvgOptions::thumbnail(),
+        false => SvgOptions::default(),
+    };
+    let cell_size = match query.get("size").map(|x| x.parse::<u32>()) {
+        Some(Ok(size)) if (4..=96).contains(&size) => size,
+        Some(_) => return Err("size should be a number of pixels from 4 to 96".to_string()),
+        None => defaults.cell_size,
+    };
+    let highlight = query.get("highlight").cloned();
+    if let Some(id) = &highlight {
+        if !crossword.hints().any(|(clue, _)| clue == id) {
+            return Err(format!("There's no clue {id}"))
+        }
+    }
+    Ok(SvgOptions {
+        cell_size,
+        fills: query_flag(query, "fills", defaults.fills)?,
+        numbers: query_flag(query, "numbers", defaults.numbers)?,
+        highlight,
+        clues: query_flag(query, "clues", defaults.clues)?,
+    })
+}
+
+/// Draws a puzzle as it is now, as an SVG picture.
+fn puzzle_svg_handler(req: &HttpRequest, tera: Arc<Tera>, mut stream: TcpStream) -> Result<(), HandlerError> {
+    let (status_line, headers) = match req {
+        HttpRequest::Get { status_line, headers } => (status_line, headers),
+        HttpRequest::Post { .. } => return bad_request(tera, stream, "Unsupported http method"),
+    };
+
+    let path_info = Regex::new(r"(?<num>\d+)\.svg").expect("Invalid regular expression");
+    let caps = match path_info.captures(&status_line.route) {
+        Some(caps) => caps,
+        None => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, "api route doesn't match the regex used by the route handler")))
+    };
+    let puzzle_num: i64 = caps["num"].parse().unwrap();
+
+    match find_viewable_puzzle(puzzle_num, headers) {
+        Ok(Some(_)) => (),
+        Ok(None) => return not_found(tera, stream, Some(&format!("Can't find puzzle {puzzle_num}"))),
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
+    let crossword = match PUZZLEPOOL.lock() {
+        Ok(guard) => guard.current_crossword(puzzle_num),
+        Err(e) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}", e))))
+    };
+    let crossword = match crossword {
+        Ok(Some(crossword)) => crossword,
+        Ok(None) => return not_found(tera, stream, Some(&format!("Can't find puzzle {puzzle_num}"))),
+        Err(error) => return Err(HandlerError::new(stream, error))
+    };
+
+    let options = match svg_options(&status_line.query(), &crossword) {
+        Ok(options) => options,
+        Err(e) => return json_error(tera, stream, StatusCode::BadRequest, "invalid_request", &e),
+    };
+
+    let svg = match crossword.to_svg(&options) {
+        Ok(svg) => svg,
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let response = ResponseBuilder::new()
+        .set_status_code(StatusCode::Ok)
+        .build_binary(svg.as_bytes(), "image/svg+xml");
+
+    match stream.write_all(&response) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(HandlerError::new(stream, error))
+    }
+}
+
+fn puzzle_list_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    let (status_line, headers) = match req {
+        HttpRequest::Get { status_line, headers } => (status_line, headers),
+        HttpRequest::Post { status_line, headers, .. } => (status_line, headers),
+    };
+
+    let query = match puzzle_query(status_line) {
+        Ok(query) => query,
+        Err(message) => return json_error(tera, stream, StatusCode::BadRequest, "invalid_query", &message)
+    };
+
+    let user = is_authorised_for(headers, TokenScope::Read).ok();
+
+    match can_list_collection(&query, user.as_ref()) {
+        Ok(true) => (),
+        Ok(false) => return json_error(tera, stream, StatusCode::NotFound, "not_found", "No such collection"),
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
+    // Clients that don't ask for a page get every puzzle as a plain array,
+    // as they did before the listing was paged.
+    let params = status_line.query();
+    if !params.contains_key("page") && !params.contains_key("per_page") {
+        return match list_all_puzzles(user.as_ref(), &query) {
+            Ok(puzzles) => json_response(tera, stream, StatusCode::Ok, &puzzles),
+            Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+        }
+    }
+
+    match list_puzzles(user.as_ref(), &query) {
+        Ok(page) => json_response(tera, stream, StatusCode::Ok, &page),
+        Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+}
+
+const SEARCH_LIMIT: usize = 50;
+
+fn api_search_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    let (status_line, headers) = match req {
+        HttpRequest::Get { status_line, headers } => (status_line, headers),
+        HttpRequest::Post { .. } => return json_error(tera, stream, StatusCode::BadRequest, "unsupported_method", "Use GET to search"),
+    };
+
+    let q = match status_line.query().remove("q") {
+        Some(q) if !q.trim().is_empty() => q,
+        _ => return json_error(tera, stream, StatusCode::BadRequest, "invalid_query", "Search for something with ?q="),
+    };
+
+    let user = is_authorised_for(headers, TokenScope::Read).ok();
+
+    match search_clues(user.as_ref(), &q, SEARCH_LIMIT) {
+        Ok(matches) => json_response(tera, stream, StatusCode::Ok, &matches),
+        Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+}
+
+/// Reads `q`, `tag`, `collection`, `sort`, `order`, `page` and `per_page` from the query string,
+/// falling back to the defaults for any that are missing.
+fn puzzle_query(status_line: &StatusLine) -> Result<PuzzleQuery, String> {
+    let params = status_line.query();
+    let defaults = PuzzleQuery::default();
+
+    let q = params.get("q").map(|q| q.trim().to_string()).filter(|q| !q.is_empty());
+    let tag = params.get("tag").map(|tag| tag.trim().to_string()).filter(|tag| !tag.is_empty());
+    let collection = match params.get("collection") {
+        Some(collection) => Some(collection.parse().map_err(|_| format!("{collection} is not a valid collection"))?),
+        None => None,
+    };
+    let sort = match params.get("sort") {
+        Some(sort) => sort.parse()?,
+        None => defaults.sort,
+    };
+    let order = match params.get("order") {
+        Some(order) => order.parse()?,
+        None => defaults.order,
+    };
+    let page = match params.get("page") {
+        Some(page) => page.parse().ok().filter(|x| *x > 0).ok_or_else(|| format!("{page} is not a valid page"))?,
+        None => defaults.page,
+    };
+    let per_page = match params.get("per_page") {
+        Some(per_page) => per_page.parse().ok()
+            .filter(|x| (1..=PuzzleQuery::MAX_PER_PAGE).contains(x))
+            .ok_or_else(|| format!("per_page must be between 1 and {}", PuzzleQuery::MAX_PER_PAGE))?,
+        None => defaults.per_page,
+    };
+    Ok(PuzzleQuery { q, tag, collection, sort, order, page, per_page })
+}
+
+/// Collections are personal, so only their owner can list what's in them.
+fn can_list_collection(query: &PuzzleQuery, user: Option<&User>) -> Result<bool, rusqlite::Error> {
+    match (query.collection, user) {
+        (None, _) => Ok(true),
+        (Some(_), None) => Ok(false),
+        (Some(id), Some(user)) => Ok(find_own_collection(&id, user)?.is_some()),
+    }
+}
+
+/// Adds a page of puzzles, and the search that found them, for the index
+/// template's listing and paging controls.
+fn insert_puzzle_page(context: &mut tera::Context, viewer: Option<&User>, query: &PuzzleQuery) -> Result<(), rusqlite::Error> {
+    let page = list_puzzles(viewer, query)?;
+    let last_page = page.total.div_ceil(page.per_page).max(1);
+    context.insert("page", &page);
+    context.insert("last_page", &last_page);
+    context.insert("q", &query.q.clone().unwrap_or_default());
+    context.insert("tag", &query.tag.clone().unwrap_or_default());
+    context.insert("collection", &query.collection);
+    context.insert("sort", &query.sort.to_string());
+    context.insert("order", &query.order.to_string());
+    Ok(())
+}
+
+/// Finds a puzzle that whoever made the request is allowed to see. Private
+/// puzzles are reported as missing rather than forbidden, so that their
+/// existence isn't leaked.
+fn find_viewable_puzzle(puzzle_num: i64, headers: &HashMap<String, String>) -> Result<Option<PuzzleDbData>, rusqlite::Error> {
+    let data = match get_puzzle_db(&puzzle_num) {
+        Ok(data) => data,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(error) => return Err(error),
+    };
+
+    if data.visibility != Visibility::Private {
+        return Ok(Some(data))
+    }
+
+    let user = is_authorised_for(headers, TokenScope::Read).ok();
+    if data.can_view(user.as_ref()) { Ok(Some(data)) } else { Ok(None) }
+}
+
+#[derive(Deserialize, Debug)]
+struct EditPuzzleBody {
+    name: Option<String>,
+    visibility: Option<Visibility>,
+    crossword: Option<Crossword>,
+    /// Replaces the puzzle's metadata without sending the whole grid.
+    metadata: Option<PuzzleMetadata>,
+    solution: Option<SolutionInput>,
+}
+
+fn puzzle_edit_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+
+    let (status_line, headers, body) = match req {
+        HttpRequest::Get { .. } => return bad_request(tera, stream, "Unsupported http method"),
+        HttpRequest::Post { status_line, headers, body } => (status_line, headers, body),
+    };
+
+    let user = match is_authorised_for(headers, TokenScope::Upload) {
+        Ok(user) => user,
+        Err(_) => return not_authorised(tera, stream)
+    };
+
+    let path_info = Regex::new(r"(?<num>\d+)/edit").expect("Invalid regular expression");
+    let caps = match path_info.captures(&status_line.route) {
+        Some(caps) => caps,
+        None => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, "api route doesn't match the regex used by the route handler")))
+    };
+    let puzzle_num: i64 = caps["num"].parse().unwrap();
+
+    let stored = match get_puzzle_db(&puzzle_num) {
+        Ok(data) if data.can_edit(&user) => data,
+        Ok(data) if data.can_view(Some(&user)) => return forbidden(tera, stream),
+        Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return not_found(tera, stream, Some(&format!("No puzzle with ID {puzzle_num}")))
+        },
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let request_data: EditPuzzleBody = match serde_json::from_slice(body) {
+        Ok(s) => s,
+        Err(e) => {
+            return bad_request(tera, stream, &format!("Body of the request did not match the schema for editing puzzles {e}"))
+        },
+    };
+
+    if request_data.name.as_deref().is_some_and(|name| name.trim().is_empty()) {
+        return bad_request(tera, stream, "Puzzles must have a name")
+    }
+
+    let crossword = match (request_data.crossword, request_data.metadata) {
+        (Some(mut crossword), Some(metadata)) => {
+            crossword.set_metadata(metadata);
+            Some(crossword)
+        },
+        // clients that predate metadata shouldn't wipe it when they replace the grid.
+        (Some(mut crossword), None) => {
+            if crossword.metadata().is_empty() {
+                crossword.set_metadata(stored.metadata);
+            }
+            Some(crossword)
+        },
+        (None, Some(metadata)) => match get_puzzle(&puzzle_num) {
+            Ok(Some(mut crossword)) => {
+                crossword.set_metadata(metadata);
+                Some(crossword)
+            },
+            Ok(None) => return not_found(tera, stream, Some(&format!("No puzzle with ID {puzzle_num}"))),
+            Err(error) => return Err(HandlerError::new(stream, error))
+        },
+        (None, None) => None,
+    };
+
+    if let Some(Err(errors)) = crossword.as_ref().map(|x| x.validate()) {
+        return invalid_crossword(tera, stream, &errors)
+    }
+
+    let solution = match (&request_data.solution, &crossword) {
+        (Some(input), Some(crossword)) => Some(crossword.solution(input)),
+        (Some(input), None) => match get_puzzle(&puzzle_num) {
+            Ok(Some(crossword)) => Some(crossword.solution(input)),
+            Ok(None) => return not_found(tera, stream, Some(&format!("No puzzle with ID {puzzle_num}"))),
+            Err(error) => return Err(HandlerError::new(stream, error))
+        },
+        (None, _) => None,
+    };
+    let solution = match solution {
+        Some(Ok(solution)) => Some(solution),
+        Some(Err(errors)) => return invalid_crossword(tera, stream, &errors),
+        None => None,
+    };
+
+    if let Some(crossword) = &crossword {
+        // a live channel would overwrite the new grid with its own copy when it closes.
+        let is_live = match PUZZLEPOOL.lock() {
+            Ok(guard) => guard.is_live(&puzzle_num),
+            Err(e) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}", e))))
+        };
+        if is_live {
+            return bad_request(tera, stream, "The grid can't be replaced while people are solving it")
+        }
+        if let Err(error) = save_puzzle(&puzzle_num, crossword) {
+            return Err(HandlerError::new(stream, error))
+        }
+        // the old solution only survives a new grid if it still fits it.
+        if solution.is_none() {
+            match get_solution(&puzzle_num) {
+                Ok(Some(stored)) if stored.fits(crossword).is_err() => {
+                    info!("Removing the solution to puzzle {puzzle_num} as it doesn't fit the new grid");
+                    if let Err(error) = set_solution(&puzzle_num, None) {
+                        return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+                    }
+                },
+                Ok(_) => (),
+                Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+            }
+        }
+    }
+
+    if let Some(solution) = &solution {
+        if let Err(error) = set_solution(&puzzle_num, Some(solution)) {
+            return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+        }
+    }
+
+    if let Err(error) = update_puzzle_details(puzzle_num, request_data.name.as_deref().map(|x| x.trim()), request_data.visibility) {
+        return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
+    match get_puzzle_db(&puzzle_num) {
+        Ok(data) => json_response(tera, stream, StatusCode::Ok, &data),
+        Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+}
+
+fn puzzle_check_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    puzzle_solve_handler(req, tera, stream, false)
+}
+
+fn puzzle_reveal_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    puzzle_solve_handler(req, tera, stream, true)
+}
+
+/// Checks or reveals a square (`{"x": 0, "y": 2}`), a clue (`{"clue": "3a"}`)
+/// or, for checks, the whole grid (`{}` or no body).
+fn puzzle_solve_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream, reveal: bool) -> Result<(), HandlerError> {
+    let (status_line, headers, body) = match req {
+        HttpRequest::Get { .. } => return bad_request(tera, stream, "Unsupported http method"),
+        HttpRequest::Post { status_line, headers, body } => (status_line, headers, body),
+    };
+
+    let path_info = Regex::new(r"(?<num>\d+)/(check|reveal)").expect("Invalid regular expression");
+    let caps = match path_info.captures(&status_line.route) {
+        Some(caps) => caps,
+        None => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, "api route doesn't match the regex used by the route handler")))
+    };
+    let puzzle_num: i64 = caps["num"].parse().unwrap();
+
+    match find_viewable_puzzle(puzzle_num, headers) {
+        Ok(Some(_)) => (),
+        Ok(None) => return json_error(tera, stream, StatusCode::NotFound, "not_found", &format!("No puzzle with ID {puzzle_num}")),
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
+    let body: &[u8] = if body.iter().all(|b| b.is_ascii_whitespace()) { b"{}" } else { body };
+    let target: Target = match serde_json::from_slice(body) {
+        Ok(target) => target,
+        Err(e) => return json_error(tera, stream, StatusCode::BadRequest, "invalid_request", &format!("{e}")),
+    };
+    let request = match reveal {
+        true => SolveRequest::Reveal(target),
+        false => SolveRequest::Check(target),
+    };
+    let user_id = is_authorised_for(headers, TokenScope::Read).ok().map(|user| user.id);
+
+    let outcome = match PUZZLEPOOL.lock() {
+        Ok(guard) => guard.solve(puzzle_num, &request, user_id),
+        Err(e) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}", e))))
+    };
+
+    match outcome {
+        Ok(SolveOutcome::Checked(result)) => json_response(tera, stream, StatusCode::Ok, &result),
+        Ok(SolveOutcome::Revealed(cells)) => json_response(tera, stream, StatusCode::Ok, &RevealReply { revealed: &cells }),
+        Err(SolveError::Server(reason)) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, reason))),
+        Err(e @ SolveError::NoSolution) | Err(e @ SolveError::NotInGrid) => json_error(tera, stream, StatusCode::NotFound, e.code(), e.message()),
+        Err(e @ SolveError::RevealGrid) => json_error(tera, stream, StatusCode::BadRequest, e.code(), e.message()),
+    }
+}
+
+fn puzzle_stats_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    let (status_line, headers) = match req {
+        HttpRequest::Get { status_line, headers } => (status_line, headers),
+        HttpRequest::Post { .. } => return bad_request(tera, stream, "Unsupported http method"),
+    };
+
+    let path_info = Regex::new(r"(?<num>\d+)/stats").expect("Invalid regular expression");
+    let caps = match path_info.captures(&status_line.route) {
+        Some(caps) => caps,
+        None => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, "api route doesn't match the regex used by the route handler")))
+    };
+    let puzzle_num: i64 = caps["num"].parse().unwrap();
+
+    match find_viewable_puzzle(puzzle_num, headers) {
+        Ok(Some(_)) => (),
+        Ok(None) => return json_error(tera, stream, StatusCode::NotFound, "not_found", &format!("No puzzle with ID {puzzle_num}")),
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
+    match get_puzzle_stats(&puzzle_num) {
+        Ok(stats) => json_response(tera, stream, StatusCode::Ok, &stats),
+        Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+}
+
+fn json_response<T: Serialize>(tera: Arc<Tera>, mut stream: TcpStream, status_code: StatusCode, data: &T) -> Result<(), HandlerError> {
+    let contents = match serde_json::to_string(data){
+        Ok(s) => s,
+        Err(error) => {
+            error!("Unsuccessfully serialised response, {}", error);
+            return server_error(tera, stream)
+        },
+    };
+
+    let response = ResponseBuilder::new()
+        .set_status_code(status_code)
+        .set_json_content(contents)
+        .build();
+
+    match stream.write_all(response.as_bytes()) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(HandlerError::new(stream, error))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct CreateTokenBody {
+    name: String,
+    scopes: Option<Vec<TokenScope>>
+}
+
+#[derive(Serialize, Debug)]
+struct CreatedToken {
+    token: String,
+    #[serde(flatten)]
+    info: cw_grid_server::db::ApiToken
+}
+
+fn api_tokens_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+
+    match req {
+        HttpRequest::Get { status_line: _, headers } => {
+            let user = match is_authorised_for(headers, TokenScope::Read) {
+                Ok(user) => user,
+                Err(_) => return not_authorised(tera, stream)
+            };
+
+            match get_api_tokens(user.id) {
+                Ok(tokens) => json_response(tera, stream, StatusCode::Ok, &tokens),
+                Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+            }
+        },
+        HttpRequest::Post { status_line: _, headers, body } => {
+            // tokens can only be made by someone who has logged in, so a
+            // leaked token cannot be used to mint more.
+            let user = match is_authorised(headers) {
+                Ok(user) => user,
+                Err(_) => return not_authorised(tera, stream)
+            };
+
+            let request_data: CreateTokenBody = match serde_json::from_slice(body) {
+                Ok(s) => s,
+                Err(e) => {
+                    return bad_request(tera, stream, &format!("Body of the request did not match the schema for creating tokens {e}"))
+                },
+            };
+
+            if request_data.name.trim().is_empty() {
+                return bad_request(tera, stream, "Tokens must have a name")
+            }
+
+            match create_api_token(user.id, request_data.name.trim(), request_data.scopes.as_deref()) {
+                Ok((info, token)) => json_response(tera, stream, StatusCode::Created, &CreatedToken { token, info }),
+                Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+            }
+        }
+    }
+}
+
+fn api_token_revoke_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+
+    let (status_line, headers) = match req {
+        HttpRequest::Get { .. } => return bad_request(tera, stream, "Unsupported http method"),
+        HttpRequest::Post { status_line, headers, .. } => (status_line, headers),
+    };
+
+    let user = match is_authorised(headers) {
+        Ok(user) => user,
+        Err(_) => return not_authorised(tera, stream)
+    };
+
+    let path_info = Regex::new(r"(?<num>\d+)/revoke").expect("Invalid regular expression");
+    let caps = match path_info.captures(&status_line.route) {
+        Some(caps) => caps,
+        None => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, "api route doesn't match the regex used by the route handler")))
+    };
+    let token_id: i64 = caps["num"].parse().unwrap();
+
+    match get_api_token(token_id) {
+        Ok(token) if token.user_id == user.id => (),
+        Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return not_found(tera, stream, Some(&format!("No token with ID {token_id}")))
+        },
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
+    if let Err(error) = revoke_api_token(token_id) {
+        return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
+    match get_api_token(token_id) {
+        Ok(token) => json_response(tera, stream, StatusCode::Ok, &token),
+        Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct TagBody {
+    tag: String,
+}
+
+/// Tags and collection names are trimmed and must be 1 to 64 characters.
+fn clean_label(label: &str) -> Option<&str> {
+    let label = label.trim();
+    match label.chars().count() {
+        1..=MAX_LABEL_LEN => Some(label),
+        _ => None,
+    }
+}
+
+fn api_tags_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    if let HttpRequest::Post { .. } = req {
+        return bad_request(tera, stream, "Unsupported http method")
+    }
+
+    match get_tags() {
+        Ok(tags) => json_response(tera, stream, StatusCode::Ok, &tags),
+        Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+}
+
+fn puzzle_tags_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    puzzle_tag_change(req, tera, stream, false)
+}
+
+fn puzzle_untag_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    puzzle_tag_change(req, tera, stream, true)
+}
+
+/// Lists a puzzle's tags on a GET. A POST adds the tag in the body, or
+/// removes it when `remove` is set, and replies with the updated tags.
+fn puzzle_tag_change(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream, remove: bool) -> Result<(), HandlerError> {
+    let status_line = match req {
+        HttpRequest::Get { status_line, .. } => status_line,
+        HttpRequest::Post { status_line, .. } => status_line,
+    };
+
+    let path_info = Regex::new(r"puzzle/(?<num>\d+)/tags").expect("Invalid regular expression");
+    let caps = match path_info.captures(&status_line.route) {
+        Some(caps) => caps,
+        None => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, "api route doesn't match the regex used by the route handler")))
+    };
+    let puzzle_num: i64 = caps["num"].parse().unwrap();
+
+    let (headers, body) = match req {
+        HttpRequest::Get { headers, .. } if !remove => {
+            return match find_viewable_puzzle(puzzle_num, headers) {
+                Ok(Some(_)) => match get_puzzle_tags(&puzzle_num) {
+                    Ok(tags) => json_response(tera, stream, StatusCode::Ok, &tags),
+                    Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+                },
+                Ok(None) => not_found(tera, stream, Some(&format!("No puzzle with ID {puzzle_num}"))),
+                Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+            }
+        },
+        HttpRequest::Get { .. } => return bad_request(tera, stream, "Unsupported http method"),
+        HttpRequest::Post { headers, body, .. } => (headers, body),
+    };
+
+    let user = match is_authorised_for(headers, TokenScope::Upload) {
+        Ok(user) => user,
+        Err(_) => return not_authorised(tera, stream)
+    };
+
+    match get_puzzle_db(&puzzle_num) {
+        Ok(data) if data.can_edit(&user) => (),
+        Ok(data) if data.can_view(Some(&user)) => return forbidden(tera, stream),
+        Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return not_found(tera, stream, Some(&format!("No puzzle with ID {puzzle_num}")))
+        },
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
+    let request_data: TagBody = match serde_json::from_slice(body) {
+        Ok(s) => s,
+        Err(e) => {
+            return bad_request(tera, stream, &format!("Body of the request did not match the schema for tagging puzzles {e}"))
+        },
+    };
+    let tag = match clean_label(&request_data.tag) {
+        Some(tag) => tag,
+        None => return bad_request(tera, stream, &format!("Tags must be between 1 and {MAX_LABEL_LEN} characters")),
+    };
+
+    let result = match remove {
+        true => untag_puzzle(&puzzle_num, tag).map(|_| ()),
+        false => tag_puzzle(&puzzle_num, tag),
+    };
+    if let Err(error) = result {
+        return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
+    match get_puzzle_tags(&puzzle_num) {
+        Ok(tags) => json_response(tera, stream, StatusCode::Ok, &tags),
+        Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct CreateCollectionBody {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CollectionPuzzleBody {
+    puzzle_id: i64,
+}
+
+fn api_collections_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+
+    match req {
+        HttpRequest::Get { status_line: _, headers } => {
+            let user = match is_authorised_for(headers, TokenScope::Read) {
+                Ok(user) => user,
+                Err(_) => return not_authorised(tera, stream)
+            };
+
+            match get_collections(user.id) {
+                Ok(collections) => json_response(tera, stream, StatusCode::Ok, &collections),
+                Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+            }
+        },
+        HttpRequest::Post { status_line: _, headers, body } => {
+            let user = match is_authorised_for(headers, TokenScope::Upload) {
+                Ok(user) => user,
+                Err(_) => return not_authorised(tera, stream)
+            };
+
+            let request_data: CreateCollectionBody = match serde_json::from_slice(body) {
+                Ok(s) => s,
+                Err(e) => {
+                    return bad_request(tera, stream, &format!("Body of the request did not match the schema for creating collections {e}"))
+                },
+            };
+            let name = match clean_label(&request_data.name) {
+                Some(name) => name,
+                None => return bad_request(tera, stream, &format!("Collection names must be between 1 and {MAX_LABEL_LEN} characters")),
+            };
+
+            match create_collection(user.id, name) {
+                Ok(collection) => json_response(tera, stream, StatusCode::Created, &collection),
+                Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ErrorCode::ConstraintViolation => {
+                    bad_request(tera, stream, &format!("You already have a collection called {name}"))
+                },
+                Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+            }
+        }
+    }
+}
+
+/// Finds a collection belonging to `user`. Other people's collections are
+/// reported as missing.
+fn find_own_collection(collection_id: &i64, user: &User) -> Result<Option<Collection>, rusqlite::Error> {
+    match get_collection(collection_id) {
+        Ok(collection) if collection.owner_id == user.id => Ok(Some(collection)),
+        Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+fn api_collection_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    let (status_line, headers) = match req {
+        HttpRequest::Get { status_line, headers } => (status_line, headers),
+        HttpRequest::Post { .. } => return bad_request(tera, stream, "Unsupported http method"),
+    };
+
+    let user = match is_authorised_for(headers, TokenScope::Read) {
+        Ok(user) => user,
+        Err(_) => return not_authorised(tera, stream)
+    };
+
+    let path_info = Regex::new(r"collections/(?<num>\d+)").expect("Invalid regular expression");
+    let caps = match path_info.captures(&status_line.route) {
+        Some(caps) => caps,
+        None => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, "api route doesn't match the regex used by the route handler")))
+    };
+    let collection_id: i64 = caps["num"].parse().unwrap();
+
+    match find_own_collection(&collection_id, &user) {
+        Ok(Some(collection)) => json_response(tera, stream, StatusCode::Ok, &collection),
+        Ok(None) => not_found(tera, stream, Some(&format!("No collection with ID {collection_id}"))),
+        Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+}
+
+fn api_collection_add_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    collection_change(req, tera, stream, false)
+}
+
+fn api_collection_remove_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    collection_change(req, tera, stream, true)
+}
+
+fn collection_change(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream, remove: bool) -> Result<(), HandlerError> {
+    let (status_line, headers, body) = match req {
+        HttpRequest::Get { .. } => return bad_request(tera, stream, "Unsupported http method"),
+        HttpRequest::Post { status_line, headers, body } => (status_line, headers, body),
+    };
+
+    let user = match is_authorised_for(headers, TokenScope::Upload) {
+        Ok(user) => user,
+        Err(_) => return not_authorised(tera, stream)
+    };
+
+    let path_info = Regex::new(r"collections/(?<num>\d+)").expect("Invalid regular expression");
+    let caps = match path_info.captures(&status_line.route) {
+        Some(caps) => caps,
+        None => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, "api route doesn't match the regex used by the route handler")))
+    };
+    let collection_id: i64 = caps["num"].parse().unwrap();
+
+    match find_own_collection(&collection_id, &user) {
+        Ok(Some(_)) => (),
+        Ok(None) => return not_found(tera, stream, Some(&format!("No collection with ID {collection_id}"))),
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
+    let request_data: CollectionPuzzleBody = match serde_json::from_slice(body) {
+        Ok(s) => s,
+        Err(e) => {
+            return bad_request(tera, stream, &format!("Body of the request did not match the schema for changing collections {e}"))
+        },
+    };
+    let puzzle_id = request_data.puzzle_id;
+
+    let result = match remove {
+        true => remove_from_collection(&collection_id, &puzzle_id).map(|_| ()),
+        false => match find_viewable_puzzle(puzzle_id, headers) {
+            Ok(Some(_)) => add_to_collection(&collection_id, &puzzle_id),
+            Ok(None) => return not_found(tera, stream, Some(&format!("No puzzle with ID {puzzle_id}"))),
+            Err(error) => Err(error),
+        },
+    };
+    if let Err(error) = result {
+        return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+
+    match get_collection(&collection_id) {
+        Ok(collection) => json_response(tera, stream, StatusCode::Ok, &collection),
+        Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+}
+
+/// Checks the request was made by an admin. The status code is the one to
+/// reply with when it wasn't.
+fn authorise_admin(headers: &HashMap<String, String>, scope: TokenScope) -> Result<User, StatusCode> {
+    match is_authorised_for(headers, scope) {
+        Ok(user) if user.is_admin => Ok(user),
+        Ok(_) => Err(StatusCode::Forbidden),
+        Err(_) => Err(StatusCode::Unauthorized),
+    }
+}
+
+fn wants_json(headers: &HashMap<String, String>) -> bool {
+    headers.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("Accept"))
+        .is_some_and(|(_, v)| v.contains("application/json"))
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct AdminBody {
+    #[serde(default)]
+    live: bool,
+}
+
+/// Like prune, admin operations are a dry run unless `live` is set, either
+/// as the form field `live=true` or in a JSON body.
+fn is_live(body: &[u8]) -> bool {
+    if let Ok(data) = serde_json::from_slice::<AdminBody>(body) {
+        return data.live
+    }
+    let raw_form = String::from_utf8_lossy(body);
+    match get_form_data(&raw_form) {
+        Ok(form) => form.get("live").copied().flatten() == Some("true"),
+        Err(_) => false,
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct AdminReport {
+    live: bool,
+    puzzles: Vec<PuzzleDbData>,
+}
+
+fn admin_denied(tera: Arc<Tera>, stream: TcpStream, headers: &HashMap<String, String>, status_code: StatusCode) -> Result<(), HandlerError> {
+    match (wants_json(headers), status_code) {
+        (true, StatusCode::Forbidden) => json_error(tera, stream, StatusCode::Forbidden, "forbidden", "Admin rights are required"),
+        (true, _) => json_error(tera, stream, StatusCode::Unauthorized, "unauthorised", "You are not logged in"),
+        (false, StatusCode::Forbidden) => forbidden(tera, stream),
+        (false, _) => not_authorised(tera, stream),
+    }
+}
+
+/// Replies with the outcome of an admin operation, either as JSON or as the
+/// console fragment listing what is still soft deleted.
+fn admin_report(tera: Arc<Tera>, mut stream: TcpStream, headers: &HashMap<String, String>, message: Option<&str>, report: AdminReport) -> Result<(), HandlerError> {
+    if wants_json(headers) {
+        return json_response(tera, stream, StatusCode::Ok, &report)
+    }
+
+    let remaining = match get_soft_delete_puzzles() {
+        Ok(puzzles) => puzzles,
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let mut context = tera::Context::new();
+    context.insert("puzzles", &remaining);
+    context.insert("affected", &report.puzzles);
+    if let Some(message) = message {
+        context.insert("message", message);
+    }
+    let template = match message {
+        Some(_) => "admin_content.html",
+        None => "admin.html",
+    };
+    let contents = match tera.render(template, &context){
+        Ok(contents) => contents,
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let response = ResponseBuilder::new()
+        .set_status_code(StatusCode::Ok)
+        .set_html_content(contents)
+        .build();
+
+    match stream.write_all(response.as_bytes()) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(HandlerError::new(stream, error))
+    }
+}
+
+fn admin_puzzles_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    let headers = match req {
+        HttpRequest::Get { headers, .. } => headers,
+        HttpRequest::Post { .. } => return bad_request(tera, stream, "Unsupported http method"),
+    };
+
+    if let Err(status_code) = authorise_admin(headers, TokenScope::Read) {
+        return admin_denied(tera, stream, headers, status_code)
+    }
+
+    match get_soft_delete_puzzles() {
+        Ok(puzzles) => admin_report(tera, stream, headers, None, AdminReport { live: false, puzzles }),
+        Err(error) => Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AdminAction {
+    Restore,
+    Purge,
+}
+
+fn admin_restore_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    admin_single_handler(req, tera, stream, AdminAction::Restore)
+}
+
+fn admin_purge_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    admin_single_handler(req, tera, stream, AdminAction::Purge)
+}
+
+fn admin_single_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream, action: AdminAction) -> Result<(), HandlerError> {
+    let (status_line, headers, body) = match req {
+        HttpRequest::Get { .. } => return bad_request(tera, stream, "Unsupported http method"),
+        HttpRequest::Post { status_line, headers, body } => (status_line, headers, body),
+    };
+
+    if let Err(status_code) = authorise_admin(headers, TokenScope::Delete) {
+        return admin_denied(tera, stream, headers, status_code)
+    }
+
+    let path_info = Regex::new(r"(?<num>\d+)/(restore|purge)").expect("Invalid regular expression");
+    let caps = match path_info.captures(&status_line.route) {
+        Some(caps) => caps,
+        None => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, "api route doesn't match the regex used by the route handler")))
+    };
+    let puzzle_num: i64 = caps["num"].parse().unwrap();
+
+    // only puzzles that have already been soft deleted can be restored or purged.
+    let puzzle = match get_puzzle_db(&puzzle_num) {
+        Ok(data) if data.is_deleted() => data,
+        Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => {
+            let message = format!("No soft deleted puzzle with ID {puzzle_num}");
+            return match wants_json(headers) {
+                true => json_error(tera, stream, StatusCode::NotFound, "not_found", &message),
+                false => not_found(tera, stream, Some(&message)),
+            }
+        },
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let live = is_live(body);
+    if live {
+        let result = match action {
+            AdminAction::Restore => restore_puzzle(&puzzle_num).map_err(anyhow::Error::from),
+            AdminAction::Purge => delete_puzzle(&puzzle_num),
+        };
+        if let Err(error) = result {
+            return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+        }
+    }
+
+    let message = match (action, live) {
+        (AdminAction::Restore, true) => "Restored",
+        (AdminAction::Restore, false) => "Dry run, would restore",
+        (AdminAction::Purge, true) => "Deleted",
+        (AdminAction::Purge, false) => "Dry run, would delete",
+    };
+    admin_report(tera, stream, headers, Some(message), AdminReport { live, puzzles: vec![puzzle] })
+}
+
+fn admin_batch_restore_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    admin_batch_handler(req, tera, stream, AdminAction::Restore)
+}
+
+fn admin_batch_delete_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream) -> Result<(), HandlerError> {
+    admin_batch_handler(req, tera, stream, AdminAction::Purge)
+}
+
+fn admin_batch_handler(req: &HttpRequest, tera: Arc<Tera>, stream: TcpStream, action: AdminAction) -> Result<(), HandlerError> {
+    let (headers, body) = match req {
+        HttpRequest::Get { .. } => return bad_request(tera, stream, "Unsupported http method"),
+        HttpRequest::Post { headers, body, .. } => (headers, body),
+    };
+
+    if let Err(status_code) = authorise_admin(headers, TokenScope::Delete) {
+        return admin_denied(tera, stream, headers, status_code)
+    }
+
+    let puzzles = match get_soft_delete_puzzles() {
+        Ok(puzzles) => puzzles,
+        Err(error) => return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+    };
+
+    let live = is_live(body);
+    if live {
+        let result = match action {
+            AdminAction::Restore => batch_restore().map_err(anyhow::Error::from),
+            AdminAction::Purge => batch_delete(),
+        };
+        if let Err(error) = result {
+            return Err(HandlerError::new(stream, Error::new(ErrorKind::Other, format!("{}",error))))
+        }
+    }
+
+    let message = match (action, live) {
+        (AdminAction::Restore, true) => "Restored every soft deleted puzzle",
+        (AdminAction::Restore, false) => "Dry run, would restore every soft deleted puzzle",
+        (AdminAction::Purge, true) => "Deleted every soft deleted puzzle",
+        (AdminAction::Purge, false) => "Dry run, would delete every soft deleted puzzle",
+    };
+    admin_report(tera, stream, headers, Some(message), AdminReport { live, puzzles })
+}
+
+#[derive(Debug)]
+struct PuzzlePool {
+    pool: HashMap<i64, Arc<Mutex<PuzzleChannel>>>,
+    tera: Arc<Tera>
+}
+
+impl PuzzlePool {
+    fn new() -> Self {
+        let pool = HashMap::new();
+
+        let tera = Tera::new("templates/**/*").unwrap_or_else(|err| {
+            error!("Sever failed to load templates: {}", err);
+            std::process::exit(1);
+        });
+
+        Self { pool, tera: Arc::new(tera) }
+    }
+
+    fn connect_client(&mut self, puzzle_num: i64, stream: TcpStream, user_id: Option<i64>) -> Result<(), HandlerError> {
+
+
+        match self.pool.get(&puzzle_num) {
+            Some(puzzle_channel) => {
+                info!("Connecting websocket client to existing puzzle.");
+                route_stream_to_puzzle(puzzle_channel.clone(), stream, self.tera.clone(), user_id)
+            }
+            None => {
+                info!("No channel found to route websocket client. Creating a new channel");
+                match PuzzleChannel::new(puzzle_num.clone()){
+                    Ok(channel) => {
+                        match channel {
+                            Some(channel) => {
                                 let new_channel = Arc::new(Mutex::new(channel));
                                 self.pool.insert(puzzle_num, new_channel.clone());
-                                route_stream_to_puzzle(new_channel.clone(), stream,  self.tera.clone())
+                                route_stream_to_puzzle(new_channel.clone(), stream,  self.tera.clone(), user_id)
                             },
                             None => {
                                 Err( HandlerError::new(stream, Error::from(ErrorKind::Other)))
@@ -1067,6 +2768,65 @@ impl PuzzlePool {
         }
     }
 
+    /// Checks or reveals part of a puzzle. A reveal is shown straight away
+    /// to anyone solving it live, and saved to disk otherwise.
+    fn solve(&self, puzzle_num: i64, request: &SolveRequest, user_id: Option<i64>) -> Result<SolveOutcome, SolveError> {
+        match self.pool.get(&puzzle_num) {
+            Some(puzzle_channel) => {
+                let channel = puzzle_channel.lock().unwrap_or_else(|err| {
+                    warn!("This mutex is in a poisoned state, but we're attempting to solve the puzzle anyway");
+                    err.into_inner()
+                });
+                let outcome = {
+                    let mut crossword = channel.crossword.lock().unwrap_or_else(|err| {
+                        warn!("puzzle {} is poisoned, but we're solving it anyway", puzzle_num);
+                        err.into_inner()
+                    });
+                    solve_crossword(puzzle_num, &mut crossword, request, user_id)?
+                };
+                if let SolveOutcome::Revealed(cells) = &outcome {
+                    cell_messages(cells).iter().for_each(|msg| send_to_clients(&channel.clients, msg));
+                }
+                Ok(outcome)
+            },
+            None => {
+                let mut crossword = match get_puzzle(&puzzle_num) {
+                    Ok(Some(crossword)) => crossword,
+                    Ok(None) => return Err(SolveError::Server(format!("No crossword with ID {puzzle_num}"))),
+                    Err(e) => return Err(SolveError::Server(format!("{e}"))),
+                };
+                let outcome = solve_crossword(puzzle_num, &mut crossword, request, user_id)?;
+                if let SolveOutcome::Revealed(_) = outcome {
+                    save_puzzle(&puzzle_num, &crossword).map_err(|e| SolveError::Server(format!("{e}")))?;
+                }
+                Ok(outcome)
+            }
+        }
+    }
+
+    /// The puzzle as it is now: the live copy if people are solving it,
+    /// otherwise the one on disk.
+    fn current_crossword(&self, puzzle_num: i64) -> Result<Option<Crossword>, Error> {
+        match self.pool.get(&puzzle_num) {
+            Some(puzzle_channel) => {
+                let channel = puzzle_channel.lock().unwrap_or_else(|err| {
+                    warn!("This mutex is in a poisoned state, but we're attempting to read the puzzle anyway");
+                    err.into_inner()
+                });
+                let crossword = channel.crossword.lock().unwrap_or_else(|err| {
+                    warn!("puzzle {} is poisoned, but we're reading it anyway", puzzle_num);
+                    err.into_inner()
+                });
+                Ok(Some(crossword.clone()))
+            },
+            None => get_puzzle(&puzzle_num),
+        }
+    }
+
+    fn is_live(&self, puzzle_num: &i64) -> bool {
+        self.pool.contains_key(puzzle_num)
+    }
+
     fn remove_channel(&mut self, puzzle_num: &i64) {
         self.pool.remove(puzzle_num);
     }
@@ -1074,9 +2834,128 @@ impl PuzzlePool {
 
 type ThreadSafeSenderVector = Arc<Mutex<Vec<Arc<Sender<Message>>>>>;
 
+/// A message for the puzzle channel, along with the client that sent it so
+/// a rejected update can be answered to that client alone.
+struct ChannelMessage {
+    msg: Message,
+    origin: Option<Arc<Sender<Message>>>,
+    user_id: Option<i64>,
+}
+
+impl ChannelMessage {
+    fn new(msg: Message, origin: Option<Arc<Sender<Message>>>, user_id: Option<i64>) -> Self {
+        Self { msg, origin, user_id }
+    }
+}
+
+/// Where the channel should send the result of a client's message.
+enum ChannelReply {
+    Everyone(Vec<Message>),
+    Origin(Message),
+}
+
+fn channel_error(error: &str, message: &str) -> ChannelReply {
+    let body = serde_json::to_string(&ApiError { error, message }).unwrap_or_default();
+    ChannelReply::Origin(Message::new_from_str(&body))
+}
+
+fn send_to_clients(clients: &ThreadSafeSenderVector, msg: &Message) {
+    clients
+        .lock()
+        .unwrap_or_else(|err| {
+            warn!("This mutex is in a poisoned state, but we're attempting to send the clients messages anyway");
+            err.into_inner()
+        })
+        .iter()
+        .filter_map(|x| x.send( msg.clone() ).err())
+        .for_each(drop);
+}
+
+fn cell_messages(cells: &[Cell]) -> Vec<Message> {
+    cells.iter()
+        .filter_map(|cell| serde_json::to_string(cell).ok())
+        .map(|body| Message::new_from_str(&body))
+        .collect()
+}
+
+/// A request to check or reveal part of the grid, sent over the websocket
+/// as e.g. `{"op": "check", "clue": "3a"}` or to `/puzzle/{id}/check`.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum SolveRequest {
+    Check(Target),
+    Reveal(Target),
+}
+
+enum SolveOutcome {
+    Checked(CheckResult),
+    Revealed(Vec<Cell>),
+}
+
+#[derive(Serialize)]
+struct CheckReply<'a> {
+    op: &'a str,
+    #[serde(flatten)]
+    result: &'a CheckResult,
+}
+
+#[derive(Serialize)]
+struct RevealReply<'a> {
+    revealed: &'a [Cell],
+}
+
+#[derive(Debug)]
+enum SolveError {
+    NoSolution,
+    NotInGrid,
+    RevealGrid,
+    Server(String),
+}
+
+impl SolveError {
+    fn code(&self) -> &'static str {
+        match self {
+            SolveError::NoSolution => "no_solution",
+            SolveError::NotInGrid => "not_in_grid",
+            SolveError::RevealGrid => "invalid_request",
+            SolveError::Server(_) => "server_error",
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match self {
+            SolveError::NoSolution => "The puzzle doesn't have a solution",
+            SolveError::NotInGrid => "The square or clue is not part of the grid",
+            SolveError::RevealGrid => "Only a square or a clue can be revealed",
+            SolveError::Server(_) => "Something went wrong",
+        }
+    }
+}
+
+fn solve_crossword(puzzle_num: i64, crossword: &mut Crossword, request: &SolveRequest, user_id: Option<i64>) -> Result<SolveOutcome, SolveError> {
+    let solution = match get_solution(&puzzle_num) {
+        Ok(Some(solution)) => solution,
+        Ok(None) => return Err(SolveError::NoSolution),
+        Err(e) => return Err(SolveError::Server(format!("{e}"))),
+    };
+    match request {
+        SolveRequest::Check(target) => crossword.check(&solution, target)
+            .map(SolveOutcome::Checked)
+            .ok_or(SolveError::NotInGrid),
+        SolveRequest::Reveal(Target::Grid) => Err(SolveError::RevealGrid),
+        SolveRequest::Reveal(target) => {
+            let cells = crossword.reveal(&solution, target).ok_or(SolveError::NotInGrid)?;
+            if let Err(e) = record_reveal(&puzzle_num, user_id, &target.to_string(), cells.len()) {
+                error!("Could not record a reveal of {target} in puzzle {puzzle_num}: {e}");
+            }
+            Ok(SolveOutcome::Revealed(cells))
+        },
+    }
+}
+
 #[derive(Debug)]
 struct PuzzleChannel {
-    channel_wide_sender: Arc<Sender<Message>>,
+    channel_wide_sender: Arc<Sender<ChannelMessage>>,
     clients: ThreadSafeSenderVector,
     terminate_sender: mpsc::Sender<bool>,
     crossword: Arc<Mutex<Crossword>>,
@@ -1088,7 +2967,7 @@ impl PuzzleChannel {
     fn new(puzzle_num: i64) -> Result<Option<Self>, Error> {
         // let puzzle_num_clone = puzzle_num.clone();
 
-        let (sender, receiver) = mpsc::channel::<Message>();
+        let (sender, receiver) = mpsc::channel::<ChannelMessage>();
 
         let (terminate_sender, terminate_rec) = mpsc::channel();
 
@@ -1117,7 +2996,7 @@ impl PuzzleChannel {
                     }
                 }
 
-                let msg = match receiver.recv() {
+                let ChannelMessage { msg, origin, user_id } = match receiver.recv() {
                     Ok(d) => d,
                     Err(e) => {
                         error!("There was an error recieving data: {e}");
@@ -1127,25 +3006,25 @@ impl PuzzleChannel {
 
                 let msg_clone = msg.clone();
                 
-                match msg_clone.opcode {
+                let reply = match msg_clone.opcode {
                     OpCode::Continuation => todo!(),
-                    OpCode::Text => send_msg_to_clients(&msg, &crossword_clone, puzzle_num),
+                    OpCode::Text => handle_client_message(&msg, &crossword_clone, puzzle_num, user_id),
                     OpCode::Binary => todo!(),
                     OpCode::Reserved(_) => todo!(),
-                    OpCode::Close => trace!("Close"),
-                    OpCode::Ping => trace!("Ping"),
-                    OpCode::Pong => trace!("Pong"),
-                }
+                    OpCode::Close => {trace!("Close"); ChannelReply::Everyone(vec![msg])},
+                    OpCode::Ping => {trace!("Ping"); ChannelReply::Everyone(vec![msg])},
+                    OpCode::Pong => {trace!("Pong"); ChannelReply::Everyone(vec![msg])},
+                };
 
-                clients_clone
-                    .lock()
-                    .unwrap_or_else(|err| {
-                        warn!("This mutex is in a poisoned state, but we're attempting to send the clients messages anyway");
-                        err.into_inner()
-                    })
-                    .iter()
-                    .filter_map(|x| x.send( msg.clone() ).err())
-                    .for_each(drop);
+                match reply {
+                    ChannelReply::Everyone(msgs) => msgs.iter().for_each(|msg| send_to_clients(&clients_clone, msg)),
+                    ChannelReply::Origin(reply) => match origin {
+                        Some(origin) => if let Err(e) = origin.send(reply) {
+                            warn!("Could not reply to the client: {e}")
+                        },
+                        None => warn!("There is no client to reply to"),
+                    },
+                }
                 }
             
             info!("finishing");
@@ -1235,29 +3114,76 @@ impl PuzzleChannel {
 
 }
 
-fn send_msg_to_clients(msg: &Message, crossword_clone: &Arc<Mutex<Crossword>>, puzzle_num: i64) {
-    match String::from_utf8(msg.clone().body) {
-        Ok(client_data) => {
-            info!("decoded incoming data: {}", client_data);
-            let incoming_data: Result<Cell, serde_json::Error>  = serde_json::from_str(&client_data);
-            match incoming_data {
-                Ok(deserialised) => {
-                    match crossword_clone.lock() {
-                        Ok(mut guard) => guard.update_cell(deserialised),
-                        Err(e)  => {
-                            warn!("puzzle {} is poisoned, but we're sending the data anyway", puzzle_num);
-                            e.into_inner().update_cell(deserialised)
-                        }
-                    }
-                
-                },
-                Err(_) => {
-                    warn!("cannot deserialise into cell data")
-                },
-            }
-        
+/// Works out what to do with a message from a client. Cell updates are
+/// applied and passed on to everyone; checks are answered to the client
+/// that asked, and reveals send the filled in squares to everyone. Anything
+/// that can't be read, or is for a square outside the grid, is refused.
+fn handle_client_message(msg: &Message, crossword_clone: &Arc<Mutex<Crossword>>, puzzle_num: i64, user_id: Option<i64>) -> ChannelReply {
+    let client_data = match String::from_utf8(msg.clone().body) {
+        Ok(client_data) => client_data,
+        Err(e) => {
+            warn!("tried to decode, but there was an error: {}", e);
+            return channel_error("invalid_request", "The message could not be decoded")
+        },
+    };
+    info!("decoded incoming data: {}", client_data);
+    let value: serde_json::Value = match serde_json::from_str(&client_data) {
+        Ok(value) => value,
+        Err(_) => {
+            warn!("cannot deserialise client data");
+            return channel_error("invalid_request", "The message is not json")
+        },
+    };
+
+    let mut crossword = crossword_clone.lock().unwrap_or_else(|e| {
+        warn!("puzzle {} is poisoned, but we're sending the data anyway", puzzle_num);
+        e.into_inner()
+    });
+
+    if value.get("op").is_some() {
+        let request: SolveRequest = match serde_json::from_value(value) {
+            Ok(request) => request,
+            Err(e) => {
+                warn!("cannot deserialise into a solve request: {e}");
+                return channel_error("invalid_request", "The request should be a check or reveal of a square, a clue or the grid")
+            },
+        };
+        return match solve_crossword(puzzle_num, &mut crossword, &request, user_id) {
+            Ok(SolveOutcome::Checked(result)) => {
+                let body = serde_json::to_string(&CheckReply { op: "check", result: &result }).unwrap_or_default();
+                ChannelReply::Origin(Message::new_from_str(&body))
+            },
+            Ok(SolveOutcome::Revealed(cells)) => ChannelReply::Everyone(cell_messages(&cells)),
+            Err(e) => {
+                if let SolveError::Server(reason) = &e {
+                    error!("Could not solve puzzle {puzzle_num}: {reason}");
+                }
+                channel_error(e.code(), e.message())
+            },
+        }
+    }
+
+    let deserialised: Cell = match serde_json::from_value(value) {
+        Ok(deserialised) => deserialised,
+        Err(_) => {
+            warn!("cannot deserialise into cell data");
+            return channel_error("invalid_cell", "The update is not a cell")
+        },
+    };
+    // the square is sent on as it was stored, e.g. with combining marks composed.
+    match crossword.update_cell(deserialised.clone()) {
+        Ok(clues) => {
+            trace!("updated clues {}", clues.join(", "));
+            ChannelReply::Everyone(cell_messages(&[deserialised]))
+        },
+        Err(CellError::NotInGrid) => {
+            warn!("ignoring an update to a square outside puzzle {}", puzzle_num);
+            channel_error("invalid_cell", "The square is not part of the grid")
+        },
+        Err(CellError::Value(e)) => {
+            warn!("ignoring an update to puzzle {}: {e}", puzzle_num);
+            channel_error("invalid_cell", &format!("The square can't be filled in: {e}"))
         },
-        Err(e) => warn!("tried to decode, but there was an error: {}", e),
     }
 }
 
@@ -1278,7 +3204,7 @@ impl Drop for PuzzleChannel {
 }
 
 
-fn route_stream_to_puzzle(puzzle_channel: Arc<Mutex<PuzzleChannel>>,stream: TcpStream, tera: Arc<Tera>) -> Result<(), HandlerError>{
+fn route_stream_to_puzzle(puzzle_channel: Arc<Mutex<PuzzleChannel>>,stream: TcpStream, tera: Arc<Tera>, user_id: Option<i64>) -> Result<(), HandlerError>{
 
     let stream_clone = match stream.try_clone(){
         Ok(stream) => stream,
@@ -1297,6 +3223,7 @@ fn route_stream_to_puzzle(puzzle_channel: Arc<Mutex<PuzzleChannel>>,stream: TcpS
 
     let sender = Arc::new(sender);
     let sender_clone = sender.clone();
+    let origin = sender.clone();
     {
         match puzzle_channel.lock() {
             Ok(mut guard) => guard.add_new_client(sender),
@@ -1325,7 +3252,7 @@ fn route_stream_to_puzzle(puzzle_channel: Arc<Mutex<PuzzleChannel>>,stream: TcpS
 
     match THREADPOOL.execute( move || {
         loop {            
-            match heartbeat_channel_wide_sender.send(Message::ping_message()){
+            match heartbeat_channel_wide_sender.send(ChannelMessage::new(Message::ping_message(), None, None)){
                 Ok(_) => trace!("Server heart beat"),
                 Err(_) => {
                     warn!("failed to send heart beat");
@@ -1438,8 +3365,8 @@ fn route_stream_to_puzzle(puzzle_channel: Arc<Mutex<PuzzleChannel>>,stream: TcpS
                                 warn!("Cannot handle op code {}", x);
                                 Ok(())
                             },
-                            OpCode::Text => channel_wide_sender.send(msg),
-                            OpCode::Binary => channel_wide_sender.send(msg),
+                            OpCode::Text => channel_wide_sender.send(ChannelMessage::new(msg, Some(origin.clone()), user_id)),
+                            OpCode::Binary => channel_wide_sender.send(ChannelMessage::new(msg, Some(origin.clone()), user_id)),
                         }
                     },
                     Err(_err) => {
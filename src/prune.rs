@@ -1,4 +1,6 @@
 use clap::{Args, ArgAction, Parser, Subcommand};
+use cw_grid_server::crossword::text::printable;
+use cw_grid_server::db::TokenScope;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -18,6 +20,73 @@ enum Commands {
     BatchRestore(BatchArgs),
     /// Permenantly delete all puzzles that have been soft deleted.
     BatchDelete(BatchArgs),
+    /// Create a personal API token for a user.
+    TokenCreate(TokenCreateArgs),
+    /// List a user's API tokens.
+    TokenList(UserArgs),
+    /// Revoke an API token.
+    TokenRevoke(TokenRevokeArgs),
+    /// Grant a user admin rights, or take them away.
+    SetAdmin(SetAdminArgs),
+    /// Rebuild the clue search index from the puzzle files.
+    Reindex,
+    /// Check every puzzle's crossword and report any problems.
+    Validate,
+    /// Print a puzzle's grid and clues.
+    Show(ShowArgs),
+}
+
+#[derive(Args)]
+struct UserArgs {
+    #[arg(short, long)]
+    /// The username of the user who owns the tokens.
+    username: String,
+}
+
+#[derive(Args)]
+struct TokenCreateArgs {
+    #[arg(short, long)]
+    /// The username of the user the token is for.
+    username: String,
+    #[arg(short, long)]
+    /// A name to help recognise the token later.
+    name: String,
+    #[arg(short, long, value_delimiter = ',')]
+    /// Comma separated scopes (read, upload, delete). Omit for an unrestricted token.
+    scopes: Option<Vec<TokenScope>>,
+}
+
+#[derive(Args)]
+struct TokenRevokeArgs {
+    #[arg(short, long, action=ArgAction::SetTrue)]
+    /// Perform the operation. (default behaviour is a dry run)
+    live: bool,
+    #[arg(short, long)]
+    /// The ID of the token to revoke.
+    id: i64
+}
+
+#[derive(Args)]
+struct SetAdminArgs {
+    #[arg(short, long, action=ArgAction::SetTrue)]
+    /// Perform the operation. (default behaviour is a dry run)
+    live: bool,
+    #[arg(short, long)]
+    /// The username of the user to change.
+    username: String,
+    #[arg(short, long, action=ArgAction::SetTrue)]
+    /// Take admin rights away instead of granting them.
+    revoke: bool,
+}
+
+#[derive(Args)]
+struct ShowArgs {
+    #[arg(short, long)]
+    /// The ID of the puzzle to show.
+    id: i64,
+    #[arg(short, long, action=ArgAction::SetTrue)]
+    /// Pick out numbers and letters with terminal colours.
+    colour: bool,
 }
 
 #[derive(Args)]
@@ -47,6 +116,13 @@ fn main() {
         Commands::BatchDelete(args) => batch_delete(args),
         Commands::Restore(args) => restore(args),
         Commands::Delete(args) => delete(args),
+        Commands::TokenCreate(args) => token_create(args),
+        Commands::TokenList(args) => token_list(args),
+        Commands::TokenRevoke(args) => token_revoke(args),
+        Commands::SetAdmin(args) => set_admin(args),
+        Commands::Reindex => reindex(),
+        Commands::Validate => validate(),
+        Commands::Show(args) => show(args),
     }
 }
 
@@ -140,4 +216,155 @@ fn find_puzzle(args: &SingleArgs) -> Option<cw_grid_server::db::PuzzleDbData> {
         } 
     };
     Some(puzzle)
-}
\ No newline at end of file
+}
+
+fn find_user(username: &str) -> Option<cw_grid_server::db::User> {
+    match cw_grid_server::db::get_user(username) {
+        Ok(user) => Some(user),
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            println!("No user called {}", username);
+            None
+        },
+        Err(e) => {
+            eprintln!("{e}");
+            None
+        }
+    }
+}
+
+fn token_create(args: &TokenCreateArgs) {
+    let user = match find_user(&args.username) {
+        Some(value) => value,
+        None => return,
+    };
+
+    match cw_grid_server::db::create_api_token(user.id, &args.name, args.scopes.as_deref()) {
+        Ok((info, token)) => {
+            println!("Created {:?}", info);
+            println!("Token (this will not be shown again): {}", token);
+        },
+        Err(e) => eprintln!("{e}"),
+    }
+}
+
+fn token_list(args: &UserArgs) {
+    let user = match find_user(&args.username) {
+        Some(value) => value,
+        None => return,
+    };
+
+    let tokens = cw_grid_server::db::get_api_tokens(user.id).unwrap();
+    tokens.iter().for_each(|el| {
+        println!("{:?}",el);
+    });
+}
+
+fn token_revoke(args: &TokenRevokeArgs) {
+    match args.live {
+        true => println!("Starting revocation"),
+        false => println!("This is a dry run"),
+    }
+    let token = match cw_grid_server::db::get_api_token(args.id) {
+        Ok(token) => token,
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            println!("No token with id {}", args.id);
+            return
+        },
+        Err(e) => {
+            eprintln!("{e}");
+            return
+        }
+    };
+
+    println!("Revoking {:?}",token);
+    if args.live {
+        cw_grid_server::db::revoke_api_token(args.id).unwrap();
+    }
+    match args.live {
+        true => println!("Completed revocation"),
+        false => println!("Finished dry run"),
+    }
+}
+
+fn set_admin(args: &SetAdminArgs) {
+    match args.live {
+        true => println!("Starting admin change"),
+        false => println!("This is a dry run"),
+    }
+    let user = match find_user(&args.username) {
+        Some(value) => value,
+        None => return,
+    };
+
+    match args.revoke {
+        true => println!("Removing admin rights from {:?}",user),
+        false => println!("Granting admin rights to {:?}",user),
+    }
+    if args.live {
+        cw_grid_server::db::set_admin(user.id, !args.revoke).unwrap();
+    }
+    match args.live {
+        true => println!("Completed admin change"),
+        false => println!("Finished dry run"),
+    }
+}
+
+fn reindex() {
+    println!("Starting reindex");
+    match cw_grid_server::db::reindex_clues() {
+        Ok(count) => println!("Indexed the clues of {} puzzles", count),
+        Err(e) => eprintln!("{e}"),
+    }
+}
+
+fn validate() {
+    let ids = match cw_grid_server::db::get_puzzle_ids() {
+        Ok(ids) => ids,
+        Err(e) => {
+            eprintln!("{e}");
+            return
+        }
+    };
+
+    let mut invalid = 0;
+    for id in &ids {
+        match cw_grid_server::db::get_puzzle(id) {
+            Ok(Some(cw)) => {
+                if let Err(errors) = cw.validate() {
+                    invalid += 1;
+                    println!("Puzzle {} has {} problems:", id, errors.len());
+                    errors.iter().for_each(|el| println!("    {}", el));
+                }
+            },
+            Ok(None) => println!("Puzzle {} has no crossword data", id),
+            Err(e) => println!("Puzzle {} could not be read: {}", id, e),
+        }
+    }
+    println!("Checked {} puzzles, {} were not valid", ids.len(), invalid);
+}
+
+fn show(args: &ShowArgs) {
+    let data = match cw_grid_server::db::get_puzzle_db(&args.id) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Puzzle {} could not be found: {}", args.id, e);
+            return
+        }
+    };
+    match cw_grid_server::db::get_puzzle(&args.id) {
+        Ok(Some(cw)) => {
+            let name = printable(&data.name);
+            match &cw.metadata().author {
+                Some(author) => println!("{} by {}\n", name, printable(author)),
+                None => println!("{}\n", name),
+            }
+            let options = cw_grid_server::crossword::TextOptions { colour: args.colour, ..Default::default() };
+            match cw.to_text(&options) {
+                Ok(text) => print!("{}", text),
+                Err(e) => eprintln!("Puzzle {} could not be drawn: {}", args.id, e),
+            }
+        },
+        Ok(None) => println!("Puzzle {} has no crossword data", args.id),
+        Err(e) => eprintln!("Puzzle {} could not be read: {}", args.id, e),
+    }
+}
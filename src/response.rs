@@ -233,6 +233,15 @@ impl ResponseBuilder  {
         self
     }
 
+    /// Builds a response with a body that isn't text, such as a download.
+    pub fn build_binary(&mut self, content: &[u8], content_type: &str) -> Vec<u8> {
+        self.set_content(String::new(), content_type);
+        self.unique_headers.insert("Content-Length".to_string(), format!("{}", content.len()));
+        let mut response = self.build().into_bytes();
+        response.extend_from_slice(content);
+        response
+    }
+
     pub fn add_cookie(&mut self, cookie: SetCookie<String>) -> & mut Self {
         
         let cookie = ("Set-Cookie".to_string(), format!("{cookie}"));
@@ -312,6 +321,22 @@ impl<T: Display> SetCookie<T> {
         self.expires = Some(expires);
         self
     }
+    pub fn set_http_only(&mut self, http_only: bool) -> &Self {
+        self.http_only = Some(http_only);
+        self
+    }
+    pub fn set_secure(&mut self, secure: bool) -> &Self {
+        self.secure = Some(secure);
+        self
+    }
+    pub fn set_same_site(&mut self, same_site: SameSite) -> &Self {
+        self.same_site = Some(same_site);
+        self
+    }
+    pub fn set_path(&mut self, path: &str) -> &Self {
+        self.path = Some(path.to_string());
+        self
+    }
 }
 
 impl<T: Display> Display for SetCookie<T> {
@@ -346,11 +371,71 @@ impl<T: Display> Display for SetCookie<T> {
     }
 }
 
+/// A single name-value pair from a `Cookie` request header.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+}
+
+/// The cookies a client sent in its `Cookie` header, in the order they were sent.
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct Cookies(Vec<Cookie>);
+
+impl Cookies {
+    /// Parses the value of a `Cookie` header. Pairs that are missing an `=` or
+    /// a name are skipped rather than rejected, and surrounding whitespace and
+    /// double quotes are removed from values. Everything after the first `=`
+    /// is kept, so base64 values are not truncated.
+    pub fn parse(header: &str) -> Self {
+        let cookies = header
+            .split(';')
+            .filter_map(|pair| {
+                let (name, value) = pair.split_once('=')?;
+                let name = name.trim();
+                if name.is_empty() {
+                    return None
+                }
+                let value = value.trim();
+                let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                    Some(unquoted) => unquoted,
+                    None => value,
+                };
+                Some(Cookie { name: name.to_string(), value: value.to_string() })
+            })
+            .collect();
+        Cookies(cookies)
+    }
+
+    pub fn from_headers(headers: &HashMap<String, String>) -> Self {
+        headers.get("Cookie").map(|header| Self::parse(header)).unwrap_or_default()
+    }
+
+    /// The value of the first cookie with this name. Browsers send the cookie
+    /// with the most specific path first when names are duplicated.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter().find(|c| c.name == name).map(|c| c.value.as_str())
+    }
+
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.0.iter().filter(move |c| c.name == name).map(|c| c.value.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
     use chrono::{Duration, NaiveDate};
+    use super::Cookies;
     use super::SameSite;
     use super::SetCookie;
     use super::StatusCode;
@@ -515,4 +600,62 @@ mod tests {
         assert_eq!(&response,expected)
     }
 
+    #[test]
+    fn test_parse_cookies() {
+        let cookies = Cookies::parse("a=b; c=d");
+        assert_eq!(cookies.get("a"), Some("b"));
+        assert_eq!(cookies.get("c"), Some("d"));
+        assert_eq!(cookies.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_cookie_value_with_equals() {
+        let cookies = Cookies::parse("session-id=YWJj==; user=x");
+        assert_eq!(cookies.get("session-id"), Some("YWJj=="));
+    }
+
+    #[test]
+    fn test_parse_cookie_without_equals() {
+        let cookies = Cookies::parse("broken; a=b");
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies.get("a"), Some("b"));
+    }
+
+    #[test]
+    fn test_parse_cookie_without_name() {
+        let cookies = Cookies::parse("=b;;  ; a=");
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies.get("a"), Some(""));
+    }
+
+    #[test]
+    fn test_parse_quoted_cookie() {
+        let cookies = Cookies::parse(" a = \"b c\" ;d=\"");
+        assert_eq!(cookies.get("a"), Some("b c"));
+        assert_eq!(cookies.get("d"), Some("\""));
+    }
+
+    #[test]
+    fn test_parse_duplicate_cookies() {
+        let cookies = Cookies::parse("a=1; a=2");
+        assert_eq!(cookies.get("a"), Some("1"));
+        assert_eq!(cookies.get_all("a").collect::<Vec<&str>>(), vec!["1", "2"]);
+    }
+
+    #[test]
+    fn test_parse_empty_cookie_header() {
+        assert!(Cookies::parse("").is_empty());
+    }
+
+    #[test]
+    fn test_cookie_attribute_serialisation() {
+        let mut cookie = SetCookie::new("id".to_string(), "a3fWa".to_string());
+        cookie.set_http_only(true);
+        cookie.set_path("/");
+        cookie.set_same_site(SameSite::Lax);
+        cookie.set_secure(true);
+
+        assert_eq!(cookie.to_string(), "id=a3fWa; HttpOnly; Path=/; SameSite=Lax; Secure");
+    }
+
 }
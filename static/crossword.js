@@ -45,6 +45,8 @@ class CrosswordGrid extends HTMLElement {
 
                 this.scale = 30
                 this.activeClue = null;
+                // a rebus waiting for the server, and the text to put back if it's refused
+                this.pendingRebus = null;
                 this.connect()
                 this.fetchAllData().then(() => {
                     this.setUpGridListener()
@@ -74,6 +76,7 @@ class CrosswordGrid extends HTMLElement {
             .then(data => {
                 this.data = data
                 this.cells = new Map();
+                this.clues = new Map();
                 this.downHintsData = []
                 this.acrossHintsData = []
         
@@ -120,6 +123,16 @@ class CrosswordGrid extends HTMLElement {
                         cell = this.activeClue.backwardCellIterator.next().value;
                         this.activeClue.setActiveCell(cell);
                         break;
+                    case "Insert":
+                        // a rebus: several letters in one square
+                        let letters = window.prompt("Letters for this square")
+                        if (letters !== null) {
+                            let rebusCell = this.activeClue.getActiveCell()
+                            this.pendingRebus = { cell: rebusCell, previous: rebusCell.text }
+                            rebusCell.updateText(letters.trim().toUpperCase() || " ");
+                            this.ws.send(rebusCell.getCellData())
+                        }
+                        break;
                     case "ArrowRight":
                     case 'ArrowDown':
                         cell = this.activeClue.forwardCellIterator.next().value;
@@ -131,7 +144,7 @@ class CrosswordGrid extends HTMLElement {
                         this.activeClue.setActiveCell(cell);
                         break;
                     default:
-                        if (/^[a-zA-Z]$/.test(key.key)) {
+                        if (/^\p{L}\p{M}*$/u.test(key.key)) {
                             this.activeClue.getActiveCell().updateText(key.key);
                             this.ws.send(this.activeClue.getActiveCell().getCellData())
                             cell = this.activeClue.forwardCellIterator.next().value;
@@ -148,7 +161,11 @@ class CrosswordGrid extends HTMLElement {
     }
 
     drawGrid = () => {
-
+        let allClues = { ...this.data.across, ...this.data.down }
+        this.linkedFrom = new Map()
+        for (let clueName in allClues) {
+            (allClues[clueName].linked || []).forEach(linkedName => this.linkedFrom.set(linkedName, clueName))
+        }
 
         for (let incomingClueName in this.data.across) {
             let incomingClueData = this.data.across[incomingClueName];
@@ -159,11 +176,69 @@ class CrosswordGrid extends HTMLElement {
             let incomingClueData = this.data.down[incomingClueName];
             this.handleIncomingClue(incomingClueName, this.downHintsData, incomingClueData);
         }
+
+        // a linked clue is typed into as one clue running through all its entries
+        for (let clueName in allClues) {
+            let head = this.clues.get(clueName)
+            for (let linkedName of allClues[clueName].linked || []) {
+                let linked = this.clues.get(linkedName)
+                if (!head || !linked) {
+                    continue
+                }
+                head.cells.push(...linked.cells)
+                linked.cells.forEach(cell => {
+                    cell.cluesPartof = [...new Set(cell.cluesPartof.map(x => x === linked ? head : x))]
+                })
+            }
+            if (head && allClues[clueName].enumeration) {
+                this.drawWordBreaks(head, allClues[clueName].enumeration.breaks)
+            }
+        }
+
+        for (let square of this.data.styles || []) {
+            let cell = this.cells.get(`${square.x},${square.y}`)
+            if (cell) {
+                cell.div.classList.toggle("cell-circled", !!square.circled)
+                cell.div.classList.toggle("cell-shaded", !!square.shaded)
+                cell.div.classList.toggle("bar-right", !!square.bar_right)
+                cell.div.classList.toggle("bar-bottom", !!square.bar_bottom)
+            }
+        }
+    }
+
+    // marks the breaks between words, and hyphens, on the edge between two squares
+    drawWordBreaks = (clue, breaks) => {
+        for (let wordBreak of breaks) {
+            let before = clue.cells[wordBreak.after - 1]
+            let after = clue.cells[wordBreak.after]
+            if (!before || !after) {
+                continue
+            }
+            let side = null
+            if (after.coords.x === before.coords.x + 1 && after.coords.y === before.coords.y) {
+                side = "right"
+            } else if (after.coords.x === before.coords.x && after.coords.y === before.coords.y + 1) {
+                side = "bottom"
+            }
+            if (side === null) {
+                continue
+            }
+            if (wordBreak.kind === "hyphen") {
+                let mark = document.createElement('div')
+                mark.classList.add(`hyphen-${side}`)
+                before.div.appendChild(mark)
+            } else {
+                before.div.classList.add(`word-break-${side}`)
+            }
+        }
     }
 
     handleUpdateTextFromServer = (new_cell) => {
         let key = `${new_cell.x},${new_cell.y}`
         let cell = this.cells.get(key)
+        if (this.pendingRebus !== null && this.pendingRebus.cell === cell) {
+            this.pendingRebus = null
+        }
         cell.text = new_cell.c
         cell.updateText(new_cell.c)
     }
@@ -196,8 +271,17 @@ class CrosswordGrid extends HTMLElement {
     }
 
     handleIncomingClue = (incomingClueName, clueDirection, incomingClueData) => {
-        clueDirection.push({ name: incomingClueName, value: incomingClueData["hint"] });
+        let hint = incomingClueData["hint"]
+        let linkedFrom = this.linkedFrom.get(incomingClueName)
+        if (linkedFrom && hint.trim() === "") {
+            hint = `See ${linkedFrom.slice(0, -1)}`
+        } else if (incomingClueData.enumeration) {
+            hint = `${hint} (${incomingClueData.enumeration.text})`
+        }
+        let name = [incomingClueName, ...(incomingClueData.linked || [])].map(x => x.slice(0, -1)).join("/")
+        clueDirection.push({ name: name, value: hint });
         let clue = new Clue(incomingClueName);
+        this.clues.set(incomingClueName, clue);
         for (let incomingCellData in incomingClueData.cells) {
             let cellData = incomingClueData.cells[incomingCellData];
             let key = `${cellData.x},${cellData.y}`;
@@ -284,6 +368,18 @@ class CrosswordGrid extends HTMLElement {
 
         this.ws.addEventListener("message", (event) => {
             let message = JSON.parse(event.data);
+            if (message.error) {
+                console.warn(message.message)
+                if (message.error === "invalid_cell" && this.pendingRebus !== null) {
+                    this.pendingRebus.cell.updateText(this.pendingRebus.previous)
+                    this.pendingRebus = null
+                    window.alert(message.message)
+                }
+                return
+            }
+            if (message.op) {
+                return
+            }
             this.handleUpdateTextFromServer(message)
         });
 
@@ -373,6 +469,7 @@ class Cell {
     updateText(text) {
         this.text = text
         this.cell_text.textContent = this.text
+        this.cell_text.classList.toggle("cell-rebus", [...this.text.trim()].length > 1)
     }
 
     getCellData() {